@@ -0,0 +1,78 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use wami::{index::Index, Lake, Search, Template};
+
+// Benchmark of the loading, indexing and scoring of a synthetic lake, sequential and in parallel.
+// Run it with: cargo bench --bench lake
+
+const TEMPLATE_COUNT: usize = 10_000;
+
+// Write a lake with generated templates to a temp dir.
+// The words are repeated in different orders, so the terms have different frequencies like in a real lake.
+fn create_lake() -> PathBuf {
+    let words = [
+        "dns", "scanner", "network", "smb", "windows", "brute", "force", "web", "sql",
+        "injection", "zone", "transfer", "password", "exploit", "proxy", "wireless",
+    ];
+
+    let mut out_dir = std::env::temp_dir();
+    out_dir.push(format!("wami-bench-lake-{}", std::process::id()));
+    fs::create_dir_all(&out_dir).unwrap();
+    for i in 0..TEMPLATE_COUNT {
+        let tags: Vec<&str> = (0..4).map(|j| words[(i * 7 + j * 3) % words.len()]).collect();
+        let description: Vec<&str> = (0..20).map(|j| words[(i * 13 + j * 5) % words.len()]).collect();
+        let yaml = format!(
+            "id: tool{i}\ntitle: Tool {i}\ntags: [{}]\ndescription: {}\nreferences: [https://example.com/tool{i}]\nwhy_not: []\n",
+            tags.join(", "),
+            description.join(" "),
+        );
+        fs::write(out_dir.join(format!("tool{}.yaml", i)), yaml).unwrap();
+    }
+    out_dir
+}
+
+fn load(in_dir: &Path) -> Vec<Template> {
+    Lake::load_templates_from_dir(in_dir).unwrap().0
+}
+
+// Run a function on a thread pool with the given number of threads, 0 is one thread per core.
+fn with_threads<T: Send>(in_threads: usize, in_function: impl FnOnce() -> T + Send) -> T {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(in_threads)
+        .build()
+        .unwrap()
+        .install(in_function)
+}
+
+fn bench_lake(c: &mut Criterion) {
+    let lake_dir: PathBuf = create_lake();
+    let templates: Vec<Template> = load(&lake_dir);
+    let index: Index = Index::new(&templates);
+    let search: Search = Search::builder()
+        .words(["dns", "zone", "transfr"])
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("lake");
+    group.sample_size(10);
+    for (name, threads) in [("sequential", 1), ("parallel", 0)] {
+        group.bench_function(BenchmarkId::new("load", name), |b| {
+            b.iter(|| with_threads(threads, || load(&lake_dir)))
+        });
+        group.bench_function(BenchmarkId::new("index", name), |b| {
+            b.iter(|| with_threads(threads, || Index::new(&templates)))
+        });
+        group.bench_function(BenchmarkId::new("score", name), |b| {
+            b.iter(|| with_threads(threads, || index.score(&search)))
+        });
+    }
+    group.finish();
+
+    fs::remove_dir_all(&lake_dir).unwrap();
+}
+
+criterion_group!(benches, bench_lake);
+criterion_main!(benches);
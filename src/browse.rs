@@ -0,0 +1,497 @@
+use wami::{
+    index::Index,
+    search::{synonyms::Synonyms, NameMatch, Search, Weights},
+    template::Template,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ratatui::{
+    backend::CrosstermBackend,
+    crossterm::{
+        event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+        execute,
+        terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    },
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph, Wrap},
+    Frame, Terminal,
+};
+use std::io::{self, Stderr, Write};
+
+// This is the interactive browser of the lake.
+// The hits are ranked again with the index of the lake on every key press,
+// so the user can refine the search without running wami again.
+// The words are parsed with the query language, so fields, phrases and filters work as well.
+// The terminal user interface is drawn on stderr, so the selection printed to stdout
+// can be used in a shell, e.g. firefox "$(wami -i)".
+
+// The help line at the bottom of the browser.
+const HELP: &str = "↑↓ select  ←→ alternative  Tab open alternative  Shift-Tab back  Enter print reference  Ctrl-O print id  Ctrl-Y copy reference  Esc quit";
+
+// What the user picked, when the browser was closed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selection {
+    Reference(String),
+    Id(String),
+}
+
+pub struct Browser {
+    templates: Vec<Template>, // All templates of the lake, in the order of the index.
+    index: Index,
+    weights: Weights,
+    synonyms: Synonyms,
+    input: String,            // The search of the user.
+    hits: Vec<usize>,         // The positions of the hits in templates, the best hit first.
+    selected: usize,          // The selected hit.
+    alternative: usize,       // The selected why_not entry of the selected hit.
+    history: Vec<String>,     // The searches before the user opened an alternative.
+    status: String,           // A parse error of the search or a notice for the user.
+}
+
+impl Browser {
+    pub fn new(
+        in_templates: Vec<Template>,
+        in_index: Index,
+        in_weights: Weights,
+        in_synonyms: Synonyms,
+        in_input: &str,
+    ) -> Browser {
+        let mut out_browser = Browser {
+            templates: in_templates,
+            index: in_index,
+            weights: in_weights,
+            synonyms: in_synonyms,
+            input: in_input.to_owned(),
+            hits: Vec::new(),
+            selected: 0,
+            alternative: 0,
+            history: Vec::new(),
+            status: String::new(),
+        };
+        out_browser.update();
+        out_browser
+    }
+
+    // Rank the templates with the current input.
+    // If the input can not be parsed yet, e.g. an open quote, the last hits are kept.
+    fn update(&mut self) {
+        self.status.clear();
+        self.selected = 0;
+        self.alternative = 0;
+
+        if self.input.trim().is_empty() {
+            self.hits.clear();
+            return;
+        }
+
+        let mut search = Search::new_empty();
+        search.weights_set(self.weights.clone());
+        search.synonyms_set(self.synonyms.clone());
+        if let Err(err) = search.query_set(&self.input) {
+            self.status = err;
+            return;
+        }
+        // A query like "-tag:windows" only filters, so every template which passes it is a hit.
+        // Such an input is no name of a tool, so it is not searched as a name.
+        let filter_only: bool = search.is_filter_only();
+        if !filter_only {
+            search.name_set(&self.input);
+        }
+
+        let scores = self.index.score(&search);
+        for (template, score) in self.templates.iter_mut().zip(scores) {
+            template.set_score(score);
+        }
+
+        let templates = &self.templates;
+        let mut hits: Vec<usize> = (0..templates.len())
+            .filter(|position| (filter_only || templates[*position].distance() > 0.0) && search.matches(&templates[*position]))
+            .collect();
+        hits.sort_by(|a, b| {
+            templates[*b]
+                .distance()
+                .partial_cmp(&templates[*a].distance())
+                .unwrap()
+                .then_with(|| templates[*a].id().cmp(templates[*b].id()))
+        });
+
+        // The named tool is the first hit, like in the list of the lake.
+        let name_match: Option<NameMatch> = search.find_name(templates);
+        if let Some(name_match) = name_match {
+            if let Some(named) = templates.iter().position(|template| template.id() == name_match.id) {
+                if search.matches(&templates[named]) {
+                    hits.retain(|position| *position != named);
+                    hits.insert(0, named);
+                    if !name_match.exact {
+                        self.status = format!("Did you mean {}?", name_match.id);
+                    }
+                }
+            }
+        }
+
+        self.hits = hits;
+    }
+
+    fn selected_template(&self) -> Option<&Template> {
+        self.hits.get(self.selected).map(|position| &self.templates[*position])
+    }
+
+    fn input_push(&mut self, in_char: char) {
+        self.input.push(in_char);
+        self.update();
+    }
+
+    fn input_pop(&mut self) {
+        self.input.pop();
+        self.update();
+    }
+
+    fn select_next(&mut self) {
+        if self.selected + 1 < self.hits.len() {
+            self.selected += 1;
+            self.alternative = 0;
+        }
+    }
+
+    fn select_previous(&mut self) {
+        if self.selected > 0 {
+            self.selected -= 1;
+            self.alternative = 0;
+        }
+    }
+
+    fn alternative_next(&mut self) {
+        let count = self.selected_template().map(|template| template.why_not().len()).unwrap_or(0);
+        if self.alternative + 1 < count {
+            self.alternative += 1;
+        }
+    }
+
+    fn alternative_previous(&mut self) {
+        self.alternative = self.alternative.saturating_sub(1);
+    }
+
+    // Search for the selected alternative of the selected hit.
+    // The search before is saved, so the user can go back.
+    fn open_alternative(&mut self) {
+        let alternative: Option<String> = self
+            .selected_template()
+            .and_then(|template| template.why_not().get(self.alternative).cloned());
+
+        match alternative {
+            Some(alternative) => {
+                self.history.push(self.input.to_owned());
+                self.input = alternative;
+                self.update();
+            }
+            None => self.status = "The selected tool has no alternatives.".to_string(),
+        }
+    }
+
+    // Go back to the search before the last alternative, returns false if there is none.
+    fn go_back(&mut self) -> bool {
+        match self.history.pop() {
+            Some(input) => {
+                self.input = input;
+                self.update();
+                true
+            }
+            None => false,
+        }
+    }
+
+    // The title of a why_not entry, if it is the id of a template in the lake.
+    fn alternative_title<'a>(&'a self, in_why_not: &'a str) -> &'a str {
+        self.templates
+            .iter()
+            .find(|template| template.id() == in_why_not)
+            .map(|template| template.title())
+            .unwrap_or(in_why_not)
+    }
+
+    // The first reference of the selected hit, or its id if it has no reference.
+    fn selected_reference(&self) -> Option<String> {
+        self.selected_template().map(|template| {
+            template
+                .references()
+                .first()
+                .cloned()
+                .unwrap_or_else(|| template.id().to_owned())
+        })
+    }
+
+    // Handle a key press, returns Some if the browser should be closed.
+    fn handle_key(&mut self, in_key: KeyEvent, out_terminal: &mut Stderr) -> Option<Option<Selection>> {
+        let control = in_key.modifiers.contains(KeyModifiers::CONTROL);
+        match in_key.code {
+            KeyCode::Char('c') if control => return Some(None),
+            KeyCode::Char('o') if control => {
+                return Some(self.selected_template().map(|template| Selection::Id(template.id().to_owned())))
+            }
+            KeyCode::Char('y') if control => {
+                if let Some(reference) = self.selected_reference() {
+                    match Browser::copy_to_clipboard(out_terminal, &reference) {
+                        Ok(()) => self.status = format!("Copied {}", reference),
+                        Err(err) => self.status = format!("Failed to copy: {}", err),
+                    }
+                }
+            }
+            KeyCode::Enter => return Some(self.selected_reference().map(Selection::Reference)),
+            // Escape goes back from an alternative, or closes the browser.
+            KeyCode::Esc if !self.go_back() => return Some(None),
+            KeyCode::BackTab => {
+                self.go_back();
+            }
+            KeyCode::Tab => self.open_alternative(),
+            KeyCode::Up => self.select_previous(),
+            KeyCode::Down => self.select_next(),
+            KeyCode::Left => self.alternative_previous(),
+            KeyCode::Right => self.alternative_next(),
+            KeyCode::Backspace => self.input_pop(),
+            KeyCode::Char(c) if !control => self.input_push(c),
+            _ => {}
+        }
+        None
+    }
+
+    // Copy a text to the clipboard of the terminal with the OSC 52 escape sequence.
+    // This works without a clipboard library and over ssh on the jump boxes.
+    fn copy_to_clipboard(out_terminal: &mut Stderr, in_text: &str) -> io::Result<()> {
+        write!(out_terminal, "{}", Browser::osc52(in_text))?;
+        out_terminal.flush()
+    }
+
+    fn osc52(in_text: &str) -> String {
+        format!("\x1b]52;c;{}\x07", STANDARD.encode(in_text))
+    }
+
+    // Draw the search line, the hits, the details of the selected hit and the help.
+    fn draw(&self, out_frame: &mut Frame) {
+        let [search_area, body_area, help_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .areas(out_frame.area());
+        let [list_area, preview_area] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(body_area);
+
+        // The search line with the status on the right.
+        let search_title = if self.status.is_empty() {
+            format!(" wami {} hits ", self.hits.len())
+        } else {
+            format!(" {} ", self.status)
+        };
+        out_frame.render_widget(
+            Paragraph::new(self.input.as_str()).block(Block::bordered().title(search_title)),
+            search_area,
+        );
+        out_frame.set_cursor_position((
+            search_area.x + 1 + self.input.chars().count() as u16,
+            search_area.y + 1,
+        ));
+
+        // The list of the hits.
+        let items: Vec<ListItem> = self
+            .hits
+            .iter()
+            .map(|position| {
+                let template = &self.templates[*position];
+                ListItem::new(Line::from(vec![
+                    Span::styled(template.id(), Style::default().fg(Color::Blue)),
+                    Span::raw(" "),
+                    Span::styled(template.title(), Style::default().fg(Color::Green)),
+                ]))
+            })
+            .collect();
+        let mut list_state = ListState::default().with_selected(Some(self.selected));
+        out_frame.render_stateful_widget(
+            List::new(items)
+                .block(Block::bordered().title(" hits "))
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+            list_area,
+            &mut list_state,
+        );
+
+        // The details of the selected hit with its alternatives.
+        let mut lines: Vec<Line> = Vec::new();
+        if let Some(template) = self.selected_template() {
+            for line in template.to_string(false).lines() {
+                lines.push(Line::raw(line.to_owned()));
+            }
+            if !template.why_not().is_empty() {
+                lines.push(Line::raw(""));
+                lines.push(Line::styled("why not", Style::default().fg(Color::Magenta)));
+                for (index, why_not) in template.why_not().iter().enumerate() {
+                    let style = if index == self.alternative {
+                        Style::default().add_modifier(Modifier::REVERSED)
+                    } else {
+                        Style::default().fg(Color::Green)
+                    };
+                    lines.push(Line::styled(format!("    {}", self.alternative_title(why_not)), style));
+                }
+            }
+        }
+        out_frame.render_widget(
+            Paragraph::new(lines)
+                .wrap(Wrap { trim: false })
+                .block(Block::bordered().title(" details ")),
+            preview_area,
+        );
+
+        out_frame.render_widget(
+            Paragraph::new(HELP).style(Style::default().fg(Color::DarkGray)),
+            help_area,
+        );
+    }
+
+    // Run the browser until the user picks a hit or quits.
+    pub fn run(mut self) -> io::Result<Option<Selection>> {
+        // The details are drawn by the browser, the colors of the console output would be escaped.
+        colored::control::set_override(false);
+
+        enable_raw_mode()?;
+        execute!(io::stderr(), EnterAlternateScreen)?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(io::stderr()))?;
+
+        let result = self.event_loop(&mut terminal);
+
+        // The terminal is restored, even if the loop failed.
+        disable_raw_mode()?;
+        execute!(io::stderr(), LeaveAlternateScreen)?;
+        terminal.show_cursor()?;
+        colored::control::unset_override();
+
+        result
+    }
+
+    fn event_loop(
+        &mut self,
+        out_terminal: &mut Terminal<CrosstermBackend<Stderr>>,
+    ) -> io::Result<Option<Selection>> {
+        loop {
+            out_terminal.draw(|frame| self.draw(frame))?;
+
+            if let Event::Key(key) = event::read()? {
+                // Only the press of a key counts, not the release.
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                if let Some(selection) = self.handle_key(key, &mut io::stderr()) {
+                    return Ok(selection);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn browser(in_input: &str) -> Browser {
+        let mut lake_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        lake_dir.push("tests/fixtures/lake");
+        let templates = crate::lake::Lake::load_templates_from_dir(&lake_dir).unwrap().0;
+        let index = Index::new(&templates);
+        Browser::new(templates, index, Weights::default(), Synonyms::default(), in_input)
+    }
+
+    fn hit_ids(in_browser: &Browser) -> Vec<&str> {
+        in_browser
+            .hits
+            .iter()
+            .map(|position| in_browser.templates[*position].id())
+            .collect()
+    }
+
+    #[test]
+    fn test_browser_updates_while_typing() {
+        let mut browser = browser("");
+        assert!(browser.hits.is_empty());
+
+        for c in "zone transfer".chars() {
+            browser.input_push(c);
+        }
+        assert_eq!(hit_ids(&browser)[..2], ["dnsrecon", "dig"]);
+
+        // An open quote can not be parsed yet, the hits stay.
+        browser.input_push(' ');
+        browser.input_push('"');
+        assert!(!browser.status.is_empty());
+        assert_eq!(hit_ids(&browser)[..2], ["dnsrecon", "dig"]);
+    }
+
+    #[test]
+    fn test_browser_filter_only_input() {
+        // Every template except the windows ones, sorted by id.
+        let without_windows = browser("-tag:windows");
+        assert_eq!(without_windows.hits.len(), without_windows.templates.len() - 2);
+        assert!(!hit_ids(&without_windows).contains(&"responder") && !hit_ids(&without_windows).contains(&"enum4linux"));
+        assert_eq!(hit_ids(&without_windows)[0], "dig");
+
+        // A required word ranks the hits as well.
+        let dns = browser("+tag:dns");
+        let mut dns_ids = hit_ids(&dns);
+        dns_ids.sort();
+        assert_eq!(dns_ids, ["dig", "dnsrecon", "gobuster", "host"]);
+        assert!(dns.status.is_empty());
+    }
+
+    #[test]
+    fn test_browser_named_tool_is_first() {
+        let browser = browser("nmpa");
+
+        assert_eq!(hit_ids(&browser)[0], "nmap");
+        assert_eq!(browser.status, "Did you mean nmap?");
+    }
+
+    #[test]
+    fn test_browser_open_alternative_and_go_back() {
+        let mut browser = browser("smbclient");
+        assert_eq!(browser.selected_template().unwrap().id(), "smbclient");
+
+        // smbclient names enum4linux as alternative.
+        browser.open_alternative();
+        assert_eq!(browser.input, "enum4linux");
+        assert_eq!(browser.selected_template().unwrap().id(), "enum4linux");
+
+        assert!(browser.go_back());
+        assert_eq!(browser.input, "smbclient");
+        assert!(!browser.go_back());
+    }
+
+    #[test]
+    fn test_browser_selection_stays_in_range() {
+        let mut browser = browser("tag:dns");
+        let count = browser.hits.len();
+
+        browser.select_previous();
+        assert_eq!(browser.selected, 0);
+        for _ in 0..count + 3 {
+            browser.select_next();
+        }
+        assert_eq!(browser.selected, count - 1);
+
+        // dnsrecon has a single alternative.
+        browser.alternative_next();
+        browser.alternative_next();
+        assert!(browser.alternative < browser.selected_template().unwrap().why_not().len().max(1));
+    }
+
+    #[test]
+    fn test_browser_selected_reference() {
+        let browser = browser("dig");
+
+        assert_eq!(browser.selected_reference(), Some("https://manned.org/dig".to_string()));
+        assert_eq!(browser.alternative_title("host"), "host - DNS lookup utility");
+        assert_eq!(browser.alternative_title("unknown"), "unknown");
+    }
+
+    #[test]
+    fn test_browser_osc52() {
+        assert_eq!(Browser::osc52("nmap"), "\x1b]52;c;bm1hcA==\x07");
+    }
+}
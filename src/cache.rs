@@ -0,0 +1,293 @@
+use crate::{error::{Result, WamiError}, index::Index, template::Template};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+// This is the persistent cache of the lake.
+// Parsing every yaml file and building the index takes a while on slow machines,
+// so the templates and the index are saved with bincode in the dir of the lakes.
+// The cache belongs to the git hashes of the lakes and to the modification times of their files.
+// If one of them changes, the cache is rebuilt on the next start.
+// The broken files of the lakes are saved as well, so their warnings are shown on every start and not only once.
+
+// Increase this version, if the format of the templates or the index or the tokenizer changes.
+const CACHE_VERSION: u32 = 4;
+
+// The name of the cache file in the dir of the lakes.
+const CACHE_FILE_NAME: &str = "index.bin";
+
+// The modification time and size of a yaml file of the lake.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    name: String,
+    modified: u128, // Nanoseconds since the unix epoch.
+    size: u64,
+}
+
+// A warning of the loading of the lakes, e.g. a template which can not be parsed.
+// The errors of wami can not be saved, so the file and the message are kept.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedWarning {
+    path: Option<PathBuf>, // The broken template, a warning without a path is a broken lake.
+    message: String,
+}
+
+impl From<&WamiError> for CachedWarning {
+    fn from(in_error: &WamiError) -> CachedWarning {
+        match in_error {
+            WamiError::TemplateParse { path, message } => CachedWarning {
+                path: Some(path.to_owned()),
+                message: message.to_owned(),
+            },
+            WamiError::LakeCorrupt(message) => CachedWarning {
+                path: None,
+                message: message.to_owned(),
+            },
+            error => CachedWarning {
+                path: None,
+                message: error.to_string(),
+            },
+        }
+    }
+}
+
+impl CachedWarning {
+    pub fn to_error(&self) -> WamiError {
+        match &self.path {
+            Some(path) => WamiError::TemplateParse {
+                path: path.to_owned(),
+                message: self.message.to_owned(),
+            },
+            None => WamiError::LakeCorrupt(self.message.to_owned()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Cache {
+    version: u32,
+    hash: String,          // The names, priorities and git hashes of the lakes from the config.yaml.
+    files: Vec<FileStamp>, // The files the cache was built from.
+    pub templates: Vec<Template>,
+    pub index: Index,
+    pub warnings: Vec<CachedWarning>, // The broken files, which were skipped when the cache was built.
+}
+
+impl Cache {
+    // Build a new cache from the templates of the lake and the warnings of its broken files.
+    pub fn new(in_hash: &str, in_files: Vec<FileStamp>, in_templates: Vec<Template>, in_warnings: &[WamiError]) -> Cache {
+        let index = Index::new(&in_templates);
+        Cache {
+            version: CACHE_VERSION,
+            hash: in_hash.to_owned(),
+            files: in_files,
+            templates: in_templates,
+            index,
+            warnings: in_warnings.iter().map(CachedWarning::from).collect(),
+        }
+    }
+
+    // The cache holds the merged templates of all lakes, so it is saved in the dir of the lakes.
+    pub fn get_cache_path(in_lakes_dir: &Path) -> PathBuf {
+        in_lakes_dir.join(CACHE_FILE_NAME)
+    }
+
+    // Get the stamps of the yaml files of several lakes.
+    // The name of every file starts with the name of its lake, so a file can not move unnoticed to another lake.
+    pub fn stamp_lake_dirs(in_lake_dirs: &[(&str, PathBuf)]) -> Vec<FileStamp> {
+        let mut out_stamps: Vec<FileStamp> = Vec::new();
+        for (name, lake_dir) in in_lake_dirs {
+            out_stamps.extend(Cache::stamp_lake_dir(lake_dir).into_iter().map(|stamp| FileStamp {
+                name: format!("{}/{}", name, stamp.name),
+                ..stamp
+            }));
+        }
+        out_stamps
+    }
+
+    // Get the stamps of all yaml files in the lake dir, sorted by name.
+    pub fn stamp_lake_dir(in_lake_dir: &Path) -> Vec<FileStamp> {
+        let mut out_stamps: Vec<FileStamp> = Vec::new();
+
+        if let Ok(entries) = fs::read_dir(in_lake_dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().map(|extension| extension != "yaml").unwrap_or(true) {
+                    continue;
+                }
+
+                if let Ok(metadata) = entry.metadata() {
+                    let modified: u128 = metadata
+                        .modified()
+                        .ok()
+                        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                        .map(|duration| duration.as_nanos())
+                        .unwrap_or(0);
+                    out_stamps.push(FileStamp {
+                        name: entry.file_name().to_string_lossy().to_string(),
+                        modified,
+                        size: metadata.len(),
+                    });
+                }
+            }
+        }
+
+        out_stamps.sort_by(|a, b| a.name.cmp(&b.name));
+        out_stamps
+    }
+
+    // Check if the cache still belongs to the lake.
+    pub fn is_valid(&self, in_hash: &str, in_files: &[FileStamp]) -> bool {
+        self.version == CACHE_VERSION && self.hash == in_hash && self.files == in_files
+    }
+
+    // Load the cache file, every error means there is no usable cache.
+    // The file is read at once, so a broken length in the file can not allocate more than the file size.
+    pub fn load(in_path: &Path) -> Option<Cache> {
+        let bytes: Vec<u8> = fs::read(in_path).ok()?;
+        bincode::deserialize(&bytes).ok()
+    }
+
+    // Save the cache file.
+    pub fn save(&self, in_path: &Path) -> Result<()> {
+        let file = File::create(in_path)?;
+        bincode::serialize_into(BufWriter::new(file), self)
+            .map_err(|err| WamiError::Io(io::Error::other(format!("Failed to save the index cache: {}", err))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::Search;
+
+    // Create an empty dir in the temp dir of the system for a test.
+    fn test_dir(in_name: &str) -> PathBuf {
+        let mut out_dir = std::env::temp_dir();
+        out_dir.push(format!("wami-cache-test-{}-{}", in_name, std::process::id()));
+        let _ = fs::remove_dir_all(&out_dir);
+        out_dir.push("lake");
+        fs::create_dir_all(&out_dir).unwrap();
+        out_dir
+    }
+
+    fn write_template(in_lake_dir: &Path, in_id: &str, in_tags: &str) {
+        let yaml = format!(
+            "id: {}\ntitle: {}\ntags: [{}]\ndescription: A tool.\nreferences: []\nwhy_not: []\n",
+            in_id, in_id, in_tags
+        );
+        fs::write(in_lake_dir.join(format!("{}.yaml", in_id)), yaml).unwrap();
+    }
+
+    #[test]
+    fn test_cache_path_is_in_the_lakes_dir() {
+        let path = Cache::get_cache_path(Path::new("/home/user/.config/wami/lakes"));
+
+        assert_eq!(path, PathBuf::from("/home/user/.config/wami/lakes/index.bin"));
+    }
+
+    #[test]
+    fn test_cache_save_and_load() {
+        let lake_dir = test_dir("load");
+        write_template(&lake_dir, "dig", "dns");
+        write_template(&lake_dir, "nmap", "scanner");
+        let stamps = Cache::stamp_lake_dir(&lake_dir);
+
+        let templates = vec![
+            Template::new("dig".to_string(), "dig".to_string(), vec!["dns".to_string()], String::new(), vec![], vec![]),
+            Template::new("nmap".to_string(), "nmap".to_string(), vec!["scanner".to_string()], String::new(), vec![], vec![]),
+        ];
+        let path = Cache::get_cache_path(&lake_dir);
+        Cache::new("abc", stamps.clone(), templates, &[]).save(&path).unwrap();
+
+        let cache = Cache::load(&path).unwrap();
+        assert!(cache.is_valid("abc", &stamps));
+        assert_eq!(cache.templates.len(), 2);
+
+        // The loaded index ranks like a new one.
+        let mut search = Search::new_empty();
+        search.tags_set(&vec!["dns".to_string()]);
+        let scores = cache.index.score(&search);
+        assert!(scores[0].total > 0.0);
+        assert_eq!(scores[1].total, 0.0);
+
+        fs::remove_dir_all(lake_dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_cache_invalid_after_changes() {
+        let lake_dir = test_dir("invalid");
+        write_template(&lake_dir, "dig", "dns");
+        let stamps = Cache::stamp_lake_dir(&lake_dir);
+        let cache = Cache::new("abc", stamps.clone(), vec![], &[]);
+
+        // A new hash of the lake.
+        assert!(!cache.is_valid("def", &stamps));
+
+        // A new file in the lake.
+        write_template(&lake_dir, "host", "dns");
+        assert!(!cache.is_valid("abc", &Cache::stamp_lake_dir(&lake_dir)));
+
+        // A changed file in the lake.
+        fs::remove_file(lake_dir.join("host.yaml")).unwrap();
+        write_template(&lake_dir, "dig", "dns, lookup");
+        assert!(!cache.is_valid("abc", &Cache::stamp_lake_dir(&lake_dir)));
+
+        fs::remove_dir_all(lake_dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_cache_stamps_of_several_lakes() {
+        let lake_dir = test_dir("several");
+        write_template(&lake_dir, "dig", "dns");
+        let stamps = Cache::stamp_lake_dirs(&[("public", lake_dir.clone()), ("internal", lake_dir.clone())]);
+
+        assert_eq!(stamps.len(), 2);
+        assert_eq!(stamps[0].name, "public/dig.yaml");
+        assert_eq!(stamps[1].name, "internal/dig.yaml");
+        // The same files in other lakes are another lake.
+        assert_ne!(stamps, Cache::stamp_lake_dirs(&[("internal", lake_dir.clone()), ("public", lake_dir.clone())]));
+
+        fs::remove_dir_all(lake_dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_cache_keeps_the_warnings() {
+        let lake_dir = test_dir("warnings");
+        write_template(&lake_dir, "dig", "dns");
+        let stamps = Cache::stamp_lake_dir(&lake_dir);
+        let warnings = vec![
+            WamiError::TemplateParse {
+                path: lake_dir.join("broken.yaml"),
+                message: "missing field `id`".to_string(),
+            },
+            WamiError::LakeCorrupt("Failed to read the lake dir".to_string()),
+        ];
+        let path = Cache::get_cache_path(&lake_dir);
+        Cache::new("abc", stamps, vec![], &warnings).save(&path).unwrap();
+
+        // A broken template is a warning on every start, also if the cache is used.
+        let cache = Cache::load(&path).unwrap();
+        let errors: Vec<String> = cache.warnings.iter().map(|warning| warning.to_error().to_string()).collect();
+        assert_eq!(errors, warnings.iter().map(|warning| warning.to_string()).collect::<Vec<_>>());
+        assert!(matches!(cache.warnings[0].to_error(), WamiError::TemplateParse { .. }));
+
+        fs::remove_dir_all(lake_dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_cache_load_missing_or_broken_file() {
+        let lake_dir = test_dir("broken");
+        let path = Cache::get_cache_path(&lake_dir);
+        assert!(Cache::load(&path).is_none());
+
+        fs::write(&path, b"not a cache").unwrap();
+        assert!(Cache::load(&path).is_none());
+
+        fs::remove_dir_all(lake_dir.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,407 @@
+use crate::{lake, output};
+use clap::{Args, Parser, Subcommand};
+use colored::Colorize;
+
+// These are the command-line arguments of wami.
+// Every task has its own subcommand, plain words without a subcommand are a search in the lake,
+// so `wami dns lookup` is the same as `wami search dns lookup`.
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "wami",
+    version,
+    author = "evait security GmbH, NxtTAB <wami@evait.de>",
+    about = about_text(),
+    after_help = example_text(),
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    // The search without the search subcommand.
+    #[command(flatten)]
+    pub search: SearchArgs,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Search the lake for tools, this is the default without a subcommand.
+    Search(SearchArgs),
+    /// Search GitHub for repositories or topics.
+    Github {
+        #[command(subcommand)]
+        command: GithubCommand,
+    },
+    /// Update, check, add, remove or list the lakes.
+    Lake {
+        #[command(subcommand)]
+        command: LakeCommand,
+    },
+    /// Read or change the config.yaml.
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Show every field of a single template of the lake.
+    Show {
+        /// The id of the template, e.g. nmap.
+        #[arg(value_name = "ID")]
+        id: String,
+    },
+    /// Walk the why not alternatives of a template and rank them with the search words.
+    Alternatives {
+        /// The id of the template, e.g. nmap.
+        #[arg(value_name = "ID")]
+        id: String,
+        /// The number of steps in the alternatives graph.
+        #[arg(short, long, value_name = "DEPTH", default_value_t = 1)]
+        depth: usize,
+        /// Rank the alternatives with these words, the query language can be used.
+        #[arg(value_name = "WORDS")]
+        words: Vec<String>,
+    },
+    /// Export the why not alternatives graph of the whole lake.
+    Graph {
+        /// The format of the graph, dot for graphviz or mermaid.
+        #[arg(long, value_name = "FORMAT", value_parser = ["dot", "mermaid"], default_value = "dot")]
+        format: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum GithubCommand {
+    /// Search the repositories of GitHub.
+    Search(GithubSearchArgs),
+    /// List the topics of GitHub, which contain the words.
+    Topics {
+        /// The words of the topics.
+        #[arg(value_name = "WORDS", required = true)]
+        words: Vec<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum LakeCommand {
+    /// Download the newest version of all enabled lakes or of a single lake.
+    Update {
+        /// The name of the lake, e.g. wami-templates.
+        #[arg(value_name = "NAME")]
+        name: Option<String>,
+    },
+    /// Restore the previous version of a lake with the hash it was downloaded with.
+    Rollback {
+        /// The name of the lake, it is only needed if there are several lakes.
+        #[arg(value_name = "NAME")]
+        name: Option<String>,
+    },
+    /// Pin a lake to a commit or a tag of its repository, it stays at this commit until it is unpinned.
+    Pin {
+        /// The commit or the tag, e.g. v1.2 or 3f8b349e0c4d7a2f5b6e1c9d8a7b6c5d4e3f2a1b. A tag is saved as its commit.
+        #[arg(value_name = "COMMIT")]
+        reference: String,
+        /// The name of the lake, it is only needed if there are several lakes.
+        #[arg(short, long, value_name = "NAME")]
+        lake: Option<String>,
+    },
+    /// Remove the pin of a lake and download the newest version of its branch.
+    Unpin {
+        /// The name of the lake, it is only needed if there are several lakes.
+        #[arg(value_name = "NAME")]
+        name: Option<String>,
+    },
+    /// Show the url, the version and the templates of every lake and check if they are outdated.
+    Status {
+        /// Do not check online if the lakes are outdated.
+        #[arg(short, long)]
+        offline: bool,
+    },
+    /// Download a lake from another url or path and use it from now on.
+    Use {
+        /// The url of the zip file of the lake, e.g. https://github.com/evait-security/wami-templates/archive/refs/heads/main.zip, or the path of a local dir or zip file.
+        #[arg(value_name = "URL")]
+        url: String,
+        /// The name of the lake, it is only needed if there are several lakes.
+        #[arg(short, long, value_name = "NAME")]
+        lake: Option<String>,
+    },
+    /// Add another lake, it is searched together with the other lakes.
+    Add {
+        /// The url of the zip file of the lake, a file:// url or the path of a local dir or zip file, e.g. ./my-templates. The files of a local dir are read directly.
+        #[arg(value_name = "LOCATION")]
+        location: String,
+        /// A unique name of the lake, e.g. internal. The default is the name of the repository, the dir or the zip file.
+        #[arg(short, long, value_name = "NAME")]
+        name: Option<String>,
+        /// If two lakes have a template with the same id, the template of the lake with the higher priority is used.
+        #[arg(short, long, value_name = "PRIORITY", default_value_t = 0, allow_negative_numbers = true)]
+        priority: i32,
+        /// The host of the archive, if it can not be told from the url. A self-hosted GitLab is found by its /-/archive/ path, a Gitea or Forgejo by its OWNER/REPOSITORY/archive/BRANCH.zip path.
+        #[arg(short, long, value_name = "BACKEND", value_parser = lake::backend::Backend::NAMES)]
+        backend: Option<String>,
+        /// The minisign public key of a signed lake, e.g. RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3. The signature is downloaded from the url of the zip file with the extension .minisig.
+        #[arg(long, value_name = "KEY")]
+        public_key: Option<String>,
+    },
+    /// Check the templates of the lakes against the hashes of their manifest and the signatures of their archives.
+    Verify {
+        /// The name of the lake, all enabled lakes are checked without it.
+        #[arg(value_name = "NAME")]
+        name: Option<String>,
+    },
+    /// Remove a lake and delete its templates.
+    Remove {
+        /// The name of the lake.
+        #[arg(value_name = "NAME")]
+        name: String,
+    },
+    /// List the id and the title of every template of the lakes.
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print the whole config.yaml or the value of a single key.
+    Get {
+        /// The key, e.g. lakes, lakes.wami-templates.hash, weights, weights.title or synonyms.
+        #[arg(value_name = "KEY")]
+        key: Option<String>,
+    },
+    /// Set the value of a key in the config.yaml.
+    Set {
+        /// The key, e.g. weights, weights.title, lakes.NAME.priority, lakes.NAME.enabled or lakes.NAME.public_key.
+        #[arg(value_name = "KEY")]
+        key: String,
+        /// The value, e.g. 3 for weights.title, title=3,tags=2 for weights or false for lakes.NAME.enabled.
+        #[arg(value_name = "VALUE")]
+        value: String,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct SearchArgs {
+    /// Search the names and the tags of the lake.
+    #[arg(value_name = "WORDS")]
+    pub words: Vec<String>,
+
+    /// The search all functionality will search throw all fields with the same search criteria.
+    #[arg(short = 's', long, value_name = "SEARCH_ALL", num_args = 1..)]
+    pub search_all: Vec<String>,
+
+    /// Search the lake with the query language, e.g. 'tag:dns title:"zone transfer" -tag:windows description:(spoof OR poison)'. Words rank the results, parts with - or NOT are excluded and parts with + or AND are required.
+    #[arg(short, long, value_name = "QUERY")]
+    pub query: Vec<String>,
+
+    /// This will search throw all the unique names with the values of SEARCH_UNIQUE_NAME.
+    #[arg(short = 'n', long, value_name = "SEARCH_UNIQUE_NAME", num_args = 1..)]
+    pub search_unique_name: Vec<String>,
+
+    /// This will search throw all the title with the values of SEARCH_TITLE.
+    #[arg(short = 't', long, value_name = "SEARCH_TITLE", num_args = 1..)]
+    pub search_title: Vec<String>,
+
+    /// This will search throw all the tags with the values.
+    #[arg(short = 'T', long, value_name = "SEARCH_TAGS", num_args = 1..)]
+    pub search_tags: Vec<String>,
+
+    /// This will search throw all the description with the values of SEARCH_DESCRIPTION.
+    #[arg(short = 'd', long, value_name = "SEARCH_DESCRIPTION", num_args = 1..)]
+    pub search_description: Vec<String>,
+
+    /// This will search throw all the references with the values of SEARCH_REFERENCES.
+    #[arg(short = 'r', long, value_name = "SEARCH_REFERENCES", num_args = 1..)]
+    pub search_references: Vec<String>,
+
+    /// Override the weights of the fields for the ranking of the lake, e.g. title=3,tags=2. The fields are id, title, tags, description and references. The default weights are set in the config.yaml.
+    #[arg(long, value_name = "WEIGHT")]
+    pub weight: Vec<String>,
+
+    /// Search only the given words, without the synonyms of the lake and the config.yaml.
+    #[arg(long)]
+    pub no_synonyms: bool,
+
+    /// Set the why not flag to see other programs, witch are better for this use case.
+    #[arg(short = 'W', long = "why_not")]
+    pub why_not: bool,
+
+    /// Show the score of every searched field and the total score of each hit.
+    #[arg(long)]
+    pub explain: bool,
+
+    /// Set this flag if you do not want the online check for the updated lake file.
+    #[arg(short, long)]
+    pub offline: bool,
+
+    /// Browse the lake in a terminal user interface, the hits are updated while typing. Enter prints the reference of the selected tool.
+    #[arg(short, long, conflicts_with_all = ["format", "template", "template_file"])]
+    pub interactive: bool,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+impl SearchArgs {
+    // True if no search criteria is set, then `wami` without arguments shows the help.
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+            && self.search_all.is_empty()
+            && self.query.is_empty()
+            && self.search_unique_name.is_empty()
+            && self.search_title.is_empty()
+            && self.search_tags.is_empty()
+            && self.search_description.is_empty()
+            && self.search_references.is_empty()
+            && !self.interactive
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct GithubSearchArgs {
+    /// The words to search in the repositories.
+    #[arg(value_name = "WORDS", required = true)]
+    pub words: Vec<String>,
+
+    /// Set the minimum of stars that have to be present in the Github repos.
+    #[arg(long, value_name = "MIN_STARS", default_value_t = 100)]
+    pub min_stars: isize,
+
+    /// Set the maximum of stars that have to be present in the GitHub repos. The default value is set to 0 which means that there is no limit.
+    #[arg(long, value_name = "MAX_STARS", default_value_t = 0)]
+    pub max_stars: isize,
+
+    /// This will set the search option for GitHub to the forks of an project.
+    #[arg(long)]
+    pub fork: bool,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+// The output of the hits is the same for the lake and GitHub.
+#[derive(Debug, Args)]
+pub struct OutputArgs {
+    /// Set the maximum of listed programs.
+    #[arg(short = 'M', long, value_name = "MAX", default_value_t = 10)]
+    pub max: usize,
+
+    /// This will determine the sorting direction asc or desc.
+    #[arg(short = 'S', long, value_name = "SORT", value_parser = ["asc", "desc"], default_value = "desc")]
+    pub sort: String,
+
+    /// Display detailed list of all available information.
+    #[arg(short = 'a', long)]
+    pub show_all: bool,
+
+    /// The output format of the hits: text, json, yaml, csv or ndjson.
+    #[arg(long, value_name = "FORMAT", value_parser = output::Format::NAMES, default_value = "text")]
+    pub format: String,
+
+    /// Render every hit with a template, e.g. '{{rank}} {{id}} {{references[0]}}'. The fields are rank, score, id, title, tags, description, references, why_not, lake, stars, topics and updated_at.
+    #[arg(long, value_name = "TEMPLATE", conflicts_with_all = ["format", "template_file"])]
+    pub template: Option<String>,
+
+    /// Render every hit with the template in the file TEMPLATE_FILE.
+    #[arg(long, value_name = "TEMPLATE_FILE", conflicts_with = "format")]
+    pub template_file: Option<String>,
+}
+
+fn about_text() -> String {
+    format!("{} - What am I\n\n{} is a user-friendly tool designed in Rust, powered by Cargo, to assist individuals who struggle with remembering the names of the various programs they utilize. This open-source program aims to simplify the process of finding the most suitable programs for specific tasks.\n\nCreated at 10.07.2023", "WAMI".bold().green(), "WAMI".bold().green())
+}
+
+fn example_text() -> String {
+    let wami = "wami".magenta();
+    let pentest = "pentest".truecolor(90, 90, 255);
+    format!(
+        "Example:\n  {} {} {} {} {} {}\n    {}\n\n  {} {} {} {} {} {}\n    {}\n\n  {} {} {}\n    {}\n\n  {} {}\n    {}",
+        wami,
+        "-a".green(),
+        "-S".green(),
+        "desc".truecolor(200, 200, 200),
+        "-s".green(),
+        pentest,
+        "This example will search in the lake with extended output in descending order in all categories for the word pentest.",
+        wami,
+        "github search".green(),
+        "-a".green(),
+        "-S".green(),
+        "desc".truecolor(200, 200, 200),
+        pentest,
+        "This example will search in GitHub with extended output in descending order for the word pentest.",
+        wami,
+        "github topics".green(),
+        pentest,
+        "This example will search in GitHub for topics with the word pentest.",
+        wami,
+        "lake update".green(),
+        "This example will download the newest version of the lakes."
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_cli_words_are_a_search() {
+        let cli = Cli::try_parse_from(["wami", "-M", "2", "dns", "lookup"]).unwrap();
+
+        assert!(cli.command.is_none());
+        assert_eq!(cli.search.words, vec!["dns", "lookup"]);
+        assert_eq!(cli.search.output.max, 2);
+    }
+
+    #[test]
+    fn test_cli_search_subcommand() {
+        let cli = Cli::try_parse_from(["wami", "search", "-T", "dns", "--format", "json"]).unwrap();
+
+        match cli.command {
+            Some(Command::Search(search)) => {
+                assert_eq!(search.search_tags, vec!["dns"]);
+                assert_eq!(search.output.format, "json");
+            }
+            command => panic!("Expected the search subcommand, got {:?}", command),
+        }
+    }
+
+    #[test]
+    fn test_cli_nested_subcommands() {
+        let cli = Cli::try_parse_from(["wami", "github", "search", "--fork", "pentest"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Github { command: GithubCommand::Search(GithubSearchArgs { fork: true, .. }) })
+        ));
+
+        let cli = Cli::try_parse_from(["wami", "lake", "status", "--offline"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Lake { command: LakeCommand::Status { offline: true } })
+        ));
+
+        let cli = Cli::try_parse_from(["wami", "lake", "add", "./my-templates", "-n", "internal", "-p", "-1"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Lake { command: LakeCommand::Add { priority: -1, .. } })
+        ));
+
+        let cli = Cli::try_parse_from(["wami", "config", "set", "weights.title", "3"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Config { command: ConfigCommand::Set { .. } })
+        ));
+    }
+
+    #[test]
+    fn test_cli_conflicts() {
+        // The browser can not be combined with an output format.
+        assert!(Cli::try_parse_from(["wami", "-i", "--format", "json"]).is_err());
+        // The old flags are gone, the subcommands replace them.
+        assert!(Cli::try_parse_from(["wami", "--github", "dns"]).is_err());
+    }
+}
@@ -1,85 +1,239 @@
-use isahc;
-use isahc::ReadResponseExt;
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use crate::{error::{Result, WamiError}, index::Field, lake::{archive::ZipLimits, backend::Backend}, search::{synonyms::Synonyms, Weights}};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::env;
 use std::fs;
 use std::io::{BufReader, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use url::Url;
 
 // This is the configuration file of the rust program.
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+// The lake of wami, it is the first lake of a new config.yaml.
+const DEFAULT_LAKE_NAME: &str = "wami-templates";
+const DEFAULT_LAKE_URL: &str = "https://github.com/evait-security/wami-templates/archive/refs/heads/main.zip";
+
+// The lakes are downloaded to this dir of the config path, every lake to a dir with its name.
+const LAKES_DIR_NAME: &str = "lakes";
+
+// The number of old versions of every lake, which are kept for `wami lake rollback`.
+const DEFAULT_KEEP_VERSIONS: usize = 3;
+
+#[derive(Debug, Serialize, Clone)]
 pub struct Config {
+    pub lakes: Vec<LakeSource>, // The lakes, which are searched together.
+    pub weights: Weights, // The weights of the fields for the ranking of the lake.
+    #[serde(skip_serializing_if = "Synonyms::is_empty")]
+    pub synonyms: Synonyms, // The user's synonyms, they extend the synonyms of the lake.
+    pub keep_versions: usize, // The number of old versions of every lake, 0 keeps none.
+    pub zip_limits: ZipLimits, // The limits of the archive of a lake, a bigger archive is not extracted.
+}
+
+// A lake of the config.yaml, e.g. the public wami-templates lake or the internal lake of a team.
+// If two lakes have a template with the same id, the template of the lake with the higher priority is used.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LakeSource {
+    pub name: String, // A unique name, the lake is downloaded to a dir with this name.
     pub url: String,
-    pub hash: String,
+    #[serde(default)]
+    pub hash: String, // The version of the downloaded lake, e.g. its commit, it is empty until the lake is downloaded.
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default = "LakeSource::default_enabled")]
+    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<Backend>, // The host of the archive, it is taken from the url if it is not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>, // The minisign public key of a signed lake, its archive is only used with a valid signature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pin: Option<String>, // The commit the lake is pinned to with `wami lake pin`, it is downloaded instead of the branch.
+}
+
+// Where the templates of a lake come from, it is taken from the url of the lake.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LakeKind {
+    Remote(Backend),   // An archive on a server, e.g. of a branch of a GitHub repository, it is downloaded and extracted.
+    LocalZip(PathBuf), // A zip file on this machine, e.g. file:///home/user/lake.zip, it is extracted.
+    LocalDir(PathBuf), // A dir on this machine, e.g. the checkout of a lake, its files are read directly.
+}
+
+impl LakeKind {
+    // The name of the kind for the status of the lake.
+    pub fn name(&self) -> &'static str {
+        match self {
+            LakeKind::Remote(backend) => backend.name(),
+            LakeKind::LocalZip(_) => "zip",
+            LakeKind::LocalDir(_) => "dir",
+        }
+    }
+}
+
+// The config.yaml as it is written on the disk.
+// Old versions of wami saved a single lake with its url and hash, it is moved to the list of lakes.
+#[derive(Deserialize)]
+struct ConfigYaml {
+    #[serde(default)]
+    lakes: Vec<LakeSource>,
+    url: Option<String>,
+    hash: Option<String>,
+    #[serde(default)]
+    weights: Weights,
+    #[serde(default)]
+    synonyms: Synonyms,
+    #[serde(default = "Config::default_keep_versions")]
+    keep_versions: usize,
+    #[serde(default)]
+    zip_limits: ZipLimits,
+}
+
+impl ConfigYaml {
+    // True if this is a config.yaml with a single lake of an old version.
+    fn is_old(&self) -> bool {
+        self.lakes.is_empty() && self.url.is_some()
+    }
+}
+
+impl From<ConfigYaml> for Config {
+    fn from(in_yaml: ConfigYaml) -> Config {
+        let mut lakes: Vec<LakeSource> = in_yaml.lakes;
+        if lakes.is_empty() {
+            if let Some(url) = in_yaml.url {
+                let mut lake = LakeSource::new(&LakeSource::name_from_url(&url), &url, 0);
+                lake.hash = in_yaml.hash.unwrap_or_default();
+                lakes.push(lake);
+            }
+        }
+
+        Config {
+            lakes,
+            weights: in_yaml.weights,
+            synonyms: in_yaml.synonyms,
+            keep_versions: in_yaml.keep_versions,
+            zip_limits: in_yaml.zip_limits,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Config {
+    fn deserialize<D: Deserializer<'de>>(in_deserializer: D) -> std::result::Result<Config, D::Error> {
+        ConfigYaml::deserialize(in_deserializer).map(Config::from)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            lakes: vec![LakeSource::new(DEFAULT_LAKE_NAME, DEFAULT_LAKE_URL, 0)],
+            weights: Weights::default(),
+            synonyms: Synonyms::default(),
+            keep_versions: DEFAULT_KEEP_VERSIONS,
+            zip_limits: ZipLimits::default(),
+        }
+    }
 }
 
 impl Config {
-    pub fn new() -> Result<Config, Box<dyn std::error::Error>> {
+    fn default_keep_versions() -> usize {
+        DEFAULT_KEEP_VERSIONS
+    }
+
+    pub fn new() -> Result<Config> {
         let config_path: PathBuf = Config::get_config_path();
-        Ok(Config::load_config_yaml(config_path)?)
+        Config::load_config_yaml(config_path)
     }
 
     // Load the config.yaml file.
-    pub fn load_config_yaml(config_path: PathBuf) -> Result<Config, Box<dyn std::error::Error>> {
-        let file = fs::File::open(Config::init_config_yaml(config_path)?)?;
+    // The single lake of an old config.yaml is moved to the list of lakes, its dir is moved as well,
+    // so the lake does not have to be downloaded again.
+    pub fn load_config_yaml(config_path: PathBuf) -> Result<Config> {
+        let config_file_path: PathBuf = Config::init_config_yaml(config_path.clone())?;
+        let file = fs::File::open(&config_file_path).map_err(|err| Config::file_error(&config_file_path, err))?;
 
         let reader = BufReader::new(file);
 
-        let config: Config = serde_yaml::from_reader(reader)?;
+        let config_yaml: ConfigYaml =
+            serde_yaml::from_reader(reader).map_err(|err| Config::file_error(&config_file_path, err))?;
+        let is_old: bool = config_yaml.is_old();
+        let out_config = Config::from(config_yaml);
+        if is_old {
+            for lake in &out_config.lakes {
+                lake.move_old_lake_dir(&config_path);
+            }
+            out_config.write_to(&config_file_path)?;
+        }
+        Ok(out_config)
+    }
 
-        Ok(config)
+    // An error of the config.yaml with the path of the file.
+    fn file_error(in_path: &Path, in_err: impl std::fmt::Display) -> WamiError {
+        WamiError::Config(format!("{}: {}", in_path.display(), in_err))
     }
 
-    pub fn init_config_yaml(config_path: PathBuf) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    pub fn init_config_yaml(config_path: PathBuf) -> Result<PathBuf> {
         // If the path to $HOME/.config/wami is not present then create it.
         if !Config::is_dir_present(config_path.to_owned()) {
-            Config::create_config_path(config_path.to_owned())?;
+            Config::create_config_path(config_path.to_owned())
+                .map_err(|err| Config::file_error(&config_path, err))?;
         }
 
         // If the config.yaml file is not present then create it.
         let config_file_path: PathBuf = Config::get_config_file_path(config_path.clone());
         if !Config::is_config_yaml_present(config_file_path.clone()) {
-            // Creating an Config struct.
-            let config_yaml;
-            let in_url: &String = &"https://github.com/evait-security/wami-templates/archive/refs/heads/main.zip".to_string();
-            match Config::get_git_hash(in_url) {
-                Ok(hash) => {
-                    config_yaml = Config {
-                        url: in_url.to_owned(),
-                        hash: hash.to_string(),
-                    };    
-                },
-                Err(_err) => {
-                    println!("Version of the lake can not be downloaded. Setting the hash to empty string.");
-                    config_yaml = Config {
-                        url: in_url.to_owned(),
-                        hash: "".to_string(),
-                    };
-                }
-            }
+            // The new config has the lake of wami, its hash is saved when it is downloaded.
+            Config::default().write_to(&config_file_path)?;
+        }
 
-            let yaml_content = serde_yaml::to_string(&config_yaml)?;
+        Ok(config_file_path)
+    }
 
-            let mut file = fs::File::create(config_file_path.clone())?;
+    // Find a lake of the config by its name.
+    pub fn find_lake(&self, in_name: &str) -> Result<&LakeSource> {
+        self.lakes
+            .iter()
+            .find(|lake| lake.name == in_name)
+            .ok_or_else(|| self.unknown_lake(in_name))
+    }
 
-            file.write_all(yaml_content.as_bytes())?;
+    pub fn find_lake_mut(&mut self, in_name: &str) -> Result<&mut LakeSource> {
+        match self.lakes.iter().position(|lake| lake.name == in_name) {
+            Some(position) => Ok(&mut self.lakes[position]),
+            None => Err(self.unknown_lake(in_name)),
         }
+    }
 
-        Ok(config_file_path)
+    // The error for a name, which is no lake of the config.
+    fn unknown_lake(&self, in_name: &str) -> WamiError {
+        let names: Vec<&str> = self.lakes.iter().map(|lake| lake.name.as_str()).collect();
+        WamiError::InvalidInput(format!("There is no lake {}, the lakes are {}.", in_name, names.join(", ")))
     }
 
-    // This is a setter function, for the url value of the struct.
-    pub fn set_new_url(&mut self, in_url: String) {
-        if Url::parse(&in_url)
-            .map(|url| !url.path().contains(".."))
-            .unwrap_or(false)
-        {
-            self.url = in_url;
-        } else {
-            panic! {"Invalid URL: {}", in_url};
+    // The enabled lakes, the lake with the highest priority first.
+    // Lakes with the same priority keep the order of the config.yaml.
+    pub fn enabled_lakes(&self) -> Vec<&LakeSource> {
+        let mut out_lakes: Vec<&LakeSource> = self.lakes.iter().filter(|lake| lake.enabled).collect();
+        out_lakes.sort_by_key(|lake| std::cmp::Reverse(lake.priority));
+        out_lakes
+    }
+
+    // Add a new lake, its name has to be unique.
+    pub fn add_lake(&mut self, in_lake: LakeSource) -> Result<()> {
+        in_lake.check()?;
+        if self.lakes.iter().any(|lake| lake.name == in_lake.name) {
+            return Err(WamiError::InvalidInput(format!("There is already a lake {}.", in_lake.name)));
+        }
+        self.lakes.push(in_lake);
+        Ok(())
+    }
+
+    // Remove a lake from the config, its dir is not deleted here.
+    // The last lake can not be removed, wami needs at least one lake.
+    pub fn remove_lake(&mut self, in_name: &str) -> Result<LakeSource> {
+        match self.lakes.iter().position(|lake| lake.name == in_name) {
+            Some(_) if self.lakes.len() == 1 => Err(WamiError::InvalidInput(format!(
+                "The lake {} is the last lake, wami needs at least one lake. Its url can be changed with wami lake use.",
+                in_name
+            ))),
+            Some(position) => Ok(self.lakes.remove(position)),
+            None => Err(self.unknown_lake(in_name)),
         }
     }
 
@@ -114,18 +268,388 @@ impl Config {
     }
 
     // This will create the dir for the config folder of Wami.
-    fn create_config_path(in_path_buffer: PathBuf) -> Result<(), std::io::Error> {
+    fn create_config_path(in_path_buffer: PathBuf) -> std::io::Result<()> {
         fs::create_dir(in_path_buffer)
     }
 
+    // The dir of all lakes, the cache of the merged lakes is saved here as well.
+    pub fn get_lakes_path() -> PathBuf {
+        let mut out_path: PathBuf = Config::get_config_path();
+        out_path.push(LAKES_DIR_NAME);
+        out_path
+    }
+
+    // Save the whole config struct to the config.yaml.
+    pub fn save(&self) -> Result<()> {
+        let config_file_path = Config::init_config_yaml(Config::get_config_path())?;
+        self.write_to(&config_file_path)
+    }
+
+    // Write the config struct to a yaml file.
+    fn write_to(&self, in_path: &Path) -> Result<()> {
+        let yaml_content = serde_yaml::to_string(self).map_err(|err| Config::file_error(in_path, err))?;
+        let mut file = fs::File::create(in_path).map_err(|err| Config::file_error(in_path, err))?;
+        file.write_all(yaml_content.as_bytes())
+            .map_err(|err| Config::file_error(in_path, err))
+    }
+
+    // The value of a key for `wami config get`, lists and structs are printed as yaml.
+    // A single weight is read with the key weights.FIELD, e.g. weights.title.
+    // A lake is read with the key lakes.NAME and a field of it with lakes.NAME.FIELD, e.g. lakes.wami-templates.hash.
+    pub fn get_value(&self, in_key: &str) -> Result<String> {
+        let yaml = |in_value: std::result::Result<String, serde_yaml::Error>| {
+            in_value
+                .map(|value| value.trim_start_matches("---\n").trim_end().to_owned())
+                .map_err(|err| WamiError::Config(err.to_string()))
+        };
+
+        if let Some(lake_key) = in_key.strip_prefix("lakes.") {
+            let (name, field) = Config::split_lake_key(lake_key);
+            let lake: &LakeSource = self.find_lake(name)?;
+            return match field {
+                None => yaml(serde_yaml::to_string(lake)),
+                Some("url") => Ok(lake.url.to_owned()),
+                Some("hash") => Ok(lake.hash.to_owned()),
+                Some("priority") => Ok(lake.priority.to_string()),
+                Some("enabled") => Ok(lake.enabled.to_string()),
+                Some("backend") => lake.kind().map(|kind| kind.name().to_owned()),
+                Some("public_key") => Ok(lake.public_key.to_owned().unwrap_or_default()),
+                Some("pin") => Ok(lake.pin.to_owned().unwrap_or_default()),
+                Some(field) => Err(WamiError::InvalidInput(format!(
+                    "Unknown field '{}' of a lake, expected url, hash, priority, enabled, backend, public_key or pin.",
+                    field
+                ))),
+            };
+        }
+
+        match in_key {
+            "lakes" => yaml(serde_yaml::to_string(&self.lakes)),
+            "weights" => yaml(serde_yaml::to_string(&self.weights)),
+            "synonyms" => yaml(serde_yaml::to_string(&self.synonyms)),
+            "keep_versions" => Ok(self.keep_versions.to_string()),
+            "zip_limits" => yaml(serde_yaml::to_string(&self.zip_limits)),
+            _ => match (
+                in_key.strip_prefix("weights.").and_then(Field::from_name),
+                in_key.strip_prefix("zip_limits.").and_then(|name| self.zip_limits.get(name)),
+            ) {
+                (Some(field), _) => Ok(self.weights.get(field).to_string()),
+                (None, Some(limit)) => Ok(limit),
+                (None, None) => Err(WamiError::InvalidInput(format!(
+                    "Unknown key '{}', expected lakes, lakes.NAME, lakes.NAME.FIELD, weights, weights.FIELD, synonyms, keep_versions, zip_limits or zip_limits.FIELD.",
+                    in_key
+                ))),
+            },
+        }
+    }
+
+    // Split the key of a lake into the name and the field, e.g. wami-templates.priority.
+    // The name of a lake has no dot, so the field is everything after the first dot.
+    fn split_lake_key(in_key: &str) -> (&str, Option<&str>) {
+        match in_key.split_once('.') {
+            Some((name, field)) => (name, Some(field)),
+            None => (in_key, None),
+        }
+    }
+
+    // Change the value of a key for `wami config set`, the config.yaml is not saved here.
+    // The hash belongs to the downloaded lake and a new url needs a new lake, so both are
+    // changed with the lake subcommands.
+    pub fn set_value(&mut self, in_key: &str, in_value: &str) -> Result<()> {
+        if let Some(lake_key) = in_key.strip_prefix("lakes.") {
+            let (name, field) = Config::split_lake_key(lake_key);
+            let lake: &mut LakeSource = self.find_lake_mut(name)?;
+            let invalid_value = || WamiError::InvalidInput(format!("Invalid value '{}' for {}.", in_value, in_key));
+            return match field {
+                Some("priority") => {
+                    lake.priority = in_value.trim().parse().map_err(|_| invalid_value())?;
+                    Ok(())
+                }
+                Some("enabled") => {
+                    lake.enabled = in_value.trim().parse().map_err(|_| invalid_value())?;
+                    Ok(())
+                }
+                // The key is pinned, the signature is checked with the next update of the lake.
+                // An empty value removes the key.
+                Some("public_key") => {
+                    let public_key: &str = in_value.trim();
+                    if !public_key.is_empty() && minisign_verify::PublicKey::from_base64(public_key).is_err() {
+                        return Err(invalid_value());
+                    }
+                    lake.public_key = Some(public_key.to_owned()).filter(|public_key| !public_key.is_empty());
+                    Ok(())
+                }
+                _ => Err(WamiError::InvalidInput(format!(
+                    "The key '{}' can not be set, expected lakes.NAME.priority, lakes.NAME.enabled or lakes.NAME.public_key.",
+                    in_key
+                ))),
+            };
+        }
+
+        if in_key == "keep_versions" {
+            self.keep_versions = in_value
+                .trim()
+                .parse()
+                .map_err(|_| WamiError::InvalidInput(format!("Invalid value '{}' for {}.", in_value, in_key)))?;
+            return Ok(());
+        }
+
+        if let Some(name) = in_key.strip_prefix("zip_limits.") {
+            return self.zip_limits.set(name, in_value).map_err(WamiError::InvalidInput);
+        }
+
+        let overrides: String = match in_key {
+            "weights" => in_value.to_owned(),
+            _ => match in_key.strip_prefix("weights.") {
+                Some(field) => format!("{}={}", field, in_value),
+                None => {
+                    return Err(WamiError::InvalidInput(format!(
+                        "The key '{}' can not be set, expected weights, weights.FIELD, keep_versions, zip_limits.FIELD, lakes.NAME.priority, lakes.NAME.enabled or lakes.NAME.public_key.",
+                        in_key
+                    )))
+                }
+            },
+        };
+        self.weights.apply_overrides(&overrides).map_err(WamiError::InvalidInput)
+    }
+}
+
+impl LakeSource {
+    pub fn new(in_name: &str, in_url: &str, in_priority: i32) -> LakeSource {
+        LakeSource {
+            name: in_name.to_owned(),
+            url: in_url.to_owned(),
+            hash: String::new(),
+            priority: in_priority,
+            enabled: true,
+            backend: None,
+            public_key: None,
+            pin: None,
+        }
+    }
+
+    fn default_enabled() -> bool {
+        true
+    }
+
+    // Create a lake from the location the user typed, e.g. for `wami lake add`.
+    // The location is an url or a path of a dir or a zip file, see LakeSource::location_to_url.
+    // Without a name, the name is taken from the url and without a backend, the backend as well.
+    pub fn from_location(in_location: &str, in_name: Option<&str>, in_priority: i32, in_backend: Option<Backend>) -> Result<LakeSource> {
+        let url: String = LakeSource::location_to_url(in_location)?;
+        let mut out_lake = LakeSource::new(DEFAULT_LAKE_NAME, &url, in_priority);
+        out_lake.backend = in_backend;
+        out_lake.name = in_name.map(str::to_owned).unwrap_or_else(|| out_lake.name_of_url());
+        out_lake.check()?;
+        Ok(out_lake)
+    }
+
+    // Turn a location into the url of a lake.
+    // Urls are kept, a path is made absolute and saved as a file:// url, so the lake
+    // does not depend on the dir wami was started in.
+    pub fn location_to_url(in_location: &str) -> Result<String> {
+        // A single letter is the drive of a windows path like C:\lake, no scheme.
+        if let Ok(url) = Url::parse(in_location) {
+            if url.scheme().len() > 1 {
+                return Ok(url.to_string());
+            }
+        }
+
+        let path: PathBuf = fs::canonicalize(in_location)
+            .map_err(|err| WamiError::InvalidInput(format!("The lake {} can not be found: {}", in_location, err)))?;
+        Url::from_file_path(&path)
+            .map(|url| url.to_string())
+            .map_err(|_| WamiError::InvalidInput(format!("Invalid path of a lake: {}", path.display())))
+    }
+
+    // The name of a lake for its url.
+    pub fn name_from_url(in_url: &str) -> String {
+        LakeSource::new(DEFAULT_LAKE_NAME, in_url, 0).name_of_url()
+    }
+
+    // The name of the lake for its url and its backend.
+    // It is the name of the repository of a git host, the name of the zip file of a plain download
+    // and the name of the dir or zip file of a local lake.
+    // The name of the lake of wami is used, if the url has no usable name.
+    fn name_of_url(&self) -> String {
+        let url: Option<Url> = Url::parse(&self.url).ok();
+        let name: Option<String> = match (self.kind(), url) {
+            (Ok(LakeKind::Remote(Backend::Https)), Some(url)) => url
+                .path_segments()
+                .and_then(|mut segments| segments.next_back().map(str::to_owned))
+                .map(|file_name| file_name.trim_end_matches(".zip").to_owned()),
+            (Ok(LakeKind::Remote(backend)), Some(url)) => backend
+                .archive(&url)
+                .ok()
+                .and_then(|archive| archive.project.last().cloned()),
+            (Ok(LakeKind::LocalZip(path)), _) | (Ok(LakeKind::LocalDir(path)), _) => {
+                path.file_stem().map(|name| name.to_string_lossy().to_string())
+            }
+            _ => None,
+        };
+        name.filter(|name| LakeSource::is_valid_name(name))
+            .unwrap_or_else(|| DEFAULT_LAKE_NAME.to_owned())
+    }
+
+    // The kind of the lake, it is taken from its url.
+    // The backend of an archive is taken from the url as well, unless it is set in the config.yaml.
+    pub fn kind(&self) -> Result<LakeKind> {
+        let invalid_url = || WamiError::Config(format!("The url {} is no http(s) url, no zip file and no dir.", self.url));
+
+        let parsed_url: Url = Url::parse(&self.url).map_err(|_| invalid_url())?;
+        match parsed_url.scheme() {
+            "file" => {
+                let path: PathBuf = parsed_url.to_file_path().map_err(|_| invalid_url())?;
+                if path.extension().map(|extension| extension == "zip").unwrap_or(false) {
+                    Ok(LakeKind::LocalZip(path))
+                } else {
+                    Ok(LakeKind::LocalDir(path))
+                }
+            }
+            "http" | "https" => {
+                let backend: Backend = self.backend.unwrap_or_else(|| Backend::detect(&parsed_url));
+                // The git hosts need the repository and the branch for the version of the lake.
+                if backend != Backend::Https {
+                    backend.archive(&parsed_url)?;
+                }
+                Ok(LakeKind::Remote(backend))
+            }
+            _ => Err(invalid_url()),
+        }
+    }
+
+    // The url the archive of the lake is downloaded from.
+    // A pinned lake is downloaded from the archive of its commit, the url of the branch is kept for its version.
+    pub fn get_archive_url(&self) -> Result<String> {
+        match (&self.pin, self.kind()?) {
+            (Some(pin), LakeKind::Remote(backend)) => {
+                let url: Url = Url::parse(&self.url).map_err(|_| WamiError::Config(format!("Invalid url of the lake: {}", self.url)))?;
+                Ok(backend.pinned_url(&url, pin)?.to_string())
+            }
+            _ => Ok(self.url.to_owned()),
+        }
+    }
+
+    // The name is used as a dir and as a key of wami config, so it is limited to
+    // letters, digits, hyphens and underscores.
+    fn is_valid_name(in_name: &str) -> bool {
+        !in_name.is_empty()
+            && in_name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    }
+
+    // Check the name, the public key and the url of a new lake.
+    pub fn check(&self) -> Result<()> {
+        if !LakeSource::is_valid_name(&self.name) {
+            return Err(WamiError::InvalidInput(format!(
+                "Invalid lake name '{}', use letters, digits, - and _.",
+                self.name
+            )));
+        }
+        if let Some(public_key) = &self.public_key {
+            minisign_verify::PublicKey::from_base64(public_key)
+                .map_err(|err| WamiError::InvalidInput(format!("Invalid public key {}: {}", public_key, err)))?;
+        }
+        self.set_url_checked(&self.url).map(|_| ())
+    }
+
+    // Check a new url of the lake and return the lake with it.
+    fn set_url_checked(&self, in_url: &str) -> Result<LakeSource> {
+        if !Url::parse(in_url)
+            .map(|url| !url.path().contains(".."))
+            .unwrap_or(false)
+        {
+            return Err(WamiError::InvalidInput(format!("Invalid URL: {}", in_url)));
+        }
+        let out_lake = LakeSource {
+            url: in_url.to_owned(),
+            ..self.clone()
+        };
+        out_lake.get_lake_dir().map_err(|err| WamiError::InvalidInput(err.to_string()))?;
+        Ok(out_lake)
+    }
+
+    // This is a setter function, for the url value of the lake.
+    // The hash belongs to the old url, so it is cleared.
+    pub fn set_new_url(&mut self, in_url: &str) -> Result<()> {
+        *self = self.set_url_checked(in_url)?;
+        self.hash = String::new();
+        // The pin is a commit of the old repository.
+        self.pin = None;
+        Ok(())
+    }
+
+    // The dir the archive of the lake is extracted to.
+    pub fn get_download_dir(&self) -> PathBuf {
+        let mut out_path: PathBuf = Config::get_lakes_path();
+        out_path.push(&self.name);
+        out_path
+    }
+
+    // A new version of the lake is extracted to this dir first, it replaces the download dir when it is complete.
+    // The name of a lake has no dot, so the dir can not be the dir of another lake.
+    pub fn get_staging_dir(&self) -> PathBuf {
+        Config::get_lakes_path().join(format!("{}.staging", self.name))
+    }
+
+    // The old versions of the lake are moved to this dir, every version to a dir with the time of the update.
+    pub fn get_versions_dir(&self) -> PathBuf {
+        Config::get_lakes_path().join(format!("{}.versions", self.name))
+    }
+
+    // The dirs of the old versions of the lake, the newest version last.
+    pub fn get_versions(&self) -> Vec<PathBuf> {
+        LakeSource::get_versions_in(&self.get_versions_dir())
+    }
+
+    // The dirs of the old versions in a versions dir, the newest version last.
+    pub fn get_versions_in(in_versions_dir: &Path) -> Vec<PathBuf> {
+        let mut out_versions: Vec<PathBuf> = fs::read_dir(in_versions_dir)
+            .map(|entries| entries.flatten().map(|entry| entry.path()).filter(|path| path.is_dir()).collect())
+            .unwrap_or_default();
+        out_versions.sort();
+        out_versions
+    }
+
     // This will generate the path of the lake dir.
-    pub fn get_lake_dir(&self) -> PathBuf {
-        // Extract the path segments
-        let parsed_url =
-            Url::parse(&self.url).expect("Failed to parse URL at at config::Config::get_lake_dir.");
+    // Every host names the top dir of its archives in its own way, e.g. REPOSITORY-BRANCH on GitHub
+    // and PROJECT-BRANCH-COMMIT on GitLab, so the templates of an archive and of a local dir
+    // are found with LakeSource::find_lake_dir.
+    pub fn get_lake_dir(&self) -> Result<PathBuf> {
+        match self.kind()? {
+            LakeKind::Remote(_) | LakeKind::LocalZip(_) => Ok(LakeSource::find_lake_dir(&self.get_download_dir())),
+            LakeKind::LocalDir(path) => Ok(LakeSource::find_lake_dir(&path)),
+        }
+    }
+
+    // Find the templates in a dir, which was not made by wami.
+    // It is the lake dir of a checkout of a lake, the lake dir of the only dir of an archive
+    // or the dir itself, if the templates are not in a lake dir.
+    pub fn find_lake_dir(in_dir: &Path) -> PathBuf {
+        let lake_dir: PathBuf = in_dir.join("lake");
+        if lake_dir.is_dir() {
+            return lake_dir;
+        }
+
+        let sub_dirs: Vec<PathBuf> = fs::read_dir(in_dir)
+            .map(|entries| entries.flatten().map(|entry| entry.path()).filter(|path| path.is_dir()).collect())
+            .unwrap_or_default();
+        match sub_dirs.as_slice() {
+            [sub_dir] if sub_dir.join("lake").is_dir() => sub_dir.join("lake"),
+            _ => in_dir.to_path_buf(),
+        }
+    }
+
+    // The name of the top dir of the GitHub archive of the lake, the lake dirs of old versions of wami have this name.
+    fn get_archive_dir_name(&self) -> Result<String> {
+        let invalid_url = || WamiError::Config(format!("The url {} is no archive of a GitHub repository.", self.url));
 
         // Create segments out of the url
-        let url_segments: Vec<_> = parsed_url.path_segments().unwrap().collect();
+        let parsed_url: Url = Url::parse(&self.url).map_err(|_| invalid_url())?;
+        let url_segments: Vec<&str> = parsed_url.path_segments().ok_or_else(invalid_url)?.collect();
+        if url_segments.len() < 6 {
+            return Err(invalid_url());
+        }
 
         // Get the repository name form the segment
         let repository = url_segments[1];
@@ -135,77 +659,225 @@ impl Config {
             .strip_suffix(".zip")
             .unwrap_or(url_segments[5]);
 
-        // Create the path name
-        let mut lake_path: PathBuf = Config::get_config_path();
-        lake_path.push(repository.to_owned() + "-" + branch + "/lake/");
-        lake_path
+        Ok(repository.to_owned() + "-" + branch)
     }
 
-    // This function is used by the update function.
-    // If there is no lake dir, the lake will be downloaded from the url.
-    pub fn del_lake_dir(&self) {
-        match fs::remove_dir_all(&self.get_lake_dir().to_owned()) {
-            Ok(()) => { /* Do nothing, there is no reason to be noisy. */ }
-            Err(err) => eprintln!("Failed to delete directory: {}", err),
+    // This function is used, when the lake is removed.
+    // The download dir, the old versions and a staged update are deleted, a local dir of the user is never touched.
+    pub fn del_lake_dir(&self) -> Result<()> {
+        for dir in [self.get_download_dir(), self.get_staging_dir(), self.get_versions_dir()] {
+            match fs::remove_dir_all(dir) {
+                Ok(()) => {}
+                // There is nothing to delete.
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err.into()),
+            }
         }
+        Ok(())
     }
 
-    // Save the config.yaml
-    pub fn save_to_config_yaml(
-        in_url: &String,
-        in_hash: &String,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        // get the config path
-        let config_path: PathBuf = Config::get_config_path();
-
-        // Creating an Config struct
-        let config_yaml = Config {
-            url: in_url.to_owned(),
-            hash: in_hash.to_owned(),
+    // Old versions of wami extracted the lake directly to the config path.
+    // The dir is moved to the download dir of the lake, if it is there.
+    fn move_old_lake_dir(&self, in_config_path: &Path) {
+        let Ok(archive_dir_name) = self.get_archive_dir_name() else {
+            return;
         };
+        let old_dir: PathBuf = in_config_path.join(&archive_dir_name);
+        let new_dir: PathBuf = in_config_path.join(LAKES_DIR_NAME).join(&self.name).join(&archive_dir_name);
+        if old_dir.exists() && !new_dir.exists() {
+            // If the dir can not be moved, the lake is downloaded again.
+            let _ = fs::create_dir_all(in_config_path.join(LAKES_DIR_NAME).join(&self.name))
+                .and_then(|_| fs::rename(&old_dir, &new_dir));
+        }
+    }
+}
 
-        // Serialize the config struct
-        let yaml_content = serde_yaml::to_string(&config_yaml)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        let mut lake = LakeSource::new(DEFAULT_LAKE_NAME, DEFAULT_LAKE_URL, 0);
+        lake.hash = "abc".to_string();
+        Config {
+            lakes: vec![
+                lake,
+                LakeSource::new("internal", "https://github.com/acme/tools/archive/refs/heads/main.zip", 10),
+            ],
+            weights: Weights::default(),
+            synonyms: Synonyms::default(),
+            keep_versions: DEFAULT_KEEP_VERSIONS,
+            zip_limits: ZipLimits::default(),
+        }
+    }
 
-        // Create the config file
-        let config_file_path = Config::init_config_yaml(config_path)?;
-        let mut file = fs::File::create(config_file_path)?;
+    #[test]
+    fn test_config_get_value() {
+        let config = config();
+
+        assert_eq!(config.get_value("lakes.wami-templates.hash").unwrap(), "abc");
+        assert_eq!(config.get_value("lakes.internal.priority").unwrap(), "10");
+        assert!(config.get_value("lakes.internal").unwrap().contains("name: internal"));
+        assert!(config.get_value("lakes.unknown").is_err());
+        assert_eq!(config.get_value("weights.title").unwrap(), "1");
+        assert!(config.get_value("weights").unwrap().contains("references: 1.0"));
+        assert!(config.get_value("weights.stars").is_err());
+        assert!(config.get_value("unknown").is_err());
+    }
 
-        // Write the content to the file.
-        file.write_all(yaml_content.as_bytes())?;
+    #[test]
+    fn test_config_set_value() {
+        let mut config = config();
+
+        config.set_value("weights.title", "3").unwrap();
+        config.set_value("weights", "tags=2,references=0.5").unwrap();
+        assert_eq!(config.weights.title, 3.0);
+        assert_eq!(config.weights.tags, 2.0);
+        assert_eq!(config.weights.references, 0.5);
+
+        assert!(config.set_value("weights.title", "-1").is_err());
+        assert!(config.set_value("hash", "def").is_err());
+        assert!(config.set_value("url", "https://example.com/lake.zip").is_err());
+        assert!(config.set_value("lakes.wami-templates.hash", "def").is_err());
+        assert_eq!(config.lakes[0].hash, "abc");
+
+        config.set_value("lakes.internal.enabled", "false").unwrap();
+        config.set_value("lakes.wami-templates.priority", "5").unwrap();
+        assert!(!config.lakes[1].enabled);
+        assert_eq!(config.lakes[0].priority, 5);
+        assert!(config.set_value("lakes.internal.priority", "high").is_err());
+
+        config.set_value("lakes.internal.public_key", "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3").unwrap();
+        assert_eq!(config.get_value("lakes.internal.public_key").unwrap(), "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3");
+        assert!(config.set_value("lakes.internal.public_key", "no key").is_err());
+        config.set_value("lakes.internal.public_key", "").unwrap();
+        assert_eq!(config.lakes[1].public_key, None);
+
+        config.set_value("keep_versions", "5").unwrap();
+        assert_eq!(config.get_value("keep_versions").unwrap(), "5");
+        assert!(config.set_value("keep_versions", "-1").is_err());
+
+        config.set_value("zip_limits.max_size", "1024").unwrap();
+        assert_eq!(config.zip_limits.max_size, 1024);
+        assert_eq!(config.get_value("zip_limits.max_size").unwrap(), "1024");
+        assert!(config.set_value("zip_limits.max_size", "big").is_err());
+        assert!(config.get_value("zip_limits.max_depth").is_err());
+    }
 
-        Ok(())
+    #[test]
+    fn test_pinned_lake() {
+        let mut config = config();
+        config.lakes[0].pin = Some("0a1b2c".to_string());
+
+        assert_eq!(config.get_value("lakes.wami-templates.pin").unwrap(), "0a1b2c");
+        assert_eq!(config.get_value("lakes.internal.pin").unwrap(), "");
+        assert_eq!(
+            config.lakes[0].get_archive_url().unwrap(),
+            "https://github.com/evait-security/wami-templates/archive/0a1b2c.zip"
+        );
+        assert_eq!(config.lakes[1].get_archive_url().unwrap(), config.lakes[1].url);
+        assert!(serde_yaml::to_string(&config.lakes[0]).unwrap().contains("pin: 0a1b2c"));
+        assert!(!serde_yaml::to_string(&config.lakes[1]).unwrap().contains("pin:"));
+
+        // The pin belongs to the repository of the old url.
+        config.lakes[0].set_new_url("https://github.com/acme/tools/archive/refs/heads/dev.zip").unwrap();
+        assert_eq!(config.lakes[0].pin, None);
     }
 
-    pub fn get_git_hash(in_url: &String) -> Result<String, Box<dyn std::error::Error>> {
-        if let Ok(url) = Url::parse(&in_url) {
-            if let Some(branch_name) = url.path_segments().unwrap().last() {
-                if branch_name.ends_with(".zip") {
-                    let repo_url = url.clone();
-                    let repo_parts: Vec<&str> = repo_url.path_segments().unwrap().take(3).collect();
-    
-                    let mut api_url = Url::parse("https://api.github.com").unwrap();
-                    api_url
-                        .path_segments_mut()
-                        .unwrap()
-                        .extend(&["repos", repo_parts[0], repo_parts[1], "git", "refs", "heads", branch_name.trim_end_matches(".zip")]);
-    
-                    let mut response = isahc::get(&api_url.to_string())?;
-                    let response_text = response.text()?;
-                    let parsed_response: Value = serde_json::from_str(&response_text)?;
-                    if let Some(sha_value) = parsed_response["object"]["sha"].as_str() {
-                        Ok(sha_value.to_string()) 
-                    } else {
-                        Err("SHA value not found in JSON response".into())
-                    }
-                } else {
-                    Err("Invalid URL structure".into())
-                }
-            } else {
-                Err("Invalid URL structure".into())
-            }
-        } else {
-            Err("Invalid URL".into())
-        }
+    #[test]
+    fn test_config_old_single_lake_is_migrated() {
+        let yaml = "url: https://github.com/evait-security/wami-templates/archive/refs/heads/main.zip\nhash: abc\n";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+
+        assert_eq!(config.lakes.len(), 1);
+        assert_eq!(config.lakes[0].name, "wami-templates");
+        assert_eq!(config.lakes[0].hash, "abc");
+        assert!(config.lakes[0].enabled);
+        assert_eq!(config.keep_versions, DEFAULT_KEEP_VERSIONS);
+        assert!(!serde_yaml::to_string(&config).unwrap().contains("\nurl:"));
+    }
+
+    #[test]
+    fn test_config_enabled_lakes_by_priority() {
+        let mut config = config();
+        config.add_lake(LakeSource::new("local", DEFAULT_LAKE_URL, 10)).unwrap();
+
+        let names: Vec<&str> = config.enabled_lakes().iter().map(|lake| lake.name.as_str()).collect();
+        assert_eq!(names, vec!["internal", "local", "wami-templates"]);
+
+        config.lakes[1].enabled = false;
+        let names: Vec<&str> = config.enabled_lakes().iter().map(|lake| lake.name.as_str()).collect();
+        assert_eq!(names, vec!["local", "wami-templates"]);
+    }
+
+    #[test]
+    fn test_lake_kind_of_the_url() {
+        assert_eq!(LakeSource::new("a", DEFAULT_LAKE_URL, 0).kind().unwrap(), LakeKind::Remote(Backend::GitHub));
+        assert_eq!(
+            LakeSource::new("a", "file:///home/user/lake.zip", 0).kind().unwrap(),
+            LakeKind::LocalZip(PathBuf::from("/home/user/lake.zip"))
+        );
+        assert_eq!(
+            LakeSource::new("a", "file:///home/user/my-templates", 0).kind().unwrap(),
+            LakeKind::LocalDir(PathBuf::from("/home/user/my-templates"))
+        );
+        assert!(LakeSource::new("a", "ftp://example.com/lake.zip", 0).kind().is_err());
+
+        assert_eq!(LakeSource::name_from_url("file:///home/user/my-templates"), "my-templates");
+        assert_eq!(LakeSource::name_from_url("file:///home/user/lake.zip"), "lake");
+        assert_eq!(LakeSource::name_from_url(DEFAULT_LAKE_URL), "wami-templates");
+    }
+
+    #[test]
+    fn test_lake_of_another_host() {
+        let gitlab_url = "https://gitlab.acme.local/red/wami-lake/-/archive/main/wami-lake-main.zip";
+        assert_eq!(LakeSource::new("a", gitlab_url, 0).kind().unwrap(), LakeKind::Remote(Backend::GitLab));
+        assert_eq!(LakeSource::name_from_url(gitlab_url), "wami-lake");
+        assert_eq!(LakeSource::name_from_url("https://files.acme.local/wami/internal.zip"), "internal");
+
+        // A Gitea path on a host, which only serves files, is set to a plain download in the config.yaml.
+        let mut lake = LakeSource::new("a", "https://files.acme.local/wami/lake/archive/v2.zip", 0);
+        assert_eq!(lake.kind().unwrap(), LakeKind::Remote(Backend::Gitea));
+        lake.backend = Some(Backend::Https);
+        assert_eq!(lake.kind().unwrap(), LakeKind::Remote(Backend::Https));
+        assert!(!serde_yaml::to_string(&LakeSource::new("a", gitlab_url, 0)).unwrap().contains("backend"));
+        assert!(serde_yaml::to_string(&lake).unwrap().contains("backend: https"));
+
+        // A git host needs the repository and the branch.
+        lake.backend = Some(Backend::GitLab);
+        assert!(lake.kind().is_err());
+    }
+
+    #[test]
+    fn test_lake_from_a_local_path() {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("wami-config-test-local-{}", std::process::id()));
+        fs::create_dir_all(dir.join("my-templates").join("lake")).unwrap();
+
+        let lake = LakeSource::from_location(dir.join("my-templates").to_str().unwrap(), None, 5, None).unwrap();
+        assert_eq!(lake.name, "my-templates");
+        assert!(lake.url.starts_with("file:///"));
+        assert_eq!(lake.priority, 5);
+        // The templates of a checkout are in its lake dir.
+        assert_eq!(lake.get_lake_dir().unwrap(), fs::canonicalize(dir.join("my-templates").join("lake")).unwrap());
+
+        assert!(LakeSource::from_location(dir.join("missing").to_str().unwrap(), None, 0, None).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_config_add_lake_checks_name_and_url() {
+        let mut config = config();
+
+        assert!(config.add_lake(LakeSource::new("internal", DEFAULT_LAKE_URL, 0)).is_err());
+        assert!(config.add_lake(LakeSource::new("my lake", DEFAULT_LAKE_URL, 0)).is_err());
+        assert!(config.add_lake(LakeSource::new("other", "ftp://example.com/lake.zip", 0)).is_err());
+        assert_eq!(config.lakes.len(), 2);
+        // Any zip file of a web server can be a lake.
+        assert!(config.add_lake(LakeSource::new("other", "https://example.com/lake.zip", 0)).is_ok());
+        assert_eq!(config.remove_lake("other").unwrap().backend, None);
+        assert_eq!(config.remove_lake("internal").unwrap().priority, 10);
+        assert!(config.remove_lake("internal").is_err());
+        assert!(config.remove_lake("wami-templates").is_err());
     }
 }
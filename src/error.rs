@@ -0,0 +1,143 @@
+use std::{fmt, io, path::PathBuf};
+
+// This is the error of wami.
+// Every error has its own exit code, so scripts can tell e.g. a search without results
+// apart from a network which is down. The exit codes are documented in the README.
+
+#[derive(Debug)]
+pub enum WamiError {
+    NoResults,                                              // The search found nothing, this is no failure of wami.
+    InvalidInput(String),                                   // A query, a weight or another value of the user is invalid.
+    UnknownId { id: String, suggestions: Vec<String> },     // There is no template with the id in the lake.
+    Network(String),                                        // The lake or GitHub can not be reached.
+    Config(String),                                         // The config.yaml can not be read or written or has an invalid value.
+    LakeCorrupt(String),                                    // The lake dir or the downloaded archive is broken.
+    TemplateParse { path: PathBuf, message: String },       // A yaml file of the lake is no valid template.
+    GithubApi { status: u16, message: String },             // The GitHub API answered with an error.
+    RateLimit { reset: Option<u64> },                       // The GitHub API limit is used up until the reset time.
+    Io(io::Error),                                          // Any other error of the file system or the terminal.
+}
+
+pub type Result<T> = std::result::Result<T, WamiError>;
+
+impl WamiError {
+    // The exit codes of wami, 0 is a success.
+    // The code 2 is also used by the command line parser for invalid arguments.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            WamiError::NoResults => 1,
+            WamiError::InvalidInput(_) => 2,
+            WamiError::UnknownId { .. } => 3,
+            WamiError::Network(_) => 4,
+            WamiError::Config(_) => 5,
+            WamiError::LakeCorrupt(_) => 6,
+            WamiError::TemplateParse { .. } => 7,
+            WamiError::GithubApi { .. } => 8,
+            WamiError::RateLimit { .. } => 9,
+            WamiError::Io(_) => 10,
+        }
+    }
+}
+
+impl fmt::Display for WamiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WamiError::NoResults => write!(f, "Nothing was found."),
+            WamiError::InvalidInput(message) => write!(f, "{}", message),
+            WamiError::UnknownId { id, suggestions } if suggestions.is_empty() => {
+                write!(f, "There is no template with the id {} in the lake.", id)
+            }
+            WamiError::UnknownId { id, suggestions } => write!(
+                f,
+                "There is no template with the id {} in the lake.\nDid you mean {}?",
+                id,
+                suggestions.join(", ")
+            ),
+            WamiError::Network(message) => write!(f, "Network error: {}", message),
+            WamiError::Config(message) => write!(f, "Config error: {}", message),
+            WamiError::LakeCorrupt(message) => write!(f, "The lake is corrupt: {}", message),
+            WamiError::TemplateParse { path, message } => {
+                write!(f, "Failed to parse the template {}: {}", path.display(), message)
+            }
+            WamiError::GithubApi { status, message } => write!(f, "GitHub API error {}: {}", status, message),
+            WamiError::RateLimit { reset: Some(reset) } => write!(
+                f,
+                "The rate limit of the GitHub API is reached, it is reset at {} (unix time).",
+                reset
+            ),
+            WamiError::RateLimit { reset: None } => write!(f, "The rate limit of the GitHub API is reached."),
+            WamiError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for WamiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WamiError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for WamiError {
+    fn from(err: io::Error) -> WamiError {
+        WamiError::Io(err)
+    }
+}
+
+impl From<isahc::Error> for WamiError {
+    fn from(err: isahc::Error) -> WamiError {
+        WamiError::Network(err.to_string())
+    }
+}
+
+impl From<reqwest::Error> for WamiError {
+    fn from(err: reqwest::Error) -> WamiError {
+        WamiError::Network(err.to_string())
+    }
+}
+
+impl From<zip::result::ZipError> for WamiError {
+    fn from(err: zip::result::ZipError) -> WamiError {
+        WamiError::LakeCorrupt(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes_are_unique() {
+        let errors = vec![
+            WamiError::NoResults,
+            WamiError::InvalidInput(String::new()),
+            WamiError::UnknownId { id: String::new(), suggestions: vec![] },
+            WamiError::Network(String::new()),
+            WamiError::Config(String::new()),
+            WamiError::LakeCorrupt(String::new()),
+            WamiError::TemplateParse { path: PathBuf::new(), message: String::new() },
+            WamiError::GithubApi { status: 500, message: String::new() },
+            WamiError::RateLimit { reset: None },
+            WamiError::Io(io::Error::other("")),
+        ];
+        let mut codes: Vec<i32> = errors.iter().map(WamiError::exit_code).collect();
+        codes.dedup();
+
+        assert_eq!(codes, (1..=10).collect::<Vec<i32>>());
+    }
+
+    #[test]
+    fn test_display_unknown_id() {
+        let error = WamiError::UnknownId {
+            id: "nmpa".to_string(),
+            suggestions: vec!["nmap".to_string(), "nikto".to_string()],
+        };
+
+        assert_eq!(
+            error.to_string(),
+            "There is no template with the id nmpa in the lake.\nDid you mean nmap, nikto?"
+        );
+    }
+}
@@ -1,21 +1,20 @@
-use crate::search::Search;
+use crate::{error::{Result, WamiError}, output::Hit, search::Search};
 
 use colored::Colorize;
-use isahc;
-use isahc::ReadResponseExt;
+use isahc::{http::HeaderMap, ReadResponseExt};
 use serde::Deserialize;
-use std::error::Error;
+use serde_json::Value;
 
 // This is the structure that will save the information of a repositories.
 #[derive(Debug, Deserialize)]
-struct GitHubRepositories {
-    name: String,
-    html_url: String,
-    description: String,
-    updated_at: String,
-    stargazers_count: u64,
-    topics: Vec<String>,
-    score: f64
+pub struct GitHubRepositories {
+    pub name: String,
+    pub html_url: String,
+    pub description: String,
+    pub updated_at: String,
+    pub stargazers_count: u64,
+    pub topics: Vec<String>,
+    pub score: f64
 }
 
 // This is the main structure,
@@ -26,7 +25,7 @@ pub struct GithubSearch {
 }
 
 impl GithubSearch {
-    pub fn new(in_search: Search) -> Result<Self, Box<dyn Error>> {
+    pub fn new(in_search: Search) -> Result<Self> {
         let base_url = "https://api.github.com/search/repositories";
         // Setting up the required url value for the search at api github.
         let in_tags = in_search.tags_get().join(",");
@@ -43,40 +42,42 @@ impl GithubSearch {
         // Add the base url to the query
         let url = format!("{}?{}", base_url, query_stage_final);
 
-        // Get the response
-        let mut response = isahc::get(url.to_owned())?;
-
-        if response.status().is_success() {
-            // First make an string
-            let tmp_body: String = response.text()?;
-            
-            // Replace all NULL with ""
-            let finalized_body: String = tmp_body.replace(": null", ": \"\"");
-            
-            // Then get the response body
-            let response_body: Result<GithubSearch, serde_json::Error> = serde_json::from_str(&finalized_body);
-
-            match response_body {
-                Ok(search_result) => {
-                    // If everything is ok return search_result
-                    Ok(search_result)
-                },
-                Err(err) => {
-                    // By error return error
-                    println!("Error: serde_json {:?}", err);
-                    Err(err.into())
-                },
-            }
-        } else {
-            // If it is not successful then print an error.
-            println!("Error: at GitHub API-Request");
-            println!("URL: {}", url);
-            println!("Base_URL: {}", base_url);
-            println!("in_search: {}", in_search.title_get());
-            println!("response.status(): {}", response.status());
-            Err("Error: at GitHub API-Request".into())
-        }
+        // Replace all NULL with "", so the repositories without a description can be read.
+        let body: String = get_github_api(&url)?.replace(": null", ": \"\"");
+        serde_json::from_str(&body).map_err(|err| WamiError::GithubApi {
+            status: 200,
+            message: err.to_string(),
+        })
+    }
+
+    // The repositories in the order of the GitHub API.
+    pub fn iter(&self) -> std::slice::Iter<'_, GitHubRepositories> {
+        self.items.iter()
+    }
+
+    // The repositories as a list for the machine readable output, in the order of the GitHub API.
+    pub fn hits(&self, in_max_list: usize) -> Vec<Hit> {
+        self.items
+            .iter()
+            .take(in_max_list)
+            .enumerate()
+            .map(|(index, item)| Hit {
+                rank: index + 1,
+                score: item.score as f32,
+                id: item.name.to_owned(),
+                title: item.name.to_owned(),
+                tags: item.topics.to_owned(),
+                description: item.description.to_owned(),
+                references: vec![item.html_url.to_owned()],
+                why_not: Vec::new(),
+                lake: None,
+                stars: Some(item.stargazers_count),
+                topics: Some(item.topics.to_owned()),
+                updated_at: Some(item.updated_at.to_owned()),
+            })
+            .collect()
     }
+
     pub fn to_string(&self, in_max_list: usize, in_sort_value: &str, in_show_all: bool) -> String {
         let mut out_string: String = String::new();
         let mut count: usize;
@@ -102,10 +103,10 @@ impl GithubSearch {
                 if count <= in_max_list
                 {
                     if in_show_all {
-                       out_string.push_str(&print_long(count, item));
+                        out_string.push_str(&print_long(count, item));
+                    } else {
+                        out_string.push_str(&print_short(count, item));
                     }
-                } else {
-                    out_string.push_str(&print_short(count, item));
                 }
                 count -= 1;
             }
@@ -115,6 +116,41 @@ impl GithubSearch {
 }
 
 
+// Send a request to the GitHub API and return the body of the response.
+// A used up rate limit is its own error, so scripts can wait for the reset instead of failing.
+pub fn get_github_api(in_url: &str) -> Result<String> {
+    let mut response = isahc::get(in_url)?;
+    let status: u16 = response.status().as_u16();
+    let body: String = response.text()?;
+    check_github_response(status, response.headers(), &body)?;
+    Ok(body)
+}
+
+// Turn an error response of the GitHub API into an error.
+// GitHub answers 403 with no remaining requests or 429, if the rate limit is used up.
+fn check_github_response(in_status: u16, in_headers: &HeaderMap, in_body: &str) -> Result<()> {
+    if (200..300).contains(&in_status) {
+        return Ok(());
+    }
+
+    let header = |in_name: &str| -> Option<u64> {
+        in_headers
+            .get(in_name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+    };
+    if in_status == 429 || (in_status == 403 && header("x-ratelimit-remaining") == Some(0)) {
+        return Err(WamiError::RateLimit { reset: header("x-ratelimit-reset") });
+    }
+
+    // The API explains the error in the message of the json body.
+    let message: String = serde_json::from_str::<Value>(in_body)
+        .ok()
+        .and_then(|body| body["message"].as_str().map(|message| message.to_owned()))
+        .unwrap_or_else(|| in_body.trim().to_owned());
+    Err(WamiError::GithubApi { status: in_status, message })
+}
+
 fn print_short(count: usize, in_item: &GitHubRepositories) -> String {
     let out_string = format!("{} {}\n  {}\n", 
         &count.to_string().magenta(),
@@ -161,9 +197,54 @@ fn print_long(count: usize, in_item: &GitHubRepositories) -> String {
 // This is used to cut an string to the max_length value.
 fn cut_of_string(input: &str, max_length: usize) -> String {
     if input.chars().count() <= max_length {
-        return input.to_string()
+        input.to_string()
     } else {
         let cut_of_string: String = input.chars().take(max_length).collect();
-        return cut_of_string
+        cut_of_string
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use isahc::http::HeaderValue;
+
+    fn headers(in_headers: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut out_headers = HeaderMap::new();
+        for (name, value) in in_headers {
+            out_headers.insert(*name, HeaderValue::from_static(value));
+        }
+        out_headers
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_github_response_success() {
+        assert!(check_github_response(200, &headers(&[]), "{}").is_ok());
+    }
+
+    #[test]
+    fn test_github_response_rate_limit() {
+        let limited = headers(&[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset", "1700000000")]);
+        assert!(matches!(
+            check_github_response(403, &limited, "{}"),
+            Err(WamiError::RateLimit { reset: Some(1700000000) })
+        ));
+        assert!(matches!(
+            check_github_response(429, &headers(&[]), ""),
+            Err(WamiError::RateLimit { reset: None })
+        ));
+    }
+
+    #[test]
+    fn test_github_response_api_error() {
+        // A 403 with remaining requests is no rate limit.
+        let error = check_github_response(
+            403,
+            &headers(&[("x-ratelimit-remaining", "12")]),
+            r#"{"message": "Resource not accessible"}"#,
+        );
+        assert!(matches!(
+            error,
+            Err(WamiError::GithubApi { status: 403, ref message }) if message == "Resource not accessible"
+        ));
+    }
+}
@@ -1,6 +1,5 @@
 use colored::Colorize;
-use isahc;
-use isahc::ReadResponseExt;
+use crate::{error::{Result, WamiError}, github_search::get_github_api};
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize)]
@@ -12,34 +11,25 @@ struct GitHubTopic {
     name: String,
 }
 
-pub fn get_github_topics(in_list: Vec<String>) -> Result<(), isahc::Error> {
+// Search the topics of GitHub, which contain the words.
+pub fn get_github_topics(in_list: Vec<String>) -> Result<Vec<String>> {
     let query: String = String::from("q=").to_owned() + &in_list.join(",");
     let url = format!("https://api.github.com/search/topics?{}", query);
 
-    let mut response = isahc::get(&url.to_string())?;
-
-    if response.status().is_success() {
-        let body = response.text()?;
-
-        let response_body: Result<Body, serde_json::Error> = serde_json::from_str(&body);
-
-        match response_body {
-            Ok(response_body) => {
-                let mut count = 1;
-                for item in response_body.items {
-                    let tmp_out_string=format!("{} {}", count.to_string().magenta(), item.name.green() );
-                    println!("{}", tmp_out_string);
-                    count += 1;
-                }
-            }
-            Err(err) => {
-                println!("Error deserializing JSON: {}", err);
-            }
-        }
-        
-    } else {
-        println!("Request was not successful. Status code: {:?}", response.status());
-    }
+    let body: String = get_github_api(&url)?;
+    let response_body: Body = serde_json::from_str(&body).map_err(|err| WamiError::GithubApi {
+        status: 200,
+        message: err.to_string(),
+    })?;
+    Ok(response_body.items.into_iter().map(|item| item.name).collect())
+}
 
-    Ok(())
-}
\ No newline at end of file
+// The topics as a numbered list for the console.
+pub fn topics_to_string(in_topics: &[String]) -> String {
+    in_topics
+        .iter()
+        .enumerate()
+        .map(|(index, topic)| format!("{} {}", (index + 1).to_string().magenta(), topic.green()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,232 @@
+use crate::template::Template;
+use std::collections::{HashMap, VecDeque};
+
+// This is the graph of the alternatives in the lake.
+// Every why_not entry of a template, which is the id of another template, is an edge to this template.
+// Entries which are no id of the lake are dangling, they are reported but not part of the graph.
+// Links to websites are allowed in why_not, so they are not dangling.
+
+// An alternative found by walking the graph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alternative {
+    pub position: usize, // The position of the template in the lake.
+    pub depth: usize,    // The number of steps from the start template.
+}
+
+// A why_not entry which is not the id of a template of the lake.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dangling {
+    pub id: String,      // The template with the entry.
+    pub why_not: String, // The unknown id.
+}
+
+#[derive(Debug)]
+pub struct Graph {
+    edges: Vec<Vec<usize>>, // The positions of the alternatives of every template.
+    dangling: Vec<Dangling>,
+}
+
+impl Graph {
+    // Resolve the why_not entries of all templates.
+    pub fn new(in_templates: &[Template]) -> Graph {
+        let positions: HashMap<&str, usize> = in_templates
+            .iter()
+            .enumerate()
+            .map(|(position, template)| (template.id(), position))
+            .collect();
+
+        let mut edges: Vec<Vec<usize>> = Vec::with_capacity(in_templates.len());
+        let mut dangling: Vec<Dangling> = Vec::new();
+        for template in in_templates {
+            let mut alternatives: Vec<usize> = Vec::new();
+            for why_not in template.why_not() {
+                match positions.get(why_not.trim()) {
+                    Some(position) if !alternatives.contains(position) => alternatives.push(*position),
+                    Some(_) => {}
+                    None if Graph::is_link(why_not) => {}
+                    None => dangling.push(Dangling {
+                        id: template.id().to_owned(),
+                        why_not: why_not.to_owned(),
+                    }),
+                }
+            }
+            edges.push(alternatives);
+        }
+
+        Graph { edges, dangling }
+    }
+
+    // A why_not entry can also be a link to a website.
+    fn is_link(in_why_not: &str) -> bool {
+        in_why_not.contains("://")
+    }
+
+    pub fn dangling(&self) -> &[Dangling] {
+        &self.dangling
+    }
+
+    // Walk the graph from a template up to the given depth.
+    // Every alternative is found once with its shortest depth, the start template is no alternative.
+    pub fn walk(&self, in_start: usize, in_depth: usize) -> Vec<Alternative> {
+        let mut out_alternatives: Vec<Alternative> = Vec::new();
+        let mut visited: Vec<bool> = vec![false; self.edges.len()];
+        let mut queue: VecDeque<(usize, usize)> = VecDeque::new();
+        visited[in_start] = true;
+        queue.push_back((in_start, 0));
+
+        while let Some((position, depth)) = queue.pop_front() {
+            if depth >= in_depth {
+                continue;
+            }
+            for alternative in &self.edges[position] {
+                if !visited[*alternative] {
+                    visited[*alternative] = true;
+                    out_alternatives.push(Alternative {
+                        position: *alternative,
+                        depth: depth + 1,
+                    });
+                    queue.push_back((*alternative, depth + 1));
+                }
+            }
+        }
+
+        out_alternatives
+    }
+
+    // Export the graph in the dot language of graphviz.
+    pub fn to_dot(&self, in_templates: &[Template]) -> String {
+        let mut out_string = String::from("digraph alternatives {\n");
+        for template in in_templates {
+            out_string.push_str(&format!(
+                "    \"{}\" [label=\"{}\"];\n",
+                template.id(),
+                template.title().replace('\\', "\\\\").replace('"', "\\\"")
+            ));
+        }
+        for (position, alternatives) in self.edges.iter().enumerate() {
+            for alternative in alternatives {
+                out_string.push_str(&format!(
+                    "    \"{}\" -> \"{}\";\n",
+                    in_templates[position].id(),
+                    in_templates[*alternative].id()
+                ));
+            }
+        }
+        out_string.push('}');
+        out_string
+    }
+
+    // Export the graph as a mermaid flowchart.
+    // The ids are used as node names, the hyphens are replaced because mermaid reads them as edges.
+    pub fn to_mermaid(&self, in_templates: &[Template]) -> String {
+        let node = |in_template: &Template| in_template.id().replace('-', "_");
+
+        let mut out_string = String::from("graph LR");
+        for template in in_templates {
+            out_string.push_str(&format!(
+                "\n    {}[\"{}\"]",
+                node(template),
+                template.title().replace('"', "#quot;")
+            ));
+        }
+        for (position, alternatives) in self.edges.iter().enumerate() {
+            for alternative in alternatives {
+                out_string.push_str(&format!(
+                    "\n    {} --> {}",
+                    node(&in_templates[position]),
+                    node(&in_templates[*alternative])
+                ));
+            }
+        }
+        out_string
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(in_id: &str, in_why_not: &[&str]) -> Template {
+        Template::new(
+            in_id.to_string(),
+            format!("{} \"tool\"", in_id),
+            vec![],
+            String::new(),
+            vec![],
+            in_why_not.iter().map(|why_not| why_not.to_string()).collect(),
+        )
+    }
+
+    // nmap -> masscan -> zmap, dig <-> host, dig -> unknown
+    fn templates() -> Vec<Template> {
+        vec![
+            template("nmap", &["masscan", "https://example.com/scanner"]),
+            template("masscan", &["zmap", "nmap"]),
+            template("zmap", &[]),
+            template("dig", &["host", "unknown", "host"]),
+            template("host", &["dig"]),
+        ]
+    }
+
+    fn ids(in_alternatives: &[Alternative], in_templates: &[Template]) -> Vec<(String, usize)> {
+        in_alternatives
+            .iter()
+            .map(|alternative| (in_templates[alternative.position].id().to_string(), alternative.depth))
+            .collect()
+    }
+
+    #[test]
+    fn test_graph_dangling() {
+        let graph = Graph::new(&templates());
+
+        // The link is no dangling id.
+        assert_eq!(
+            graph.dangling(),
+            &[Dangling { id: "dig".to_string(), why_not: "unknown".to_string() }]
+        );
+    }
+
+    #[test]
+    fn test_graph_walk_depth() {
+        let templates = templates();
+        let graph = Graph::new(&templates);
+
+        assert_eq!(ids(&graph.walk(0, 1), &templates), vec![("masscan".to_string(), 1)]);
+        assert_eq!(
+            ids(&graph.walk(0, 3), &templates),
+            vec![("masscan".to_string(), 1), ("zmap".to_string(), 2)]
+        );
+        assert!(graph.walk(0, 0).is_empty());
+    }
+
+    #[test]
+    fn test_graph_walk_cycle() {
+        let templates = templates();
+        let graph = Graph::new(&templates);
+
+        // dig and host name each other, the walk ends anyway.
+        assert_eq!(ids(&graph.walk(3, 10), &templates), vec![("host".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_graph_to_dot() {
+        let templates = vec![template("dig", &["host"]), template("host", &[])];
+        let dot = Graph::new(&templates).to_dot(&templates);
+
+        assert_eq!(
+            dot,
+            "digraph alternatives {\n    \"dig\" [label=\"dig \\\"tool\\\"\"];\n    \"host\" [label=\"host \\\"tool\\\"\"];\n    \"dig\" -> \"host\";\n}"
+        );
+    }
+
+    #[test]
+    fn test_graph_to_mermaid() {
+        let templates = vec![template("enum4linux-ng", &["smbclient"]), template("smbclient", &[])];
+        let mermaid = Graph::new(&templates).to_mermaid(&templates);
+
+        assert_eq!(
+            mermaid,
+            "graph LR\n    enum4linux_ng[\"enum4linux-ng #quot;tool#quot;\"]\n    smbclient[\"smbclient #quot;tool#quot;\"]\n    enum4linux_ng --> smbclient"
+        );
+    }
+}
@@ -0,0 +1,546 @@
+use crate::search::{Search, Weights};
+use crate::template::Template;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// This is the inverted index over all templates of the lake.
+// For every field of a template a separate index is built,
+// so a word in the title can be ranked independently of the same word in a reference.
+// The ranking is done with BM25. The inverse document frequency (IDF)
+// makes words, which are found in many templates like "tool" or "scanner",
+// count less than rare words, which describe a tool much better.
+
+// BM25 term frequency saturation.
+const BM25_K1: f32 = 1.2;
+
+// BM25 length normalization, 0.0 ignores the length of the field, 1.0 fully normalizes it.
+const BM25_B: f32 = 0.75;
+
+// A search term which is not part of the index will be matched against
+// similar words of the index. This is the minimum bigram cosine similarity for such a word.
+const FUZZY_THRESHOLD: f32 = 0.6;
+
+// The searchable fields of a template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Field {
+    Id,
+    Title,
+    Tags,
+    Description,
+    References,
+}
+
+impl Field {
+    pub const ALL: [Field; 5] = [
+        Field::Id,
+        Field::Title,
+        Field::Tags,
+        Field::Description,
+        Field::References,
+    ];
+
+    // The name of the field, as it is used in the config.yaml and on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Field::Id => "id",
+            Field::Title => "title",
+            Field::Tags => "tags",
+            Field::Description => "description",
+            Field::References => "references",
+        }
+    }
+
+    // Find the field by its name.
+    pub fn from_name(in_name: &str) -> Option<Field> {
+        Field::ALL
+            .iter()
+            .find(|field| field.name() == in_name.trim().to_lowercase())
+            .copied()
+    }
+
+    // Get the values of this field from a template.
+    pub fn values(self, in_template: &Template) -> Vec<&str> {
+        match self {
+            Field::Id => vec![in_template.id()],
+            Field::Title => vec![in_template.title()],
+            Field::Tags => in_template.tags().iter().map(|tag| tag.as_str()).collect(),
+            Field::Description => vec![in_template.description()],
+            Field::References => in_template
+                .references()
+                .iter()
+                .map(|reference| reference.as_str())
+                .collect(),
+        }
+    }
+}
+
+// A posting saves in which template a term was found and how often.
+#[derive(Debug, Serialize, Deserialize)]
+struct Posting {
+    template: usize,
+    term_frequency: u32,
+}
+
+// The inverted index of a single field.
+#[derive(Debug, Serialize, Deserialize)]
+struct FieldIndex {
+    postings: HashMap<String, Vec<Posting>>,
+    bigrams: HashMap<String, HashMap<String, f64>>, // The bigrams of every term for the fuzzy search.
+    lengths: Vec<u32>,   // The number of terms per template in this field.
+    average_length: f32, // The average number of terms in this field over all templates.
+}
+
+impl FieldIndex {
+    fn new(in_field: Field, in_templates: &[Template]) -> FieldIndex {
+        // Count the terms of this field in every template, the templates are tokenized in parallel.
+        let counted: Vec<(HashMap<String, u32>, u32)> = in_templates
+            .par_iter()
+            .map(|template| {
+                let mut term_frequencies: HashMap<String, u32> = HashMap::new();
+                let mut length: u32 = 0;
+                for value in in_field.values(template) {
+                    for term in Search::tokenize(value) {
+                        *term_frequencies.entry(term).or_insert(0) += 1;
+                        length += 1;
+                    }
+                }
+                (term_frequencies, length)
+            })
+            .collect();
+
+        // The postings are filled in the order of the templates.
+        let mut postings: HashMap<String, Vec<Posting>> = HashMap::new();
+        let mut lengths: Vec<u32> = Vec::with_capacity(in_templates.len());
+        for (template_index, (term_frequencies, length)) in counted.into_iter().enumerate() {
+            for (term, term_frequency) in term_frequencies {
+                postings.entry(term).or_default().push(Posting {
+                    template: template_index,
+                    term_frequency,
+                });
+            }
+            lengths.push(length);
+        }
+
+        // Templates with an empty field are not part of the average length,
+        // otherwise every template without references would shorten the average for the others.
+        let filled: Vec<u32> = lengths.iter().copied().filter(|length| *length > 0).collect();
+        let average_length = if filled.is_empty() {
+            0.0
+        } else {
+            filled.iter().sum::<u32>() as f32 / filled.len() as f32
+        };
+
+        // The bigrams are created once while building the index and not for every search.
+        let bigrams: HashMap<String, HashMap<String, f64>> = postings
+            .par_iter()
+            .map(|(term, _)| term)
+            .map(|term| (term.to_owned(), Search::create_bigrams(term)))
+            .collect();
+
+        FieldIndex {
+            postings,
+            bigrams,
+            lengths,
+            average_length,
+        }
+    }
+
+    // The inverse document frequency of a term.
+    // The + 1.0 inside the logarithm keeps the value positive for very common terms.
+    fn idf(&self, in_term: &str) -> f32 {
+        let template_count = self.lengths.len() as f32;
+        let document_frequency = self
+            .postings
+            .get(in_term)
+            .map(|postings| postings.len())
+            .unwrap_or(0) as f32;
+        (1.0 + (template_count - document_frequency + 0.5) / (document_frequency + 0.5)).ln()
+    }
+
+    // Find the terms of the index which will be used for a search term.
+    // An exact hit is used as it is, otherwise all similar terms are used
+    // and weighted by their similarity to the search term.
+    fn expand(&self, in_term: &str) -> Vec<(&String, f32)> {
+        if let Some((term, _)) = self.postings.get_key_value(in_term) {
+            return vec![(term, 1.0)];
+        }
+
+        if in_term.is_empty() {
+            return vec![];
+        }
+
+        let search_bigrams = Search::create_bigrams(in_term);
+        self.bigrams
+            .par_iter()
+            .filter_map(|(term, bigrams)| {
+                let similarity = Search::bigram_similarity(bigrams, &search_bigrams);
+                if similarity >= FUZZY_THRESHOLD {
+                    Some((term, similarity))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    // Add the BM25 score of all search terms to the scores of the templates.
+    fn score(&self, in_terms: &[String], out_scores: &mut [f32]) {
+        for search_term in in_terms {
+            // If several similar terms are found in one template, only the best one counts.
+            let mut term_scores: HashMap<usize, f32> = HashMap::new();
+
+            for (term, similarity) in self.expand(search_term) {
+                let idf = self.idf(term);
+                for posting in &self.postings[term] {
+                    let term_frequency = posting.term_frequency as f32;
+                    let length = self.lengths[posting.template] as f32;
+                    let normalization = if self.average_length > 0.0 {
+                        1.0 - BM25_B + BM25_B * length / self.average_length
+                    } else {
+                        1.0
+                    };
+                    let score = similarity * idf * term_frequency * (BM25_K1 + 1.0)
+                        / (term_frequency + BM25_K1 * normalization);
+
+                    let best = term_scores.entry(posting.template).or_insert(0.0);
+                    if score > *best {
+                        *best = score;
+                    }
+                }
+            }
+
+            for (template, score) in term_scores {
+                out_scores[template] += score;
+            }
+        }
+    }
+}
+
+// The score of a single field of a template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldScore {
+    pub field: Field,
+    pub score: f32,  // The BM25 score of the field.
+    pub weight: f32, // The weight of the field in the total score.
+}
+
+// The score of a template.
+// Besides the total, the score of every searched field is saved for the explain mode.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Score {
+    pub fields: Vec<FieldScore>,
+    pub total: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Index {
+    fields: Vec<(Field, FieldIndex)>,
+    template_count: usize,
+}
+
+impl Index {
+    // Build the index over all templates of the lake.
+    pub fn new(in_templates: &[Template]) -> Index {
+        Index {
+            fields: Field::ALL
+                .par_iter()
+                .map(|field| (*field, FieldIndex::new(*field, in_templates)))
+                .collect(),
+            template_count: in_templates.len(),
+        }
+    }
+
+    // Calculate the score of every template for the search.
+    // The returned vector has the same order as the templates the index was built from.
+    // Every field is scored on its own and the fields are averaged with the weights of the search.
+    // Only the fields the user searched for are part of the average,
+    // so a search for tags only is not diluted by the empty title or description search.
+    pub fn score(&self, in_search: &Search) -> Vec<Score> {
+        let mut out_scores: Vec<Score> = vec![Score::default(); self.template_count];
+        let weights: &Weights = in_search.weights_get();
+
+        // Collect the fields with search terms and a weight.
+        let searched_fields: Vec<(Field, &FieldIndex, Vec<String>, f32)> = self
+            .fields
+            .iter()
+            .map(|(field, field_index)| (*field, field_index, in_search.field_terms(*field), weights.get(*field)))
+            .filter(|(_, _, terms, weight)| !terms.is_empty() && *weight > 0.0)
+            .collect();
+
+        // If nothing was searched, nothing can be ranked.
+        let weight_sum: f32 = searched_fields.iter().map(|(_, _, _, weight)| weight).sum();
+        if weight_sum <= 0.0 {
+            return out_scores;
+        }
+
+        // The fields are scored in parallel and added up in their fixed order,
+        // so the totals are the same as with a single thread.
+        let field_scores: Vec<Vec<f32>> = searched_fields
+            .par_iter()
+            .map(|(_, field_index, terms, _)| {
+                let mut field_scores: Vec<f32> = vec![0.0; self.template_count];
+                field_index.score(terms, &mut field_scores);
+                field_scores
+            })
+            .collect();
+
+        for ((field, _, _, weight), field_scores) in searched_fields.into_iter().zip(field_scores) {
+            for (score, field_score) in out_scores.iter_mut().zip(field_scores) {
+                score.fields.push(FieldScore {
+                    field,
+                    score: field_score,
+                    weight,
+                });
+                score.total += field_score * weight / weight_sum;
+            }
+        }
+
+        out_scores
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn totals(in_scores: Vec<Score>) -> Vec<f32> {
+        in_scores.iter().map(|score| score.total).collect()
+    }
+
+    fn template(in_id: &str, in_tags: &[&str], in_description: &str) -> Template {
+        Template::new(
+            in_id.to_string(),
+            in_id.to_string(),
+            in_tags.iter().map(|tag| tag.to_string()).collect(),
+            in_description.to_string(),
+            vec![],
+            vec![],
+        )
+    }
+
+    fn tag_search(in_tags: &[&str]) -> Search {
+        let mut search = Search::new_empty();
+        search.tags_set(&in_tags.iter().map(|tag| tag.to_string()).collect());
+        search
+    }
+
+    #[test]
+    fn test_index_empty_search_scores_zero() {
+        let templates = vec![
+            template("nmap", &["scanner", "network"], "Network scanner."),
+            template("dig", &["dns"], "DNS lookup utility."),
+        ];
+        let index = Index::new(&templates);
+
+        assert_eq!(totals(index.score(&Search::new_empty())), vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_index_empty_lake() {
+        let index = Index::new(&[]);
+
+        assert!(index.score(&tag_search(&["dns"])).is_empty());
+    }
+
+    #[test]
+    fn test_index_rare_term_counts_more_than_common_term() {
+        // Every template is a scanner, but only one knows about dns.
+        let templates = vec![
+            template("nmap", &["scanner"], ""),
+            template("masscan", &["scanner"], ""),
+            template("dnsrecon", &["scanner", "dns"], ""),
+            template("nikto", &["scanner"], ""),
+        ];
+        let index = Index::new(&templates);
+
+        let common = totals(index.score(&tag_search(&["scanner"])));
+        let rare = totals(index.score(&tag_search(&["dns"])));
+
+        // The rare word has a higher weight than the word found in every template.
+        assert!(rare[2] > common[2]);
+
+        // The template with both words is the best hit.
+        let both = totals(index.score(&tag_search(&["scanner", "dns"])));
+        assert!(both[2] > both[0]);
+        assert!(both[2] > both[1]);
+        assert!(both[2] > both[3]);
+    }
+
+    #[test]
+    fn test_index_term_not_found_scores_zero() {
+        let templates = vec![
+            template("nmap", &["scanner"], ""),
+            template("dig", &["dns"], ""),
+        ];
+        let index = Index::new(&templates);
+
+        let scores = totals(index.score(&tag_search(&["dns"])));
+
+        assert_eq!(scores[0], 0.0);
+        assert!(scores[1] > 0.0);
+    }
+
+    #[test]
+    fn test_index_fuzzy_term() {
+        let templates = vec![
+            template("nmap", &["scanner"], ""),
+            template("dig", &["dns"], ""),
+        ];
+        let index = Index::new(&templates);
+
+        // The typo is still similar enough to find the scanner.
+        let scores = totals(index.score(&tag_search(&["scaner"])));
+
+        assert!(scores[0] > 0.0);
+        assert_eq!(scores[1], 0.0);
+    }
+
+    #[test]
+    fn test_index_short_field_ranks_higher() {
+        let templates = vec![
+            template("a", &[], "dns"),
+            template("b", &[], "dns with a lot of other words in the description"),
+        ];
+        let index = Index::new(&templates);
+
+        let mut search = Search::new_empty();
+        search.description_set(&"dns".to_string());
+        let scores = totals(index.score(&search));
+
+        assert!(scores[0] > scores[1]);
+    }
+
+    // The same word is found in the title of one template and in the references of the other one.
+    fn weighted_templates() -> Vec<Template> {
+        vec![
+            Template::new(
+                "a".to_string(),
+                "Zone transfer".to_string(),
+                vec![],
+                "".to_string(),
+                vec!["https://example.com/a".to_string()],
+                vec![],
+            ),
+            Template::new(
+                "b".to_string(),
+                "Lookup".to_string(),
+                vec![],
+                "".to_string(),
+                vec!["https://example.com/zone".to_string()],
+                vec![],
+            ),
+        ]
+    }
+
+    fn zone_search(in_weights: &str) -> Search {
+        let mut search = Search::new_empty();
+        search.title_set(&"zone".to_string());
+        search.reference_set(&vec!["zone".to_string()]);
+        let mut weights = Weights::default();
+        weights.apply_overrides(in_weights).unwrap();
+        search.weights_set(weights);
+        search
+    }
+
+    #[test]
+    fn test_index_weights_title_first() {
+        let templates = weighted_templates();
+        let index = Index::new(&templates);
+
+        let scores = totals(index.score(&zone_search("title=3")));
+
+        assert!(scores[0] > scores[1]);
+    }
+
+    #[test]
+    fn test_index_weights_references_first() {
+        let templates = weighted_templates();
+        let index = Index::new(&templates);
+
+        let scores = totals(index.score(&zone_search("references=3")));
+
+        assert!(scores[1] > scores[0]);
+    }
+
+    #[test]
+    fn test_index_weights_zero_ignores_field() {
+        let templates = weighted_templates();
+        let index = Index::new(&templates);
+
+        let scores = totals(index.score(&zone_search("title=0")));
+
+        assert_eq!(scores[0], 0.0);
+        assert!(scores[1] > 0.0);
+    }
+
+    #[test]
+    fn test_index_weights_all_zero() {
+        let templates = weighted_templates();
+        let index = Index::new(&templates);
+
+        let scores = totals(index.score(&zone_search("id=0,title=0,tags=0,description=0,references=0")));
+
+        assert_eq!(scores, vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_index_unsearched_fields_do_not_dilute_the_score() {
+        let templates = weighted_templates();
+        let index = Index::new(&templates);
+
+        // A search in the title only has the full title score,
+        // the four other fields are not part of the average.
+        let mut title_search = Search::new_empty();
+        title_search.title_set(&"zone".to_string());
+        let title_scores = totals(index.score(&title_search));
+
+        let mut all_search = Search::new_empty();
+        all_search.id_set("zone");
+        all_search.title_set(&"zone".to_string());
+        let all_scores = totals(index.score(&all_search));
+
+        // The id does not match, so searching it as well halves the score.
+        assert!((title_scores[0] - 2.0 * all_scores[0]).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_index_empty_references_do_not_change_the_score() {
+        // The two templates only differ in the references, which are not searched.
+        let with_references = Template::new(
+            "nmap".to_string(),
+            "nmap".to_string(),
+            vec!["scanner".to_string()],
+            "".to_string(),
+            vec!["https://nmap.org".to_string()],
+            vec![],
+        );
+        let templates = vec![with_references, template("masscan", &["scanner"], "")];
+        let index = Index::new(&templates);
+
+        let scores = totals(index.score(&tag_search(&["scanner"])));
+
+        assert_eq!(scores[0], scores[1]);
+    }
+
+    #[test]
+    fn test_index_score_of_the_fields() {
+        let templates = weighted_templates();
+        let index = Index::new(&templates);
+
+        let scores = index.score(&zone_search("title=3"));
+
+        // Only the searched fields are part of the score.
+        let fields: Vec<Field> = scores[0].fields.iter().map(|field| field.field).collect();
+        assert_eq!(fields, vec![Field::Title, Field::References]);
+
+        // The total is the weighted average of the fields.
+        let title = &scores[0].fields[0];
+        let references = &scores[0].fields[1];
+        assert_eq!(title.weight, 3.0);
+        assert_eq!(references.weight, 1.0);
+        assert!(title.score > 0.0);
+        assert_eq!(references.score, 0.0);
+        assert!((scores[0].total - title.score * 3.0 / 4.0).abs() < 0.0001);
+    }
+}
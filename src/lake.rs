@@ -1,341 +1,1466 @@
 use colored::Colorize;
-use crate::{config::Config, search::Search, template::Template, yaml_template};
+use crate::{cache::{Cache, CachedWarning}, config::{Config, LakeKind, LakeSource}, error::{Result, WamiError}, graph::{Alternative, Graph}, index::{Index, Score}, search::{synonyms::Synonyms, NameMatch, Search}, template::Template, yaml_template};
+use rayon::prelude::*;
 use reqwest::Client;
 use std::{
-    fs::{self, File},
-    io::{self, Read},
-    path::PathBuf,
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
 };
-use tokio::fs::create_dir_all;
-use zip::ZipArchive;
+use sha2::{Digest, Sha256};
 
-#[derive(Debug)]
-struct MyError {
-    message: String,
-}
+pub mod archive;
+pub mod backend;
+pub mod manifest;
+pub mod results;
 
-impl std::fmt::Display for MyError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.message)
-    }
-}
+use archive::ZipLimits;
+use backend::Backend;
+use manifest::Manifest;
+use results::SearchResults;
 
-impl std::error::Error for MyError {}
+// The synonyms are saved in the lake dir next to the templates.
+const SYNONYMS_FILE_NAME: &str = "_synonyms.yaml";
+
+// The lower case hex string of a hash.
+fn to_hex(in_bytes: &[u8]) -> String {
+    in_bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
 
 pub struct Lake {
     config: Config,
-    templates: Vec<Template>,
+    templates: Vec<Template>, // All templates of the enabled lakes, in the order of the index.
+    index: Index,
+    synonyms: Synonyms,       // The synonyms of the lakes and the config.
+    warnings: Vec<WamiError>, // Lakes and files, which were skipped because they are broken.
 }
 
 impl Lake {
-    // Initializing the lake.
-    pub fn new(
-        in_url: String,
-        in_update: bool,
-        in_search: Search,
-    ) -> Result<Lake, Box<dyn std::error::Error>> {
-        let mut out_config: Config = Config::new()?;
+    // Open the lakes of the config.yaml, missing lakes are downloaded.
+    // A lake which can not be downloaded is skipped with a warning, as long as another lake can be searched.
+    pub fn open() -> Result<Lake> {
+        let mut config: Config = Config::new()?;
+        let mut warnings: Vec<WamiError> = Vec::new();
 
-        // If in_url is not empty, del the lake dir and set the config url to in_url.
-        // Then do a reload of the config struct.
-        if in_url != "" {
-            out_config.del_lake_dir();
-            out_config.set_new_url(in_url.to_owned());
-            
-            // Download the hash form git to the config.yaml file.
-            match Config::get_git_hash(&out_config.url) {
-                Ok(hash) => {
-                    // Save the url and hash in the config.yaml
-                    out_config.hash = hash;
-                    if let Err(e) = Config::save_to_config_yaml(&out_config.url, &out_config.hash) {
-                        return Err(e);
-                    }    
-                },
-                Err(_err) => {
-                    // Save the url and err for hash in the config.yaml
-                    if let Err(e) = Config::save_to_config_yaml(&out_config.url, &"no_hash".to_owned()) {
-                        return Err(e);
-                    }
+        let mut downloaded: bool = false;
+        let keep_versions: usize = config.keep_versions;
+        let zip_limits: ZipLimits = config.zip_limits;
+        for lake_source in config.lakes.iter_mut().filter(|lake_source| lake_source.enabled) {
+            if !Config::is_dir_present(lake_source.get_lake_dir()?) {
+                match Lake::download(lake_source, keep_versions, &zip_limits) {
+                    Ok(()) => downloaded = true,
+                    Err(err) => warnings.push(err),
                 }
             }
         }
+        // The hashes of the new lakes are saved, the cache belongs to them.
+        if downloaded {
+            config.save()?;
+        }
+
+        Lake::load(config, warnings)
+    }
+
+    // Load the lakes of a config, whose lake dirs are already present.
+    // A broken template or synonyms file does not stop the lake, it is a warning.
+    pub fn from_config(in_config: Config) -> Result<Lake> {
+        Lake::load(in_config, Vec::new())
+    }
+
+    // Load the lakes with the warnings of the downloads.
+    // If no lake can be searched, the first warning is the error.
+    fn load(in_config: Config, mut in_warnings: Vec<WamiError>) -> Result<Lake> {
+        let mut synonyms: Synonyms = Synonyms::default();
+        for lake_source in in_config.enabled_lakes() {
+            match lake_source.get_lake_dir().and_then(|lake_dir| Lake::load_synonyms(&lake_dir)) {
+                Ok(lake_synonyms) => synonyms.extend(lake_synonyms),
+                Err(err) => in_warnings.push(err),
+            }
+        }
+        synonyms.extend(in_config.synonyms.clone());
 
-        // If in_update is set to true, then delete the lake folder.
-        // and reload the config struct.
-        if in_update {
-            // let temp_url = out_config.url;
-            out_config.del_lake_dir();
-            
-            // Download the hash form git to the config.yaml file.
-            match Config::get_git_hash(&out_config.url) {
-                Ok(hash) => {
-                    // Save the url and hash in the config.yaml
-                    if let Err(e) = Config::save_to_config_yaml(&out_config.url, &hash) {
-                        return Err(e);
-                    }    
-                },
-                Err(_err) => {
-                    println!("Version of the lake can not be downloaded.");
-                    // Save the url and err for hash in the config.yaml
-                    if let Err(e) = Config::save_to_config_yaml(&out_config.url, &"no_hash".to_owned()) {
-                        return Err(e);
+        let (templates, index) = match Lake::load_lakes(&in_config, &mut in_warnings) {
+            Ok(lakes) => lakes,
+            Err(err) if in_warnings.is_empty() => return Err(err),
+            Err(_) => return Err(in_warnings.remove(0)),
+        };
+        Ok(Lake {
+            config: in_config,
+            templates,
+            index,
+            synonyms,
+            warnings: in_warnings,
+        })
+    }
+
+    // Search the lake.
+    // The templates are ranked with the index of the whole lake, templates which do not pass
+    // the filters of the query are removed afterwards, so the ranking still uses the statistics of the whole lake.
+    pub fn search(&self, mut in_search: Search) -> SearchResults {
+        // The synonyms of the lake and the config are added to the search terms.
+        if *in_search.use_synonyms_get() {
+            in_search.synonyms_set(self.synonyms.clone());
+        }
+
+        let mut out_templates: Vec<Template> = self.templates.clone();
+        Lake::score_templates(&mut out_templates, &self.index, &in_search);
+        out_templates.retain(|template| in_search.matches(template));
+
+        // If the user named a tool, it will be the first hit.
+        let name_match: Option<NameMatch> = in_search.find_name(&out_templates);
+        let hits: usize = Lake::rank_templates(&mut out_templates, &name_match);
+        // Without words every template has the score 0, a search which only filters keeps them all.
+        if !in_search.is_filter_only() {
+            out_templates.truncate(hits);
+        }
+
+        // The lake of a hit is only interesting, if there are several lakes.
+        SearchResults::new(out_templates, name_match, self.config.enabled_lakes().len() > 1)
+    }
+
+    pub fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn get_templates(&self) -> &[Template] {
+        &self.templates
+    }
+
+    // The broken lakes and files, which were skipped while the lake was loaded.
+    pub fn get_warnings(&self) -> &[WamiError] {
+        &self.warnings
+    }
+
+    // The parts of the lake for the interactive browser, which ranks the templates itself.
+    pub fn into_parts(self) -> (Vec<Template>, Index, Synonyms) {
+        (self.templates, self.index, self.synonyms)
+    }
+
+    // Download a lake and set the hash of the downloaded version.
+    // A lake of a git host has the commit of its branch, a plain zip file its ETag or Last-Modified header
+    // and a local lake the hash of its content.
+    // The files of a local dir are read directly, so only the hash is set.
+    // The new version is staged and replaces the lake only if it is complete, the old version is kept
+    // for `wami lake rollback`, see Lake::install_archive. The hash is only set, if the lake was replaced.
+    // The config.yaml is not saved here.
+    pub fn download(in_lake_source: &mut LakeSource, in_keep_versions: usize, in_zip_limits: &ZipLimits) -> Result<()> {
+        match in_lake_source.kind()? {
+            LakeKind::Remote(backend) => {
+                let runtime = tokio::runtime::Runtime::new()?;
+                let archive_url: String = in_lake_source.get_archive_url()?;
+                let bytes: Vec<u8> = runtime.block_on(Lake::load_zip_from_url(&archive_url, backend))?;
+                let signature: Option<String> = match in_lake_source.public_key {
+                    Some(_) => {
+                        let signature_url: String = archive_url.to_owned() + manifest::SIGNATURE_EXTENSION;
+                        let signature: Vec<u8> = runtime
+                            .block_on(Lake::load_zip_from_url(&signature_url, backend))
+                            .map_err(|err| Lake::missing_signature(in_lake_source, &signature_url, err))?;
+                        Some(String::from_utf8_lossy(&signature).to_string())
                     }
+                    None => None,
+                };
+
+                // If the version can not be downloaded, no_hash is saved, so the lake is outdated.
+                // The version of a pinned lake is its commit.
+                let version: String = match &in_lake_source.pin {
+                    Some(pin) => pin.to_owned(),
+                    None => backend.get_version(&in_lake_source.url).unwrap_or_else(|_| "no_hash".to_owned()),
+                };
+                Lake::install_archive(in_lake_source, &bytes, signature.as_deref(), &version, in_keep_versions, in_zip_limits)?;
+                in_lake_source.hash = version;
+            }
+            LakeKind::LocalZip(path) => {
+                let bytes: Vec<u8> = fs::read(&path)
+                    .map_err(|err| WamiError::LakeCorrupt(format!("Failed to read {}: {}", path.display(), err)))?;
+                let signature: Option<String> = match in_lake_source.public_key {
+                    Some(_) => {
+                        let mut signature_path = path.clone().into_os_string();
+                        signature_path.push(manifest::SIGNATURE_EXTENSION);
+                        let signature: String = fs::read_to_string(&signature_path)
+                            .map_err(|err| Lake::missing_signature(in_lake_source, &signature_path.to_string_lossy(), err.into()))?;
+                        Some(signature)
+                    }
+                    None => None,
+                };
+                let version: String = to_hex(&Sha256::digest(&bytes));
+                Lake::install_archive(in_lake_source, &bytes, signature.as_deref(), &version, in_keep_versions, in_zip_limits)?;
+                in_lake_source.hash = version;
+            }
+            LakeKind::LocalDir(path) => {
+                // The files of a local dir are read directly, there is no archive which can be signed.
+                if in_lake_source.public_key.is_some() {
+                    return Err(WamiError::Config(format!(
+                        "The lake {} is a local dir, only a zip file can be signed.",
+                        in_lake_source.name
+                    )));
                 }
+                in_lake_source.hash = Lake::hash_lake_dir(&LakeSource::find_lake_dir(&path))?;
             }
+        }
+        Ok(())
+    }
+
+    // Install a new version of a lake.
+    // The archive is extracted to the staging dir and checked there, so a failed download or a broken
+    // template never touches the lake. The complete version replaces the download dir with a rename,
+    // the old version is moved to the versions dir and only the newest versions are kept.
+    fn install_archive(
+        in_lake_source: &LakeSource,
+        in_bytes: &[u8],
+        in_signature: Option<&str>,
+        in_version: &str,
+        in_keep_versions: usize,
+        in_zip_limits: &ZipLimits,
+    ) -> Result<()> {
+        let staging_dir: PathBuf = in_lake_source.get_staging_dir();
+        Lake::remove_dir(&staging_dir)?;
+        if let Err(err) = Lake::stage_archive(in_lake_source, &staging_dir, in_bytes, in_signature, in_version, in_zip_limits) {
+            let _ = Lake::remove_dir(&staging_dir);
+            return Err(err);
+        }
+
+        Lake::swap_version(
+            &staging_dir,
+            &in_lake_source.get_download_dir(),
+            &in_lake_source.get_versions_dir(),
+            in_keep_versions,
+        )
+    }
+
+    // Replace the download dir with the staged version.
+    // A dir can not be replaced by a rename on every system, so the current version is moved to the versions dir first.
+    // If the staged version can not be moved in, the current version is moved back, so the lake is never missing.
+    // The oldest versions are only deleted, when the new version is in place.
+    fn swap_version(in_staging_dir: &Path, in_download_dir: &Path, in_versions_dir: &Path, in_keep_versions: usize) -> Result<()> {
+        let current_version: Option<PathBuf> = Lake::set_version_aside(in_download_dir, in_versions_dir)?;
+        if let Err(err) = fs::rename(in_staging_dir, in_download_dir) {
+            if let Some(current_version) = current_version {
+                let _ = fs::rename(current_version, in_download_dir);
+            }
+            return Err(err.into());
+        }
+        Lake::prune_versions(in_versions_dir, in_keep_versions)
+    }
+
+    // Move the current version of a lake to its versions dir and delete the oldest versions,
+    // until only the given number of versions is left.
+    fn keep_version(in_lake_source: &LakeSource, in_keep_versions: usize) -> Result<()> {
+        let versions_dir: PathBuf = in_lake_source.get_versions_dir();
+        Lake::set_version_aside(&in_lake_source.get_download_dir(), &versions_dir)?;
+        Lake::prune_versions(&versions_dir, in_keep_versions)
+    }
+
+    // Move the download dir to a new dir in the versions dir, the new dir is returned.
+    // A lake which was never downloaded has no version to keep.
+    fn set_version_aside(in_download_dir: &Path, in_versions_dir: &Path) -> Result<Option<PathBuf>> {
+        if !in_download_dir.exists() {
+            return Ok(None);
+        }
+        fs::create_dir_all(in_versions_dir)?;
+        let out_version_dir: PathBuf = in_versions_dir.join(Lake::version_dir_name(in_versions_dir));
+        fs::rename(in_download_dir, &out_version_dir)?;
+        Ok(Some(out_version_dir))
+    }
+
+    // Delete the oldest versions, until only the given number of versions is left.
+    fn prune_versions(in_versions_dir: &Path, in_keep_versions: usize) -> Result<()> {
+        let versions: Vec<PathBuf> = LakeSource::get_versions_in(in_versions_dir);
+        for version in versions.iter().take(versions.len().saturating_sub(in_keep_versions)) {
+            Lake::remove_dir(version)?;
+        }
+        Ok(())
+    }
+
+    // Check the signature of an archive, extract it to the staging dir and save its manifest.
+    // With a public key in the config.yaml, an archive without a valid signature is not extracted at all.
+    // Every template of the new version has to be valid, a lake with a broken template is not installed.
+    // The archive and its signature are kept next to the manifest for `wami lake verify`.
+    fn stage_archive(
+        in_lake_source: &LakeSource,
+        in_staging_dir: &Path,
+        in_bytes: &[u8],
+        in_signature: Option<&str>,
+        in_version: &str,
+        in_zip_limits: &ZipLimits,
+    ) -> Result<()> {
+        if let (Some(public_key), Some(signature)) = (&in_lake_source.public_key, in_signature) {
+            manifest::verify_signature(in_bytes, signature, public_key)
+                .map_err(|err| WamiError::LakeCorrupt(format!("The lake {} is not extracted: {}", in_lake_source.name, err)))?;
+        }
+
+        archive::extract_zip(in_bytes, in_staging_dir, in_zip_limits)?;
+        fs::write(in_staging_dir.join(manifest::ARCHIVE_FILE_NAME), in_bytes)?;
+        if let Some(signature) = in_signature {
+            fs::write(in_staging_dir.join(manifest::SIGNATURE_FILE_NAME), signature)?;
+        }
+
+        let lake_dir: PathBuf = LakeSource::find_lake_dir(in_staging_dir);
+        let (_, broken) = Lake::load_templates_from_dir(&lake_dir)?;
+        if let Some(err) = broken.into_iter().next() {
+            return Err(err);
+        }
+
+        let mut manifest: Manifest = Manifest::new(in_bytes, &lake_dir, in_signature.is_some())?;
+        manifest.url = in_lake_source.url.to_owned();
+        manifest.version = in_version.to_owned();
+        manifest.pin = in_lake_source.pin.to_owned();
+        manifest.save(in_staging_dir)
+    }
+
+    // The name of the dir of an old version, it is the time of the update, so the names sort by age.
+    fn version_dir_name(in_versions_dir: &Path) -> String {
+        let seconds: u64 = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        // Two updates in the same second get a counter.
+        let mut out_name: String = format!("{:012}", seconds);
+        let mut count: usize = 1;
+        while in_versions_dir.join(&out_name).exists() {
+            out_name = format!("{:012}-{:03}", seconds, count);
+            count += 1;
+        }
+        out_name
+    }
+
+    // Replace the download dir with an old version.
+    // The current version is moved to the staging dir first and the old version is moved in with a second rename.
+    // If the old version can not be moved in, the current version is moved back, so the lake is never missing.
+    fn restore_version(in_version_dir: &Path, in_download_dir: &Path, in_staging_dir: &Path) -> Result<()> {
+        Lake::remove_dir(in_staging_dir)?;
+        let has_current_version: bool = in_download_dir.exists();
+        if has_current_version {
+            fs::rename(in_download_dir, in_staging_dir)?;
+        }
+        if let Err(err) = fs::rename(in_version_dir, in_download_dir) {
+            if has_current_version {
+                let _ = fs::rename(in_staging_dir, in_download_dir);
+            }
+            return Err(err.into());
+        }
+        Lake::remove_dir(in_staging_dir)
+    }
+
+    // Delete a dir, if it is there.
+    fn remove_dir(in_dir: &Path) -> Result<()> {
+        match fs::remove_dir_all(in_dir) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
 
-            if let Err(e) = Config::save_to_config_yaml(&out_config.url, &out_config.hash) {
-                return Err(e);
+    // Restore the newest old version of a lake with the hash and the url it was downloaded with.
+    // The current version is deleted, so the next rollback restores the version before.
+    // Without a name the only lake of the config is rolled back.
+    pub fn rollback(in_name: Option<&str>) -> Result<Config> {
+        let mut out_config: Config = Config::new()?;
+        let name: String = Lake::name_or_only_lake(&out_config, in_name, "wami lake rollback NAME")?;
+        let lake_source: &mut LakeSource = out_config.find_lake_mut(&name)?;
+        if let LakeKind::LocalDir(_) = lake_source.kind()? {
+            return Err(WamiError::InvalidInput(format!(
+                "The lake {} is a local dir, it has no old versions.",
+                lake_source.name
+            )));
+        }
+        let Some(version_dir) = lake_source.get_versions().pop() else {
+            return Err(WamiError::InvalidInput(format!("There is no old version of the lake {}.", lake_source.name)));
+        };
+        let manifest: Option<Manifest> = Manifest::load(&version_dir)?;
+
+        Lake::restore_version(&version_dir, &lake_source.get_download_dir(), &lake_source.get_staging_dir())?;
+
+        // A version of an old version of wami has no manifest, so it is outdated.
+        match manifest {
+            Some(manifest) => {
+                lake_source.hash = Some(manifest.version).filter(|version| !version.is_empty()).unwrap_or_else(|| "no_hash".to_owned());
+                if !manifest.url.is_empty() {
+                    lake_source.url = manifest.url;
+                }
+                lake_source.pin = manifest.pin;
             }
-            out_config = Config::new()?;
+            None => {
+                lake_source.hash = "no_hash".to_owned();
+                lake_source.pin = None;
+            }
+        }
+
+        out_config.save()?;
+        Ok(out_config)
+    }
+
+    // The error of a signed lake, whose signature can not be read.
+    // The location is the url or the path the signature was fetched from, next to the archive.
+    fn missing_signature(in_lake_source: &LakeSource, in_location: &str, in_err: WamiError) -> WamiError {
+        WamiError::LakeCorrupt(format!(
+            "The lake {} has a public key, but its signature {} can not be read: {}",
+            in_lake_source.name, in_location, in_err
+        ))
+    }
+
+    // Check a downloaded lake against its manifest and return every problem, see Manifest::verify.
+    // A local dir has no manifest, its files are read directly.
+    pub fn verify(in_lake_source: &LakeSource) -> Result<Vec<String>> {
+        let download_dir: PathBuf = in_lake_source.get_download_dir();
+        match Manifest::load(&download_dir)? {
+            Some(manifest) => manifest.verify(&download_dir, &in_lake_source.get_lake_dir()?, in_lake_source.public_key.as_deref()),
+            None => Ok(vec![format!("There is no manifest, run wami lake update {}.", in_lake_source.name)]),
         }
+    }
 
-        // If the ~/.config/wami/dir_to_lake is not set then load it from the url.
-        if !Config::is_dir_present(out_config.get_lake_dir()) {
-            let runtime = match tokio::runtime::Runtime::new() {
-                Ok(rt) => rt,
-                Err(e) => return Err(Box::new(e)),
+    // The check of a lake on every start, without a public key only the templates of the lake dir are hashed.
+    // A lake with a public key has to be downloaded with its signature.
+    fn check_manifest(in_lake_source: &LakeSource, in_download_dir: &Path, in_lake_dir: &Path) -> Result<()> {
+        let corrupt = |in_problem: String| {
+            WamiError::LakeCorrupt(format!(
+                "The lake {} is skipped, {}. Run wami lake verify {} or wami lake update {}.",
+                in_lake_source.name, in_problem, in_lake_source.name, in_lake_source.name
+            ))
+        };
+        let Some(manifest) = Manifest::load(in_download_dir)? else {
+            // The lakes of old versions of wami have no manifest, the next update adds it.
+            return match in_lake_source.public_key {
+                Some(_) => Err(corrupt("it has no manifest".to_string())),
+                None => Ok(()),
             };
+        };
+        // The manifest is in the dir of the lake, so whoever changes the templates can change the manifest as well.
+        // A signed lake is checked against its archive and the archive against its signature with the pinned key,
+        // the flag of the manifest alone is not trusted.
+        let problems: Vec<String> = match &in_lake_source.public_key {
+            Some(public_key) => manifest.verify(in_download_dir, in_lake_dir, Some(public_key))?,
+            None => manifest.compare_templates(&Manifest::hash_templates(in_lake_dir)?, "the lake dir"),
+        };
+        match problems.first() {
+            Some(problem) => Err(corrupt(problem.to_owned())),
+            None => Ok(()),
+        }
+    }
 
-            let result = runtime.block_on(async { Lake::load_zip_from_url(&out_config).await });
+    // Check if there is a newer version of a lake.
+    // A lake on a server asks its backend for the version, a local zip file is compared by its content.
+    // A local dir is read directly, so it is never outdated, and a pinned lake stays at its commit on purpose.
+    pub fn is_outdated(in_lake_source: &LakeSource) -> Result<bool> {
+        if in_lake_source.pin.is_some() {
+            return Ok(false);
+        }
+        match in_lake_source.kind()? {
+            LakeKind::Remote(backend) => Ok(backend.get_version(&in_lake_source.url)? != in_lake_source.hash),
+            LakeKind::LocalZip(path) => Ok(to_hex(&Sha256::digest(fs::read(&path)?)) != in_lake_source.hash),
+            LakeKind::LocalDir(_) => Ok(false),
+        }
+    }
+
+    // Download the newest version of a lake or of all enabled lakes.
+    // The config.yaml is saved after every lake, so a failed download keeps the hashes of the lakes before.
+    pub fn update(in_name: Option<&str>) -> Result<Config> {
+        let mut out_config: Config = Config::new()?;
+        let names: Vec<String> = match in_name {
+            Some(name) => vec![out_config.find_lake(name)?.name.to_owned()],
+            None => out_config.enabled_lakes().iter().map(|lake_source| lake_source.name.to_owned()).collect(),
+        };
 
-            if let Err(e) = result {
-                return Err(e);
+        // The old version is only replaced by a complete new version, so a failed update keeps the lake and its hash.
+        let keep_versions: usize = out_config.keep_versions;
+        let zip_limits: ZipLimits = out_config.zip_limits;
+        for name in names {
+            let lake_source: &mut LakeSource = out_config.find_lake_mut(&name)?;
+            Lake::download(lake_source, keep_versions, &zip_limits)?;
+            out_config.save()?;
+        }
+        Ok(out_config)
+    }
+
+    // Pin a lake to a commit or a tag of its repository, e.g. for the reproducible tool list of a report.
+    // A tag is resolved to its commit, the archive of the commit is downloaded and the commit is saved as the pin.
+    // A pinned lake is never outdated and `wami lake update` downloads its commit again, until it is unpinned.
+    // Without a name the only lake of the config is pinned.
+    pub fn pin(in_reference: &str, in_name: Option<&str>) -> Result<Config> {
+        let mut out_config: Config = Config::new()?;
+        let name: String = Lake::name_or_only_lake(&out_config, in_name, "--lake NAME")?;
+        let keep_versions: usize = out_config.keep_versions;
+        let zip_limits: ZipLimits = out_config.zip_limits;
+
+        let lake_source: &mut LakeSource = out_config.find_lake_mut(&name)?;
+        let backend: Backend = match lake_source.kind()? {
+            LakeKind::Remote(backend) if backend != Backend::Https => backend,
+            kind => {
+                return Err(WamiError::InvalidInput(format!(
+                    "The lake {} is a {} lake, only the lake of a git repository can be pinned.",
+                    lake_source.name,
+                    kind.name()
+                )))
             }
+        };
+        let commit: String = backend.get_commit(&lake_source.url, in_reference.trim())?;
+
+        // The pin is only saved, if the archive of the commit can be installed.
+        let mut new_lake_source: LakeSource = lake_source.clone();
+        new_lake_source.pin = Some(commit);
+        Lake::download(&mut new_lake_source, keep_versions, &zip_limits)?;
+        *lake_source = new_lake_source;
+
+        out_config.save()?;
+        Ok(out_config)
+    }
+
+    // Remove the pin of a lake and download the newest version of its branch.
+    pub fn unpin(in_name: Option<&str>) -> Result<Config> {
+        let mut out_config: Config = Config::new()?;
+        let name: String = Lake::name_or_only_lake(&out_config, in_name, "wami lake unpin NAME")?;
+        let keep_versions: usize = out_config.keep_versions;
+        let zip_limits: ZipLimits = out_config.zip_limits;
+
+        let lake_source: &mut LakeSource = out_config.find_lake_mut(&name)?;
+        if lake_source.pin.is_none() {
+            return Err(WamiError::InvalidInput(format!("The lake {} is not pinned.", lake_source.name)));
         }
+        let mut new_lake_source: LakeSource = lake_source.clone();
+        new_lake_source.pin = None;
+        Lake::download(&mut new_lake_source, keep_versions, &zip_limits)?;
+        *lake_source = new_lake_source;
 
-        // Creating the lake struct.
-        Ok(Lake {
-            config: out_config.clone(),
-            templates: Lake::load_lake_from_config_dir(out_config, in_search),
-        })
+        out_config.save()?;
+        Ok(out_config)
     }
 
-    pub fn get_config_hash(&self) -> String {
-        self.config.hash.to_owned()
+    // The commit of the branch of a pinned lake and the number of commits the branch is ahead of the pin.
+    pub fn get_upstream(in_lake_source: &LakeSource) -> Result<(String, u64)> {
+        let (Some(pin), LakeKind::Remote(backend)) = (&in_lake_source.pin, in_lake_source.kind()?) else {
+            return Err(WamiError::InvalidInput(format!("The lake {} is not pinned.", in_lake_source.name)));
+        };
+        let head: String = backend.get_version(&in_lake_source.url)?;
+        let distance: u64 = if head == *pin { 0 } else { backend.get_distance(&in_lake_source.url, pin)? };
+        Ok((head, distance))
     }
 
-    pub fn get_config_url(&self) -> String {
-        self.config.url.to_owned()
+    // The name of the lake of a subcommand, without a name it is the only lake of the config.
+    // The hint tells the user how to name the lake for the subcommand.
+    fn name_or_only_lake(in_config: &Config, in_name: Option<&str>, in_hint: &str) -> Result<String> {
+        match in_name {
+            Some(name) => Ok(name.to_owned()),
+            None if in_config.lakes.len() == 1 => Ok(in_config.lakes[0].name.to_owned()),
+            None => Err(WamiError::InvalidInput(format!("There are several lakes, name the lake with {}.", in_hint))),
+        }
     }
 
-    // Sort the template vector in descending order based on distance.
-    pub fn print_top_hits(&mut self, how_many_max: usize, in_sort_value: String, why_not: bool) {
-        let _ = &self
-            .templates
-            .sort_by(|a, b| b.distance().partial_cmp(&a.distance()).unwrap());
+    // Download a lake from a new url and use it from now on.
+    // The url can also be the path of a local dir or zip file.
+    // Without a name the url is set for the only lake of the config.
+    // The lake of the old url is kept as an old version, so `wami lake rollback` restores it.
+    pub fn use_url(in_url: &str, in_name: Option<&str>) -> Result<Config> {
+        let url: String = LakeSource::location_to_url(in_url)?;
+        let mut out_config: Config = Config::new()?;
+        let name: String = Lake::name_or_only_lake(&out_config, in_name, "--lake NAME")?;
+        let keep_versions: usize = out_config.keep_versions;
+        let zip_limits: ZipLimits = out_config.zip_limits;
 
-        // Take as many we want form the top of the sorted templates.
-        let max_hits_templates = &self.templates[..how_many_max.min(self.templates.len())];
+        // The url is checked first, so an invalid url keeps the old lake.
+        let lake_source: &mut LakeSource = out_config.find_lake_mut(&name)?;
+        let mut new_lake_source: LakeSource = lake_source.clone();
+        new_lake_source.set_new_url(&url)?;
+        Lake::download(&mut new_lake_source, keep_versions, &zip_limits)?;
+        // A local dir is not extracted, so the lake of the old url is kept here.
+        if let LakeKind::LocalDir(_) = new_lake_source.kind()? {
+            Lake::keep_version(&new_lake_source, keep_versions)?;
+        }
+        *lake_source = new_lake_source;
 
-        // Out put in the desired order.
-        match in_sort_value.as_str() {
-            "desc" => {
-                for (index, template) in max_hits_templates.iter().rev().enumerate() {
-                    let reverse_index = max_hits_templates.len() - index;
-                    println!("{} {}", reverse_index.to_string().magenta(), template.to_string(why_not));
-                }
-            },
-            _ => {
-                for (index, template) in max_hits_templates.iter().enumerate() {
-                    println!("{} {}",(index + 1).to_string().magenta(), template.to_string(why_not));
-                }
+        out_config.save()?;
+        Ok(out_config)
+    }
+
+    // Add a new lake to the config and download it.
+    // The lake is only saved in the config.yaml, if it can be downloaded.
+    pub fn add(in_lake_source: LakeSource) -> Result<Config> {
+        let mut out_config: Config = Config::new()?;
+        out_config.add_lake(in_lake_source.clone())?;
+
+        let keep_versions: usize = out_config.keep_versions;
+        let zip_limits: ZipLimits = out_config.zip_limits;
+        let lake_source: &mut LakeSource = out_config.find_lake_mut(&in_lake_source.name)?;
+        if let Err(err) = Lake::download(lake_source, keep_versions, &zip_limits) {
+            lake_source.del_lake_dir()?;
+            return Err(err);
+        }
+
+        out_config.save()?;
+        Ok(out_config)
+    }
+
+    // Remove a lake from the config and delete its dir.
+    pub fn remove(in_name: &str) -> Result<Config> {
+        let mut out_config: Config = Config::new()?;
+        let lake_source: LakeSource = out_config.remove_lake(in_name)?;
+        lake_source.del_lake_dir()?;
+
+        out_config.save()?;
+        Ok(out_config)
+    }
+
+    // Set the distance of every template with the index of the whole lake.
+    fn score_templates(in_templates: &mut [Template], in_index: &Index, in_search: &Search) {
+        let scores: Vec<Score> = in_index.score(in_search);
+        for (template, score) in in_templates.iter_mut().zip(scores) {
+            template.set_score(score);
+        }
+    }
+
+    // Sort the templates in descending order based on distance.
+    // Templates with the same distance are sorted by id, so the order does not depend on the file system.
+    fn sort_templates(in_templates: &mut [Template]) {
+        in_templates.sort_by(|a, b| {
+            b.distance()
+                .partial_cmp(&a.distance())
+                .unwrap()
+                .then_with(|| a.id().cmp(b.id()))
+        });
+    }
+
+    // Sort the templates, the named template first, and return the number of hits.
+    // Templates which do not match the search at all are no hits.
+    fn rank_templates(in_templates: &mut Vec<Template>, in_name_match: &Option<NameMatch>) -> usize {
+        Lake::sort_templates(in_templates);
+
+        let mut named: usize = 0;
+        if let Some(name_match) = in_name_match {
+            if let Some(position) = in_templates.iter().position(|template| template.id() == name_match.id) {
+                let template = in_templates.remove(position);
+                in_templates.insert(0, template);
+                named = 1;
             }
         }
+
+        named
+            + in_templates[named..]
+                .iter()
+                .take_while(|template| template.distance() > 0.0)
+                .count()
     }
 
-    // Sort the template vector in descending order based on distance.
-    pub fn print_top_short_list(&mut self, how_many_max: usize, in_sort_value: String, why_not: bool) {
-        let _ = &self
-            .templates
-            .sort_by(|a, b| b.distance().partial_cmp(&a.distance()).unwrap());
+    // Load the enabled lakes of the config and merge them.
+    // If two lakes have a template with the same id, the template of the lake with the higher priority is used.
+    // The templates and the index are taken from the cache, if it still belongs to the lakes.
+    // Otherwise the yaml files are parsed, the index is built and the cache is saved for the next start.
+    // Lakes which are not downloaded are skipped, broken lakes are a warning.
+    fn load_lakes(in_config: &Config, in_warnings: &mut Vec<WamiError>) -> Result<(Vec<Template>, Index)> {
+        let mut lake_dirs: Vec<(&str, PathBuf)> = Vec::new();
+        let mut lake_versions: Vec<String> = Vec::new();
+        for lake_source in in_config.enabled_lakes() {
+            let lake_dir: PathBuf = lake_source.get_lake_dir()?;
+            if Config::is_dir_present(lake_dir.clone()) {
+                // A local dir can change at any time, so its content is hashed on every start.
+                // A downloaded lake is checked against its manifest, a changed lake is not searched.
+                let hash: String = match lake_source.kind()? {
+                    LakeKind::LocalDir(_) => Lake::hash_lake_dir(&lake_dir).unwrap_or_else(|_| lake_source.hash.to_owned()),
+                    _ => {
+                        if let Err(err) = Lake::check_manifest(lake_source, &lake_source.get_download_dir(), &lake_dir) {
+                            in_warnings.push(err);
+                            continue;
+                        }
+                        lake_source.hash.to_owned()
+                    }
+                };
+                lake_versions.push(format!("{}:{}:{}", lake_source.name, lake_source.priority, hash));
+                lake_dirs.push((&lake_source.name, lake_dir));
+            }
+        }
+        if lake_dirs.is_empty() {
+            return Err(WamiError::LakeCorrupt(
+                "There is no downloaded lake, run wami lake update.".to_string(),
+            ));
+        }
+
+        let cache_path: PathBuf = Cache::get_cache_path(&Config::get_lakes_path());
+        let version: String = lake_versions.join(",");
+        let stamps = Cache::stamp_lake_dirs(&lake_dirs);
 
-        // Take as many we want form the top of the sorted templates.
-        let max_hits_templates = &self.templates[..how_many_max.min(self.templates.len())];
+        // The broken files of the lakes are warned about again, they were skipped when the cache was built.
+        if let Some(cache) = Cache::load(&cache_path) {
+            if cache.is_valid(&version, &stamps) {
+                in_warnings.extend(cache.warnings.iter().map(CachedWarning::to_error));
+                return Ok((cache.templates, cache.index));
+            }
+        }
 
-        match in_sort_value.as_str() {
-            "desc" => {
-                for (index, template) in max_hits_templates.iter().rev().enumerate() {
-                    let reverse_index = max_hits_templates.len() - index;
-                    println!("{} {}", reverse_index.to_string().magenta(), template.to_short_string(why_not));
+        let mut lakes: Vec<(&str, Vec<Template>)> = Vec::new();
+        let mut lake_warnings: Vec<WamiError> = Vec::new();
+        for (name, lake_dir) in &lake_dirs {
+            match Lake::load_templates_from_dir(lake_dir) {
+                Ok((lake_templates, broken)) => {
+                    lake_warnings.extend(broken);
+                    lakes.push((name, lake_templates));
                 }
+                Err(err) => lake_warnings.push(err),
             }
-            _ => {
-                for (index, template) in max_hits_templates.iter().enumerate() {
-                    println!("{} {}", (index + 1).to_string().magenta(), template.to_short_string(why_not));
+        }
+        let templates: Vec<Template> = Lake::merge_templates(lakes);
+        if templates.is_empty() {
+            in_warnings.extend(lake_warnings);
+            return Err(WamiError::LakeCorrupt("There are no templates in the lakes.".to_string()));
+        }
+
+        let cache = Cache::new(&version, stamps, templates, &lake_warnings);
+        in_warnings.extend(lake_warnings);
+        if let Err(err) = cache.save(&cache_path) {
+            // The lake can still be used without the cache, it is only slower on the next start.
+            in_warnings.push(err);
+        }
+        Ok((cache.templates, cache.index))
+    }
+
+    // Merge the templates of the lakes, the lake with the highest priority first.
+    // A template whose id is already part of a lake before is skipped, every template keeps the name of its lake.
+    fn merge_templates(in_lakes: Vec<(&str, Vec<Template>)>) -> Vec<Template> {
+        let mut out_templates: Vec<Template> = Vec::new();
+        let mut ids: HashSet<String> = HashSet::new();
+        for (name, templates) in in_lakes {
+            for mut template in templates {
+                if ids.insert(template.id().to_owned()) {
+                    template.set_lake(name);
+                    out_templates.push(template);
                 }
             }
         }
+        out_templates
     }
 
-    // Read the yaml file in the given path.
-    fn read_yaml_file(file_path: &str) -> Result<String, io::Error> {
-        // Open the file
-        let mut file = File::open(file_path)?;
+    // Find a single template by its id.
+    // If there is no template with the id, similar ids are suggested in the error.
+    pub fn find_template(&self, in_id: &str) -> Result<&Template> {
+        let id: String = Template::convert_to_lowercase_alphanumeric_with_hyphens(in_id);
+        self.templates
+            .iter()
+            .find(|template| template.id() == id)
+            .ok_or_else(|| WamiError::UnknownId {
+                suggestions: Search::similar_ids(&id, &self.templates, 5),
+                id: in_id.to_owned(),
+            })
+    }
 
-        // Creating string for the content.
-        let mut contents = String::new();
+    // The alternatives graph of the whole lake.
+    pub fn graph(&self) -> Graph {
+        Graph::new(&self.templates)
+    }
+
+    // Find the alternatives of a template up to the given depth of the alternatives graph.
+    // The alternatives are ranked by the search, alternatives which do not pass its filters are removed.
+    // Without a search, the closest alternatives come first.
+    pub fn alternatives(&self, in_id: &str, in_depth: usize, mut in_search: Search) -> Result<Vec<(Template, usize)>> {
+        if *in_search.use_synonyms_get() {
+            in_search.synonyms_set(self.synonyms.clone());
+        }
+        let start: usize = {
+            let template = self.find_template(in_id)?;
+            self.templates
+                .iter()
+                .position(|other| other.id() == template.id())
+                .unwrap_or_default()
+        };
+
+        let mut templates: Vec<Template> = self.templates.clone();
+        Ok(Lake::rank_alternatives(&mut templates, &self.index, &self.graph(), start, in_depth, &in_search)
+            .into_iter()
+            .map(|alternative| (templates[alternative.position].clone(), alternative.depth))
+            .collect())
+    }
 
-        // Try to read the file content.
-        match file.read_to_string(&mut contents) {
-            Ok(_) => Ok(contents),
-            Err(err) => {
-                eprintln!(
-                    "Error reading the yaml file at path: {} \nError: {}",
-                    file_path, err
-                );
-                Err(err)
+    // Walk the graph from the start template and rank the alternatives with the search.
+    fn rank_alternatives(
+        in_templates: &mut [Template],
+        in_index: &Index,
+        in_graph: &Graph,
+        in_start: usize,
+        in_depth: usize,
+        in_search: &Search,
+    ) -> Vec<Alternative> {
+        Lake::score_templates(in_templates, in_index, in_search);
+        let mut out_alternatives: Vec<Alternative> = in_graph.walk(in_start, in_depth);
+        out_alternatives.retain(|alternative| in_search.matches(&in_templates[alternative.position]));
+        out_alternatives.sort_by(|a, b| {
+            let (template_a, template_b) = (&in_templates[a.position], &in_templates[b.position]);
+            template_b
+                .distance()
+                .partial_cmp(&template_a.distance())
+                .unwrap()
+                .then_with(|| a.depth.cmp(&b.depth))
+                .then_with(|| template_a.id().cmp(template_b.id()))
+        });
+        out_alternatives
+    }
+
+    // The list of the alternatives for the console.
+    pub fn alternatives_to_string(in_alternatives: &[(Template, usize)]) -> String {
+        let mut out_string = String::new();
+        for (index, (template, depth)) in in_alternatives.iter().enumerate() {
+            if index > 0 {
+                out_string.push('\n');
+            }
+            out_string.push_str(&format!(
+                "{} {} {}  {}",
+                (index + 1).to_string().magenta(),
+                template.id().truecolor(90, 90, 255),
+                template.title().green(),
+                format!("depth {}", depth).truecolor(150, 150, 150)
+            ));
+            for reference in template.references() {
+                out_string.push_str(&format!("\n    {}", reference.truecolor(200, 200, 200)));
             }
         }
+        out_string
     }
 
-    // Load the lake using the config struct.
-    fn load_lake_from_config_dir(in_config: Config, in_search: Search) -> Vec<Template> {
-        let mut out_templates: Vec<Template> = Vec::<Template>::new();
+    // Load the synonyms of a lake dir.
+    // A lake without a _synonyms.yaml has no synonyms.
+    pub fn load_synonyms(in_lake_dir: &Path) -> Result<Synonyms> {
+        let synonyms_path: PathBuf = in_lake_dir.join(SYNONYMS_FILE_NAME);
+        if synonyms_path.exists() {
+            Synonyms::load(&synonyms_path)
+        } else {
+            Ok(Synonyms::default())
+        }
+    }
 
+    // Load all yaml templates of a directory.
+    // The files are read and parsed in parallel on all cores.
+    // The paths are sorted by name first, so the order of the templates does not depend
+    // on the file system or on the number of threads.
+    // Broken templates are skipped and returned as errors, a lake without any template is corrupt.
+    pub fn load_templates_from_dir(in_dir: &Path) -> Result<(Vec<Template>, Vec<WamiError>)> {
         // Trying to load the dir.
-        match fs::read_dir(in_config.get_lake_dir()) {
-            Ok(entries) => {
-                for entry in entries {
-                    if let Ok(entry) = entry {
-                        // Got the path of a file.
-                        let path = entry.path();
-
-                        // If the file has an extension.
-                        if let Some(extension) = path.extension() {
-                            // If the file has a yaml extension.
-                            if extension == "yaml" {
-                                // Try to read the yaml file
-                                match Lake::read_yaml_file(path.to_str().unwrap()) {
-                                    Ok(yaml_string) => {
-                                        match serde_yaml::from_str::<yaml_template::YamlTemplate>(
-                                            &yaml_string,
-                                        ) {
-                                            Ok(in_yaml_template) => {
-                                                // Use the new operator because there is an string formatting function integrated.
-                                                // If you would use the deserializing method, it would be easier but maybe not correct.
-                                                out_templates.push(Template::new(
-                                                    in_yaml_template.id,
-                                                    in_search.id_get().to_string(),
-                                                    in_yaml_template.title,
-                                                    in_search.title_get().to_string(),
-                                                    in_yaml_template.tags.iter().map(|tag| tag.to_string()).collect(),
-                                                    in_search.tags_get().iter().map(|tag| tag.to_string()).collect(),
-                                                    in_yaml_template.description,
-                                                    in_search.description_get().to_string(),
-                                                    in_yaml_template.references.iter().map(|refe| refe.to_string()).collect(),
-                                                    in_search.reference_get().iter().map(|refe|refe.to_string()).collect(),
-                                                    in_yaml_template.why_not.iter().map(|why_not| why_not.to_string()).collect()
-                                                ));
-                                            }
-                                            Err(err) => {
-                                                eprintln!("Failed to deserialize YAML: {}", err);
-                                                eprintln!("{:#?}", path.to_str().unwrap());
-                                                continue; // Skip this file and continue.
-                                            }
-                                        }
-                                    }
-                                    Err(err) => {
-                                        eprintln!("Failed to read file: {}", err);
-                                        eprintln!("{:#?}", path.to_str().unwrap());
-                                        continue; // Skip this file and continue.
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+        let mut paths: Vec<PathBuf> = fs::read_dir(in_dir)
+            .map_err(|err| WamiError::LakeCorrupt(format!("Failed to read {}: {}", in_dir.display(), err)))?
+            .flatten()
+            .map(|entry| entry.path())
+            // Only files with a yaml extension are templates.
+            .filter(|path| path.extension().map(|extension| extension == "yaml").unwrap_or(false))
+            // Files starting with an underscore are part of the lake, but no templates, e.g. _synonyms.yaml.
+            .filter(|path| {
+                !path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().starts_with('_'))
+                    .unwrap_or(false)
+            })
+            .collect();
+        paths.sort();
 
-                out_templates
-            }
+        // The parallel iterator keeps the order of the paths.
+        let loaded: Vec<Result<Template>> = paths.par_iter().map(|path| Lake::load_template(path)).collect();
 
-            // Error there is no way to read the dir.
-            Err(err) => {
-                eprintln!("Failed to read directory: {}", err);
-                out_templates
+        let mut out_templates: Vec<Template> = Vec::with_capacity(loaded.len());
+        let mut out_broken: Vec<WamiError> = Vec::new();
+        for template in loaded {
+            match template {
+                Ok(template) => out_templates.push(template),
+                Err(err) => out_broken.push(err),
             }
         }
+
+        if out_templates.is_empty() {
+            return Err(WamiError::LakeCorrupt(format!("There are no templates in {}.", in_dir.display())));
+        }
+        Ok((out_templates, out_broken))
+    }
+
+    // Read and parse a single yaml template.
+    pub fn load_template(in_path: &Path) -> Result<Template> {
+        let parse_error = |in_message: String| WamiError::TemplateParse {
+            path: in_path.to_path_buf(),
+            message: in_message,
+        };
+
+        let yaml_string: String = fs::read_to_string(in_path).map_err(|err| parse_error(err.to_string()))?;
+        let in_yaml_template: yaml_template::YamlTemplate =
+            serde_yaml::from_str(&yaml_string).map_err(|err| parse_error(err.to_string()))?;
+
+        // Use the new operator because there is an string formatting function integrated.
+        // If you would use the deserializing method, it would be easier but maybe not correct.
+        Ok(Template::new(
+            in_yaml_template.id,
+            in_yaml_template.title,
+            in_yaml_template.tags,
+            in_yaml_template.description,
+            in_yaml_template.references,
+            in_yaml_template.why_not,
+        ))
     }
 
-    // Loading the zip file from the url, using the config struct.
-    pub async fn load_zip_from_url(in_config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    // Loading the zip file from the url of the lake.
+    // The token of a private lake is sent, if it is set for the backend.
+    pub async fn load_zip_from_url(in_url: &str, in_backend: Backend) -> Result<Vec<u8>> {
         let client = Client::new();
 
         // Check if we can connect to the url.
-        Lake::check_connection_to_url(in_config.url.to_owned()).await?;
+        // A private lake can only be reached with its token, so the request of the zip is the check.
+        let auth_header: Option<(&str, String)> = in_backend.auth_header();
+        if auth_header.is_none() {
+            Lake::check_connection_to_url(in_url.to_owned()).await?;
+        }
 
         // Send a request to get the zip.
-        let response = client.get(in_config.url.to_owned()).send().await?;
+        let mut request = client.get(in_url);
+        if let Some((name, value)) = auth_header {
+            request = request.header(name, value);
+        }
+        let response = request.send().await?;
 
         // If this request fails, the return an error.
         if !response.status().is_success() {
-            return Err("Failed to fetch the zip file at Lake::load_zip_form_url".into());
+            return Err(WamiError::Network(format!(
+                "Failed to fetch the zip file {}, status {}",
+                in_url,
+                response.status()
+            )));
         }
 
         // If the request is ok read the bytes in the archive
-        let bytes = response.bytes().await?;
-
-        // Download the hash form git to the config.yaml file.
-        match Config::get_git_hash(&in_config.url) {
-            Ok(hash) => {
-                // Save the url and hash in the config.yaml
-                if let Err(e) = Config::save_to_config_yaml(&in_config.url, &hash) {
-                    return Err(e);
-                }    
-            },
-            Err(_err) => {
-                println!("Version of the lake can not be downloaded.");
-                // Save the url and err for hash in the config.yaml
-                if let Err(e) = Config::save_to_config_yaml(&in_config.url, &"no_hash".to_owned()) {
-                    return Err(e);
-                }
-            }
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    // The hash of the content of the yaml files of a lake dir, for a lake which is not versioned by git.
+    // The files are hashed sorted by name, so the hash does not depend on the file system.
+    pub fn hash_lake_dir(in_dir: &Path) -> Result<String> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(in_dir)
+            .map_err(|err| WamiError::LakeCorrupt(format!("Failed to read {}: {}", in_dir.display(), err)))?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().map(|extension| extension == "yaml").unwrap_or(false))
+            .collect();
+        paths.sort();
+
+        let mut hasher = Sha256::new();
+        for path in paths {
+            // The name is part of the hash, so a renamed file changes the hash as well.
+            hasher.update(path.file_name().unwrap_or_default().to_string_lossy().as_bytes());
+            hasher.update([0]);
+            hasher.update(fs::read(&path)?);
+            hasher.update([0]);
         }
+        Ok(to_hex(&hasher.finalize()))
+    }
 
-        let reader = std::io::Cursor::new(bytes);
-        let mut archive = ZipArchive::new(reader)?;
+    // This will check if it is possible to connect to the url.
+    pub async fn check_connection_to_url(in_url: String) -> Result<()> {
+        match reqwest::get(&in_url).await {
+            Ok(response) if response.status().is_success() => Ok(()),
+            Ok(response) => Err(WamiError::Network(format!(
+                "Error connecting to the URL: {}, status {}",
+                in_url,
+                response.status()
+            ))),
+            Err(e) => Err(WamiError::Network(format!("Error connecting to the URL: {} {}", in_url, e))),
+        }
+    }
+}
 
-        // loop throw the archive
-        for i in 0..archive.len() {
-            // Get the file with the index.
-            let mut file = archive.by_index(i)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            // configure the path to save the files.
-            let mut out_path: PathBuf = Config::get_config_path().to_owned();
-            out_path.push(file.mangled_name());
+    // The fixture lake is a small copy of the templates lake.
+    // The tests lock in the ranking, so changes to the scoring are visible.
+    fn fixture_lake() -> Vec<Template> {
+        let mut lake_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        lake_dir.push("tests/fixtures/lake");
+        Lake::load_templates_from_dir(&lake_dir).unwrap().0
+    }
 
-            // If the file is an directory
-            if file.name().ends_with('/') {
-                // Create a directory if it doesn't exist.
-                tokio::fs::create_dir_all(&out_path).await?;
-            } else {
-                // Ensure the parent directory exists
-                if let Some(p) = out_path.parent() {
-                    if !p.exists() {
-                        create_dir_all(&p).await?;
-                    }
-                }
+    // The fixture lake with the given synonyms.
+    fn fixture(in_synonyms: Synonyms) -> Lake {
+        let templates = fixture_lake();
+        Lake {
+            config: Config::default(),
+            index: Index::new(&templates),
+            templates,
+            synonyms: in_synonyms,
+            warnings: Vec::new(),
+        }
+    }
 
-                // Create the file
-                let mut out_file = File::create(&out_path)?;
+    // Rank the fixture lake and return the ids of the best hits.
+    fn ranking(in_search: &Search, in_max: usize) -> Vec<String> {
+        ranking_in(&fixture(Synonyms::default()), in_search, in_max)
+    }
 
-                // Write the file
-                std::io::copy(&mut file, &mut out_file)?;
-            }
+    fn ranking_in(in_lake: &Lake, in_search: &Search, in_max: usize) -> Vec<String> {
+        in_lake
+            .search(in_search.clone())
+            .iter()
+            .take(in_max)
+            .map(|template| template.id().to_string())
+            .collect()
+    }
+
+    fn tag_search(in_tags: &[&str]) -> Search {
+        let mut search = Search::new_empty();
+        search.tags_set(&in_tags.iter().map(|tag| tag.to_string()).collect());
+        search
+    }
+
+    // The same search in all fields, like the -s flag.
+    fn search_all(in_words: &str) -> Search {
+        let words: Vec<String> = in_words.split(' ').map(|word| word.to_string()).collect();
+        let mut search = Search::new_empty();
+        search.id_set(in_words);
+        search.title_set(&in_words.to_string());
+        search.tags_set(&words);
+        search.description_set(&in_words.to_string());
+        search.reference_set(&words);
+        search
+    }
+
+    #[test]
+    fn test_fixture_lake_loads_every_template() {
+        assert_eq!(fixture_lake().len(), 12);
+    }
+
+    #[test]
+    fn test_ranking_tags_only() {
+        assert_eq!(
+            ranking(&tag_search(&["dns"]), 10),
+            vec!["host", "dig", "dnsrecon", "gobuster"]
+        );
+    }
+
+    #[test]
+    fn test_ranking_tags_both_words_win() {
+        // Only dnsrecon is a dns scanner, the other hits match one of the words.
+        // Of those, the templates with fewer tags are ranked first.
+        assert_eq!(
+            ranking(&tag_search(&["dns", "scanner"]), 3),
+            vec!["dnsrecon", "host", "nikto"]
+        );
+    }
+
+    #[test]
+    fn test_ranking_search_all() {
+        assert_eq!(
+            ranking(&search_all("port scanner"), 3),
+            vec!["masscan", "nikto", "nmap"]
+        );
+    }
+
+    #[test]
+    fn test_ranking_search_all_zone_transfer() {
+        assert_eq!(
+            ranking(&search_all("zone transfer"), 2),
+            vec!["dnsrecon", "dig"]
+        );
+    }
+
+    #[test]
+    fn test_ranking_stems_and_stopwords() {
+        // The stems match "transfers" and "zone", the stopwords "of the" are ignored.
+        assert_eq!(
+            ranking(&search_all("transfers of the zones"), 2),
+            ranking(&search_all("zone transfer"), 2)
+        );
+    }
+
+    #[test]
+    fn test_ranking_no_search_has_no_hits() {
+        assert!(ranking(&Search::new_empty(), 10).is_empty());
+    }
+
+    fn query_search(in_query: &str) -> Search {
+        let mut search = Search::new_empty();
+        search.query_set(in_query).unwrap();
+        search
+    }
+
+    #[test]
+    fn test_ranking_query_negation() {
+        // All smb tools except the windows ones.
+        assert_eq!(ranking(&query_search("tag:smb -tag:windows"), 10), vec!["smbclient"]);
+    }
+
+    #[test]
+    fn test_ranking_query_nested_negation() {
+        // The negated word of a group or an OR filters the templates as well.
+        assert_eq!(ranking(&query_search("tag:smb (client -tag:windows)"), 10), vec!["smbclient"]);
+
+        let hits = ranking(&query_search("dns OR smb -tag:windows"), 100);
+        assert!(hits.contains(&"smbclient".to_string()) && hits.contains(&"dig".to_string()));
+        assert!(!hits.contains(&"responder".to_string()) && !hits.contains(&"enum4linux".to_string()));
+    }
+
+    #[test]
+    fn test_ranking_query_only_filters() {
+        // Every template except the windows ones, without words there is nothing to rank.
+        let hits = ranking(&query_search("-tag:windows"), 100);
+        assert_eq!(hits.len(), fixture_lake().len() - 2);
+        assert!(!hits.contains(&"responder".to_string()) && !hits.contains(&"enum4linux".to_string()));
+
+        let hits = ranking(&query_search("+tag:dns"), 100);
+        assert!(hits.contains(&"dig".to_string()) && hits.contains(&"dnsrecon".to_string()));
+        assert!(!hits.contains(&"nmap".to_string()));
+    }
+
+    #[test]
+    fn test_ranking_query_required_and_soft_words() {
+        // Only dns tools, the zone transfer tools first.
+        assert_eq!(
+            ranking(&query_search(r#"+tag:dns "zone transfer""#), 10),
+            vec!["dnsrecon", "dig", "host", "gobuster"]
+        );
+    }
+
+    #[test]
+    fn test_ranking_query_field_group() {
+        assert_eq!(
+            ranking(&query_search("description:(poisoner OR brute)"), 10),
+            vec!["responder", "gobuster", "dnsrecon"]
+        );
+    }
+
+    // The default search of wami <name>: the words are tags and the name of a tool.
+    fn name_search(in_name: &str) -> Search {
+        let mut search = tag_search(&[in_name]);
+        search.name_set(in_name);
+        search
+    }
+
+    #[test]
+    fn test_ranking_name_typo_is_first() {
+        let search = name_search("nmpa");
+
+        assert_eq!(ranking(&search, 1), vec!["nmap"]);
+        assert_eq!(
+            search.find_name(&fixture_lake()),
+            Some(NameMatch { id: "nmap".to_string(), exact: false })
+        );
+    }
+
+    #[test]
+    fn test_ranking_name_before_better_scores() {
+        // host has the best dns score, but the user named dig.
+        let mut search = tag_search(&["dns"]);
+        search.name_set("dig");
+
+        assert_eq!(ranking(&search, 2), vec!["dig", "host"]);
+    }
+
+    // The synonyms of the fixture lake.
+    fn fixture_synonyms() -> Synonyms {
+        let mut synonyms_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        synonyms_path.push("tests/fixtures/lake");
+        synonyms_path.push(SYNONYMS_FILE_NAME);
+        Synonyms::load(&synonyms_path).unwrap()
+    }
+
+    #[test]
+    fn test_ranking_synonym_phrase_finds_the_same_tools() {
+        let lake = fixture(fixture_synonyms());
+
+        assert_eq!(
+            ranking_in(&lake, &search_all("portscan"), 3),
+            ranking_in(&lake, &search_all("port scanner"), 3)
+        );
+        assert_eq!(ranking_in(&lake, &search_all("portscan"), 3), vec!["masscan", "nmap", "nikto"]);
+    }
+
+    #[test]
+    fn test_ranking_synonym_word() {
+        // Only smbclient has the tag cifs, but enum4linux and responder know smb and samba.
+        let lake = fixture(fixture_synonyms());
+        let mut search = tag_search(&["cifs"]);
+        search.description_set(&"cifs".to_string());
+
+        assert_eq!(ranking_in(&lake, &search, 3), vec!["smbclient", "enum4linux", "responder"]);
+
+        // Without the synonyms only the word itself is found.
+        search.use_synonyms_set(false);
+        assert_eq!(ranking_in(&lake, &search, 3), vec!["smbclient"]);
+    }
+
+    #[test]
+    fn test_search_results() {
+        let mut search = tag_search(&["dns"]);
+        search.name_set("digg");
+        let results = fixture(Synonyms::default()).search(search);
+
+        assert_eq!(results.did_you_mean(), Some("dig"));
+        assert_eq!(results.hits(2).iter().map(|hit| hit.rank).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(results.hits(100).len(), results.len());
+        assert_eq!((&results).into_iter().count(), results.len());
+    }
+
+    #[test]
+    fn test_merge_templates_higher_priority_wins() {
+        let internal = vec![Template::new(
+            "nmap".to_string(),
+            "Nmap with the scripts of the team".to_string(),
+            vec!["scanner".to_string()],
+            String::new(),
+            vec![],
+            vec![],
+        )];
+        let templates = Lake::merge_templates(vec![("internal", internal), ("wami-templates", fixture_lake())]);
+
+        assert_eq!(templates.len(), fixture_lake().len());
+        let nmap = templates.iter().find(|template| template.id() == "nmap").unwrap();
+        assert_eq!(nmap.lake(), "internal");
+        assert_eq!(nmap.title(), "Nmap with the scripts of the team");
+        assert!(templates
+            .iter()
+            .filter(|template| template.id() != "nmap")
+            .all(|template| template.lake() == "wami-templates"));
+    }
+
+    #[test]
+    fn test_find_template() {
+        let lake = fixture(Synonyms::default());
+
+        assert_eq!(lake.find_template("NMAP").unwrap().id(), "nmap");
+        match lake.find_template("nmpa") {
+            Err(WamiError::UnknownId { suggestions, .. }) => assert_eq!(suggestions.first().map(String::as_str), Some("nmap")),
+            other => panic!("Expected an unknown id, got {:?}", other.map(Template::id)),
         }
-        Ok(())
     }
 
-    // This will check if it is possible to connect to the url.
-    pub async fn check_connection_to_url(in_url: String) -> Result<(), Box<dyn std::error::Error>> {
-        match reqwest::get(&in_url).await {
-            Ok(response) => {
-                if response.status().is_success() {
-                    Ok(())
-                } else {
-                    Err(format!("Error connecting to the URL: {}", in_url).into())
-                }
-            }
-            Err(e) => Err(format!("Error connecting to the URL: {} {}", in_url, e).into()),
+    #[test]
+    fn test_synonyms_file_is_no_template() {
+        assert!(fixture_lake().iter().all(|template| !template.id().is_empty()));
+        assert_eq!(fixture_lake().len(), 12);
+    }
+
+    // The ids and depths of the alternatives of a fixture template.
+    fn alternatives(in_id: &str, in_depth: usize, in_search: &Search) -> Vec<(String, usize)> {
+        let mut templates = fixture_lake();
+        let index = Index::new(&templates);
+        let graph = Graph::new(&templates);
+        let start = templates.iter().position(|template| template.id() == in_id).unwrap();
+        Lake::rank_alternatives(&mut templates, &index, &graph, start, in_depth, in_search)
+            .iter()
+            .map(|alternative| (templates[alternative.position].id().to_string(), alternative.depth))
+            .collect()
+    }
+
+    #[test]
+    fn test_alternatives_by_depth() {
+        // dnsrecon -> dig -> host
+        assert_eq!(alternatives("dnsrecon", 1, &Search::new_empty()), vec![("dig".to_string(), 1)]);
+        assert_eq!(
+            alternatives("dnsrecon", 2, &Search::new_empty()),
+            vec![("dig".to_string(), 1), ("host".to_string(), 2)]
+        );
+    }
+
+    #[test]
+    fn test_alternatives_ranked_by_search() {
+        // Only host converts names "vice versa", so it is the better alternative.
+        assert_eq!(
+            alternatives("dnsrecon", 2, &query_search("versa")),
+            vec![("host".to_string(), 2), ("dig".to_string(), 1)]
+        );
+
+        // A filter removes alternatives.
+        assert_eq!(
+            alternatives("dnsrecon", 2, &query_search("-tag:zone-transfer")),
+            vec![("host".to_string(), 2)]
+        );
+    }
+
+    #[test]
+    fn test_fixture_lake_has_no_dangling_alternatives() {
+        assert!(Graph::new(&fixture_lake()).dangling().is_empty());
+    }
+
+    // Load a lake dir and score it, the result is the id and the total score of every template.
+    fn load_and_score(in_dir: &Path, in_search: &Search) -> Vec<(String, f32)> {
+        let (mut templates, _) = Lake::load_templates_from_dir(in_dir).unwrap();
+        let index = Index::new(&templates);
+        Lake::score_templates(&mut templates, &index, in_search);
+        templates
+            .iter()
+            .map(|template| (template.id().to_string(), template.distance()))
+            .collect()
+    }
+
+    // Run a function on a thread pool with the given number of threads.
+    fn with_threads<T: Send>(in_threads: usize, in_function: impl FnOnce() -> T + Send) -> T {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(in_threads)
+            .build()
+            .unwrap()
+            .install(in_function)
+    }
+
+    #[test]
+    fn test_parallel_loading_matches_sequential() {
+        let mut lake_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        lake_dir.push("tests/fixtures/lake");
+        let search = search_all("dns zone transfer scanner");
+
+        let sequential = with_threads(1, || load_and_score(&lake_dir, &search));
+        let parallel = with_threads(4, || load_and_score(&lake_dir, &search));
+
+        assert_eq!(sequential, parallel);
+
+        // The templates are loaded in the order of their file names.
+        let ids: Vec<&String> = sequential.iter().map(|(id, _)| id).collect();
+        let mut sorted = ids.clone();
+        sorted.sort();
+        assert_eq!(ids, sorted);
+    }
+
+    // Create an empty dir in the temp dir of the system for a test.
+    fn test_dir(in_name: &str) -> PathBuf {
+        let mut out_dir = std::env::temp_dir();
+        out_dir.push(format!("wami-lake-test-{}-{}", in_name, std::process::id()));
+        let _ = fs::remove_dir_all(&out_dir);
+        fs::create_dir_all(&out_dir).unwrap();
+        out_dir
+    }
+
+    // A zip file with the given files, the names can contain dirs.
+    fn zip_bytes(in_files: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, content) in in_files {
+            writer.start_file(*name, zip::write::FileOptions::default()).unwrap();
+            std::io::Write::write_all(&mut writer, content.as_bytes()).unwrap();
         }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_local_zip_is_extracted_and_found() {
+        let dir = test_dir("zip");
+        let nmap = fs::read_to_string(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/lake/nmap.yaml")).unwrap();
+        let limits = ZipLimits::default();
+        archive::extract_zip(&zip_bytes(&[("my-lake-main/lake/nmap.yaml", &nmap), ("my-lake-main/README.md", "")]), &dir, &limits).unwrap();
+
+        let lake_dir = LakeSource::find_lake_dir(&dir);
+        assert_eq!(lake_dir, dir.join("my-lake-main").join("lake"));
+        let (templates, broken) = Lake::load_templates_from_dir(&lake_dir).unwrap();
+        assert_eq!(templates[0].id(), "nmap");
+        assert!(broken.is_empty());
+
+        assert!(!dir.join("my-lake-main").join("README.md").exists());
+        assert!(archive::extract_zip(b"no zip", &dir, &limits).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_staged_lake_needs_valid_templates() {
+        let dir = test_dir("staging");
+        let nmap = fs::read_to_string(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/lake/nmap.yaml")).unwrap();
+        let lake_source = LakeSource::new("staged", "file:///tmp/staged.zip", 0);
+
+        // A broken template stops the update, before the lake is replaced.
+        let broken = zip_bytes(&[("staged-main/lake/nmap.yaml", &nmap), ("staged-main/lake/broken.yaml", "title: [")]);
+        assert!(matches!(
+            Lake::stage_archive(&lake_source, &dir.join("broken"), &broken, None, "v2", &ZipLimits::default()),
+            Err(WamiError::TemplateParse { .. })
+        ));
+
+        let bytes = zip_bytes(&[("staged-main/lake/nmap.yaml", &nmap)]);
+        Lake::stage_archive(&lake_source, &dir.join("valid"), &bytes, None, "v2", &ZipLimits::default()).unwrap();
+        let manifest = Manifest::load(&dir.join("valid")).unwrap().unwrap();
+        assert_eq!(manifest.version, "v2");
+        assert_eq!(manifest.url, "file:///tmp/staged.zip");
+        assert!(manifest.templates.contains_key("nmap.yaml"));
+        assert!(dir.join("valid").join(manifest::ARCHIVE_FILE_NAME).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_swap_version_keeps_the_lake_if_the_rename_fails() {
+        let dir = test_dir("swap");
+        let (staging_dir, download_dir, versions_dir) = (dir.join("lake.staging"), dir.join("lake"), dir.join("lake.versions"));
+        fs::create_dir_all(&download_dir).unwrap();
+        fs::write(download_dir.join("version"), "v2").unwrap();
+        fs::create_dir_all(versions_dir.join("000000000001")).unwrap();
+
+        // There is no staged version, so the current version is moved back and no old version is deleted.
+        assert!(Lake::swap_version(&staging_dir, &download_dir, &versions_dir, 1).is_err());
+        assert_eq!(fs::read_to_string(download_dir.join("version")).unwrap(), "v2");
+        assert_eq!(LakeSource::get_versions_in(&versions_dir), vec![versions_dir.join("000000000001")]);
+
+        // The new version is in place, only then the oldest version is deleted.
+        fs::create_dir_all(&staging_dir).unwrap();
+        fs::write(staging_dir.join("version"), "v3").unwrap();
+        Lake::swap_version(&staging_dir, &download_dir, &versions_dir, 1).unwrap();
+        assert_eq!(fs::read_to_string(download_dir.join("version")).unwrap(), "v3");
+        let versions = LakeSource::get_versions_in(&versions_dir);
+        assert_eq!(versions.len(), 1);
+        assert_eq!(fs::read_to_string(versions[0].join("version")).unwrap(), "v2");
+        assert!(!staging_dir.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_restore_version_keeps_the_lake_if_the_rename_fails() {
+        let dir = test_dir("restore");
+        let (staging_dir, download_dir) = (dir.join("lake.staging"), dir.join("lake"));
+        let version_dir = dir.join("lake.versions").join("000000000001");
+        fs::create_dir_all(&download_dir).unwrap();
+        fs::write(download_dir.join("version"), "v2").unwrap();
+
+        // The old version is missing, so the current version is moved back from the staging dir.
+        assert!(Lake::restore_version(&version_dir, &download_dir, &staging_dir).is_err());
+        assert_eq!(fs::read_to_string(download_dir.join("version")).unwrap(), "v2");
+        assert!(!staging_dir.exists());
+
+        fs::create_dir_all(&version_dir).unwrap();
+        fs::write(version_dir.join("version"), "v1").unwrap();
+        Lake::restore_version(&version_dir, &download_dir, &staging_dir).unwrap();
+        assert_eq!(fs::read_to_string(download_dir.join("version")).unwrap(), "v1");
+        assert!(!version_dir.exists());
+        assert!(!staging_dir.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_signed_flag_of_the_manifest_is_not_trusted() {
+        let dir = test_dir("signed");
+        let nmap = fs::read_to_string(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/lake/nmap.yaml")).unwrap();
+        let mut lake_source = LakeSource::new("signed", "file:///tmp/signed.zip", 0);
+        let bytes = zip_bytes(&[("signed-main/lake/nmap.yaml", &nmap)]);
+        Lake::stage_archive(&lake_source, &dir, &bytes, None, "v1", &ZipLimits::default()).unwrap();
+        let lake_dir = LakeSource::find_lake_dir(&dir);
+        Lake::check_manifest(&lake_source, &dir, &lake_dir).unwrap();
+
+        // Whoever can change the lake can mark it as signed, the signature of the archive is checked again.
+        let mut manifest = Manifest::load(&dir).unwrap().unwrap();
+        manifest.signed = true;
+        manifest.save(&dir).unwrap();
+        lake_source.public_key = Some("RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3".to_string());
+        assert!(matches!(Lake::check_manifest(&lake_source, &dir, &lake_dir), Err(WamiError::LakeCorrupt(_))));
+
+        fs::write(dir.join(manifest::SIGNATURE_FILE_NAME), "untrusted comment: forged\nRUQ=\n").unwrap();
+        assert!(matches!(Lake::check_manifest(&lake_source, &dir, &lake_dir), Err(WamiError::LakeCorrupt(_))));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_hash_lake_dir_follows_the_content() {
+        let dir = test_dir("hash");
+        fs::write(dir.join("dig.yaml"), "id: dig\n").unwrap();
+        fs::write(dir.join("notes.txt"), "not part of the lake").unwrap();
+        let hash = Lake::hash_lake_dir(&dir).unwrap();
+        assert_eq!(hash.len(), 64);
+
+        fs::write(dir.join("notes.txt"), "changed").unwrap();
+        assert_eq!(Lake::hash_lake_dir(&dir).unwrap(), hash);
+
+        fs::write(dir.join("dig.yaml"), "id: dig\ntitle: dig\n").unwrap();
+        assert_ne!(Lake::hash_lake_dir(&dir).unwrap(), hash);
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(Lake::hash_lake_dir(&dir), Err(WamiError::LakeCorrupt(_))));
+    }
+
+    #[test]
+    fn test_local_dir_is_never_outdated() {
+        let dir = test_dir("outdated");
+        let mut lake_source = LakeSource::new("local", &LakeSource::location_to_url(dir.to_str().unwrap()).unwrap(), 0);
+        lake_source.hash = "old".to_string();
+
+        assert!(!Lake::is_outdated(&lake_source).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,338 @@
+use crate::error::{Result, WamiError};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::Read,
+    path::{Component, Path, PathBuf},
+};
+use zip::ZipArchive;
+
+// The extraction of the archive of a lake.
+// The archive comes from a server, so it is not trusted: entries which leave the dir, symlinks
+// and archives which are too big are rejected as a whole, before a single file is written.
+// Only the yaml files of the lake dir of the archive are extracted, the rest of the repository is skipped.
+
+// The type bits of the unix mode of an entry, a symlink has the type 0o120000.
+const UNIX_TYPE_MASK: u32 = 0o170000;
+const UNIX_SYMLINK: u32 = 0o120000;
+
+// The limits of an archive, they can be changed in the config.yaml.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ZipLimits {
+    pub max_size: u64,    // The size of all extracted files in bytes.
+    pub max_files: usize, // The number of entries of the archive, the skipped entries count as well.
+    pub max_ratio: u64,   // The size of an extracted file divided by its compressed size.
+}
+
+impl Default for ZipLimits {
+    fn default() -> ZipLimits {
+        ZipLimits {
+            max_size: 64 * 1024 * 1024,
+            max_files: 10_000,
+            max_ratio: 100,
+        }
+    }
+}
+
+impl ZipLimits {
+    pub const NAMES: [&'static str; 3] = ["max_size", "max_files", "max_ratio"];
+
+    pub fn get(&self, in_name: &str) -> Option<String> {
+        match in_name {
+            "max_size" => Some(self.max_size.to_string()),
+            "max_files" => Some(self.max_files.to_string()),
+            "max_ratio" => Some(self.max_ratio.to_string()),
+            _ => None,
+        }
+    }
+
+    // Set a limit by its name, a limit of 0 would reject every lake, so it is invalid.
+    pub fn set(&mut self, in_name: &str, in_value: &str) -> std::result::Result<(), String> {
+        let value: u64 = in_value
+            .trim()
+            .parse()
+            .ok()
+            .filter(|value| *value > 0)
+            .ok_or_else(|| format!("Invalid value '{}' for the limit {}, expected a number above 0.", in_value, in_name))?;
+        match in_name {
+            "max_size" => self.max_size = value,
+            "max_files" => self.max_files = value as usize,
+            "max_ratio" => self.max_ratio = value,
+            _ => {
+                return Err(format!(
+                    "Unknown limit '{}', expected {}.",
+                    in_name,
+                    ZipLimits::NAMES.join(", ")
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+// An entry of the archive, which passed the checks.
+struct Entry {
+    index: usize,
+    path: Vec<String>, // The normal components of the path, there is no .. and no root.
+    is_dir: bool,
+}
+
+// Extract the templates of an archive to a dir, e.g. the staging dir of a lake, and return their number.
+// The lake dir of the archive is found like LakeSource::find_lake_dir finds it on the disk:
+// it is the lake dir, the lake dir of the only top dir or the top of the archive.
+pub fn extract_zip(in_bytes: &[u8], in_dir: &Path, in_limits: &ZipLimits) -> Result<usize> {
+    let corrupt = |in_message: String| WamiError::LakeCorrupt(format!("The archive is rejected, {}", in_message));
+    let mut archive = ZipArchive::new(std::io::Cursor::new(in_bytes))?;
+    if archive.len() > in_limits.max_files {
+        return Err(corrupt(format!(
+            "it has {} entries, the limit is {}.",
+            archive.len(),
+            in_limits.max_files
+        )));
+    }
+
+    // The paths of every entry are checked first, so a bad archive does not leave half a lake.
+    let mut entries: Vec<Entry> = Vec::with_capacity(archive.len());
+    for index in 0..archive.len() {
+        let file = archive.by_index(index)?;
+        let path: Option<Vec<String>> = Path::new(file.name())
+            .components()
+            .map(|component| match component {
+                Component::Normal(name) => name.to_str().map(str::to_owned),
+                _ => None,
+            })
+            .collect();
+        let path: Vec<String> = match path {
+            Some(path) if !path.is_empty() && !file.name().contains('\\') => path,
+            _ => return Err(corrupt(format!("the entry {} leaves the lake dir.", file.name()))),
+        };
+        if file.unix_mode().map(|mode| mode & UNIX_TYPE_MASK == UNIX_SYMLINK).unwrap_or(false) {
+            return Err(corrupt(format!("the entry {} is a symlink.", file.name())));
+        }
+        entries.push(Entry {
+            index,
+            path,
+            is_dir: file.is_dir(),
+        });
+    }
+
+    // The templates are read and checked against the limits in memory, they are only written if all of them pass.
+    // The memory is bounded by the size limit.
+    let lake_dir: Vec<String> = find_archive_lake_dir(&entries);
+    let mut size: u64 = 0;
+    let mut templates: Vec<(&Entry, Vec<u8>)> = Vec::new();
+    for entry in entries.iter().filter(|entry| is_template(entry, &lake_dir)) {
+        let file = archive.by_index(entry.index)?;
+        let name: String = file.name().to_owned();
+        let compressed_size: u64 = file.compressed_size().max(1);
+
+        // The sizes of the header can be forged, so the extracted bytes are counted.
+        let remaining: u64 = in_limits.max_size.saturating_sub(size);
+        let mut bytes: Vec<u8> = Vec::new();
+        file.take(remaining.saturating_add(1)).read_to_end(&mut bytes)?;
+        size = size.saturating_add(bytes.len() as u64);
+        if size > in_limits.max_size {
+            return Err(corrupt(format!("its files are bigger than the limit of {} bytes.", in_limits.max_size)));
+        }
+        if bytes.len() as u64 > compressed_size.saturating_mul(in_limits.max_ratio) {
+            return Err(corrupt(format!(
+                "the entry {} is compressed more than the limit of {} to 1.",
+                name, in_limits.max_ratio
+            )));
+        }
+        templates.push((entry, bytes));
+    }
+
+    for (entry, bytes) in &templates {
+        let out_path: PathBuf = entry.path.iter().fold(in_dir.to_path_buf(), |path, component| path.join(component));
+        if let Some(parent) = out_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&out_path, bytes)?;
+    }
+    Ok(templates.len())
+}
+
+// The lake dir of the archive, as a list of dir names.
+fn find_archive_lake_dir(in_entries: &[Entry]) -> Vec<String> {
+    let starts_with = |in_prefix: &[&str]| {
+        in_entries
+            .iter()
+            .any(|entry| entry.path.len() > in_prefix.len() && entry.path.iter().zip(in_prefix).all(|(a, b)| a == b))
+    };
+    if starts_with(&["lake"]) {
+        return vec!["lake".to_owned()];
+    }
+
+    // The archive of a repository has a single top dir, e.g. wami-templates-main.
+    let mut top_dirs: Vec<&String> = in_entries
+        .iter()
+        .filter(|entry| entry.path.len() > 1 || entry.is_dir)
+        .map(|entry| &entry.path[0])
+        .collect();
+    top_dirs.dedup();
+    if let [top_dir] = top_dirs.as_slice() {
+        if starts_with(&[top_dir, "lake"]) {
+            return vec![top_dir.to_string(), "lake".to_owned()];
+        }
+    }
+    Vec::new()
+}
+
+// Only the yaml files directly in the lake dir are extracted.
+fn is_template(in_entry: &Entry, in_lake_dir: &[String]) -> bool {
+    !in_entry.is_dir
+        && in_entry.path.len() == in_lake_dir.len() + 1
+        && in_entry.path.starts_with(in_lake_dir)
+        && in_entry.path.last().map(|name| name.ends_with(".yaml")).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+    fn test_dir(in_name: &str) -> PathBuf {
+        let mut out_dir = std::env::temp_dir();
+        out_dir.push(format!("wami-archive-test-{}-{}", in_name, std::process::id()));
+        let _ = fs::remove_dir_all(&out_dir);
+        fs::create_dir_all(&out_dir).unwrap();
+        out_dir
+    }
+
+    // A zip file with the given files, the names are written as they are, e.g. ../evil.yaml.
+    fn zip_bytes(in_files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        for (name, content) in in_files {
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(content).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    // Turn an entry of a zip file into a symlink.
+    // The writer has no symlinks, so the unix mode of the central directory is changed.
+    fn make_symlink(in_bytes: &mut [u8], in_name: &str) {
+        let signature: [u8; 4] = [0x50, 0x4b, 0x01, 0x02];
+        let mut index: usize = 0;
+        while index + 46 < in_bytes.len() {
+            if in_bytes[index..index + 4] == signature {
+                let name_length = u16::from_le_bytes([in_bytes[index + 28], in_bytes[index + 29]]) as usize;
+                if &in_bytes[index + 46..index + 46 + name_length] == in_name.as_bytes() {
+                    let attributes: u32 = (UNIX_SYMLINK | 0o777) << 16;
+                    in_bytes[index + 38..index + 42].copy_from_slice(&attributes.to_le_bytes());
+                    return;
+                }
+            }
+            index += 1;
+        }
+        panic!("There is no entry {}.", in_name);
+    }
+
+    fn is_rejected(in_bytes: &[u8], in_limits: &ZipLimits) -> bool {
+        let dir = test_dir("rejected");
+        let result = extract_zip(in_bytes, &dir, in_limits);
+        // Nothing is written, if the archive is rejected.
+        let is_empty = fs::read_dir(&dir).unwrap().next().is_none();
+        fs::remove_dir_all(&dir).unwrap();
+        matches!(result, Err(WamiError::LakeCorrupt(_))) && is_empty
+    }
+
+    #[test]
+    fn test_only_the_templates_of_the_lake_dir_are_extracted() {
+        let dir = test_dir("templates");
+        let bytes = zip_bytes(&[
+            ("lake-main/lake/nmap.yaml", b"id: nmap"),
+            ("lake-main/lake/_synonyms.yaml", b"dns: [domain]"),
+            ("lake-main/lake/notes/old.yaml", b"id: old"),
+            ("lake-main/lake/run.sh", b"rm -rf ~"),
+            ("lake-main/README.md", b"readme"),
+            ("lake-main/config.yaml", b"url: evil"),
+        ]);
+        assert_eq!(extract_zip(&bytes, &dir, &ZipLimits::default()).unwrap(), 2);
+        assert!(dir.join("lake-main/lake/nmap.yaml").exists());
+        assert!(dir.join("lake-main/lake/_synonyms.yaml").exists());
+        assert!(!dir.join("lake-main/lake/notes").exists());
+        assert!(!dir.join("lake-main/lake/run.sh").exists());
+        assert!(!dir.join("lake-main/config.yaml").exists());
+        fs::remove_dir_all(&dir).unwrap();
+
+        // An archive without a lake dir has its templates at the top.
+        let dir = test_dir("flat");
+        let bytes = zip_bytes(&[("nmap.yaml", b"id: nmap"), ("docs/dig.yaml", b"id: dig")]);
+        assert_eq!(extract_zip(&bytes, &dir, &ZipLimits::default()).unwrap(), 1);
+        assert!(dir.join("nmap.yaml").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_path_traversal_is_rejected() {
+        let limits = ZipLimits::default();
+        assert!(is_rejected(&zip_bytes(&[("lake/nmap.yaml", b"id: nmap"), ("../evil.yaml", b"id: evil")]), &limits));
+        assert!(is_rejected(&zip_bytes(&[("lake/../../evil.yaml", b"id: evil")]), &limits));
+        assert!(is_rejected(&zip_bytes(&[("/etc/cron.d/evil.yaml", b"id: evil")]), &limits));
+        assert!(is_rejected(&zip_bytes(&[("lake\\..\\..\\evil.yaml", b"id: evil")]), &limits));
+        assert!(is_rejected(&zip_bytes(&[("./lake/nmap.yaml", b"id: nmap")]), &limits));
+    }
+
+    #[test]
+    fn test_symlinks_are_rejected() {
+        let mut bytes = zip_bytes(&[("lake/nmap.yaml", b"id: nmap"), ("lake/passwd.yaml", b"/etc/passwd")]);
+        assert!(!is_rejected(&bytes, &ZipLimits::default()));
+        make_symlink(&mut bytes, "lake/passwd.yaml");
+        assert!(is_rejected(&bytes, &ZipLimits::default()));
+    }
+
+    #[test]
+    fn test_limits_of_the_archive() {
+        let limits = ZipLimits {
+            max_size: 1024,
+            max_files: 3,
+            max_ratio: 100,
+        };
+
+        // A bomb of zeros is compressed far more than a template.
+        let bomb: Vec<u8> = vec![0; 1024 * 1024];
+        assert!(is_rejected(&zip_bytes(&[("lake/bomb.yaml", &bomb)]), &ZipLimits::default()));
+
+        let big: Vec<u8> = (0..2048u32).flat_map(|number| number.to_le_bytes()).collect();
+        assert!(is_rejected(&zip_bytes(&[("lake/big.yaml", &big)]), &limits));
+        assert!(is_rejected(
+            &zip_bytes(&[("lake/a.yaml", b"a"), ("lake/b.yaml", b"b"), ("lake/c.yaml", b"c"), ("d.md", b"d")]),
+            &limits
+        ));
+        assert!(!is_rejected(&zip_bytes(&[("lake/a.yaml", b"a"), ("lake/b.yaml", b"b")]), &limits));
+
+        // A bomb after valid templates, nothing is written, not even the templates before it.
+        assert!(is_rejected(
+            &zip_bytes(&[("lake/a.yaml", b"id: a"), ("lake/b.yaml", b"id: b"), ("lake/bomb.yaml", &bomb)]),
+            &ZipLimits::default()
+        ));
+        // Several templates, which are fine on their own, are too big together.
+        let half: Vec<u8> = big[..800].to_vec();
+        assert!(is_rejected(&zip_bytes(&[("lake/a.yaml", &half), ("lake/b.yaml", &half)]), &limits));
+
+        // The highest limits do not overflow.
+        let dir = test_dir("highest");
+        let highest = ZipLimits {
+            max_size: u64::MAX,
+            max_files: usize::MAX,
+            max_ratio: u64::MAX,
+        };
+        assert_eq!(extract_zip(&zip_bytes(&[("lake/a.yaml", b"id: a")]), &dir, &highest).unwrap(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_set_the_limits() {
+        let mut limits = ZipLimits::default();
+        limits.set("max_files", "20").unwrap();
+        assert_eq!(limits.get("max_files").unwrap(), "20");
+        assert!(limits.set("max_ratio", "0").is_err());
+        assert!(limits.set("max_depth", "3").is_err());
+        assert_eq!(limits.get("max_depth"), None);
+    }
+}
@@ -0,0 +1,548 @@
+use crate::{error::{Result, WamiError}, github_search::get_github_api};
+use isahc::{prelude::*, Request};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::env;
+use url::Url;
+
+// The hosts a lake can be downloaded from.
+// Every backend knows the layout of its archive urls and how to fetch the version of the lake,
+// so wami can tell if a downloaded lake is outdated.
+// The backend is taken from the url, it can be set in the config.yaml if the url is ambiguous.
+
+// The tokens of private lakes are read from these environment variables.
+const GITLAB_TOKEN_VAR: &str = "WAMI_GITLAB_TOKEN";
+const GITEA_TOKEN_VAR: &str = "WAMI_GITEA_TOKEN";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    GitHub, // https://github.com/OWNER/REPOSITORY/archive/refs/heads/BRANCH.zip
+    GitLab, // https://HOST/GROUP/PROJECT/-/archive/BRANCH/PROJECT-BRANCH.zip
+    Gitea,  // https://HOST/OWNER/REPOSITORY/archive/BRANCH.zip, Forgejo uses the same urls.
+    Https,  // Any other zip file, its version is the ETag or the Last-Modified header.
+}
+
+// The repository and the branch or tag of an archive url.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Archive {
+    pub project: Vec<String>, // The owner and the repository, GitLab can have sub groups in between.
+    pub reference: String,    // The branch or the tag, both can have slashes.
+    pub is_tag: bool,         // Only GitHub tells a tag apart, with refs/tags in the url.
+}
+
+impl Backend {
+    pub const NAMES: [&'static str; 4] = ["github", "gitlab", "gitea", "https"];
+
+    // Find the backend by its name.
+    pub fn from_name(in_name: &str) -> Option<Backend> {
+        match in_name.trim().to_lowercase().as_str() {
+            "github" => Some(Backend::GitHub),
+            "gitlab" => Some(Backend::GitLab),
+            "gitea" | "forgejo" => Some(Backend::Gitea),
+            "https" => Some(Backend::Https),
+            _ => None,
+        }
+    }
+
+    // Take the backend from the archive url.
+    // GitLab has its own /-/archive/ path, a short archive path on another host than github.com is Gitea.
+    // Everything else is a plain zip file.
+    pub fn detect(in_url: &Url) -> Backend {
+        let segments: Vec<&str> = in_url.path_segments().map(|segments| segments.collect()).unwrap_or_default();
+        let is_zip: bool = segments.last().map(|name| name.ends_with(".zip")).unwrap_or(false);
+        if !is_zip {
+            return Backend::Https;
+        }
+
+        if in_url.host_str() == Some("github.com") && segments.len() >= 4 && segments[2] == "archive" {
+            Backend::GitHub
+        } else if segments.windows(2).any(|pair| pair == ["-", "archive"]) {
+            Backend::GitLab
+        } else if segments.len() == 4 && segments[2] == "archive" {
+            Backend::Gitea
+        } else {
+            Backend::Https
+        }
+    }
+
+    // The name of the backend for the config.yaml and the status of the lake.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::GitHub => "github",
+            Backend::GitLab => "gitlab",
+            Backend::Gitea => "gitea",
+            Backend::Https => "https",
+        }
+    }
+
+    // Get the repository and the branch from the archive url.
+    // A plain zip file has no repository, so it has no archive.
+    pub fn archive(&self, in_url: &Url) -> Result<Archive> {
+        let invalid_url = || WamiError::Config(format!("The url {} is no archive of a {} repository.", in_url, self.name()));
+        let segments: Vec<&str> = in_url.path_segments().ok_or_else(invalid_url)?.collect();
+        let zip_name: &str = segments
+            .last()
+            .and_then(|name| name.strip_suffix(".zip"))
+            .filter(|name| !name.is_empty())
+            .ok_or_else(invalid_url)?;
+
+        let project: Vec<String> = match self {
+            // The branch of /archive/refs/heads/BRANCH.zip can have slashes, a tag is in /archive/refs/tags/TAG.zip.
+            // The short form /archive/BRANCH.zip is taken as a branch.
+            Backend::GitHub if segments.len() >= 4 && segments[2] == "archive" => {
+                let (is_tag, reference_segments): (bool, &[&str]) = match &segments[3..] {
+                    ["refs", "heads", rest @ ..] => (false, rest),
+                    ["refs", "tags", rest @ ..] => (true, rest),
+                    rest => (false, rest),
+                };
+                if reference_segments.is_empty() {
+                    return Err(invalid_url());
+                }
+                let mut reference: Vec<&str> = reference_segments[..reference_segments.len() - 1].to_vec();
+                reference.push(zip_name);
+                return Ok(Archive {
+                    project: segments[..2].iter().map(|segment| segment.to_string()).collect(),
+                    reference: reference.join("/"),
+                    is_tag,
+                });
+            }
+            Backend::GitLab => {
+                let archive_index: usize = segments
+                    .windows(2)
+                    .position(|pair| pair == ["-", "archive"])
+                    .ok_or_else(invalid_url)?;
+                // The branch of /-/archive/BRANCH/PROJECT-BRANCH.zip can have slashes as well.
+                let branch_segments: &[&str] = &segments[archive_index + 2..segments.len() - 1];
+                if archive_index < 2 || branch_segments.is_empty() {
+                    return Err(invalid_url());
+                }
+                return Ok(Archive {
+                    project: segments[..archive_index].iter().map(|segment| segment.to_string()).collect(),
+                    reference: branch_segments.join("/"),
+                    is_tag: false,
+                });
+            }
+            Backend::Gitea if segments.len() == 4 && segments[2] == "archive" => {
+                segments[..2].iter().map(|segment| segment.to_string()).collect()
+            }
+            _ => return Err(invalid_url()),
+        };
+        Ok(Archive {
+            project,
+            reference: zip_name.to_owned(),
+            is_tag: false,
+        })
+    }
+
+    // The url of the API, which answers with the commit of the branch of the lake.
+    // A plain zip file is asked for its headers directly.
+    pub fn version_url(&self, in_url: &Url) -> Result<Url> {
+        if *self == Backend::Https {
+            return Ok(in_url.clone());
+        }
+        let archive: Archive = self.archive(in_url)?;
+        let branch: String = archive.reference;
+        match self {
+            // A tag is asked for its commit, the commit of a branch is in its ref.
+            Backend::GitHub if archive.is_tag => self.api_url(in_url, &["commits", &branch]),
+            Backend::GitHub => {
+                let mut path: Vec<&str> = vec!["git", "refs", "heads"];
+                path.extend(branch.split('/'));
+                self.api_url(in_url, &path)
+            }
+            Backend::GitLab => self.api_url(in_url, &["repository", "branches", &branch]),
+            _ => self.api_url(in_url, &["branches", &branch]),
+        }
+    }
+
+    // The url of the API, which answers with the commit of a commit or a tag of the repository of the lake.
+    pub fn commit_url(&self, in_url: &Url, in_reference: &str) -> Result<Url> {
+        match self {
+            Backend::GitHub => self.api_url(in_url, &["commits", in_reference]),
+            Backend::GitLab => self.api_url(in_url, &["repository", "commits", in_reference]),
+            _ => self.api_url(in_url, &["git", "commits", in_reference]),
+        }
+    }
+
+    // The url of the API, which compares a commit with the branch of the lake.
+    pub fn compare_url(&self, in_url: &Url, in_commit: &str) -> Result<Url> {
+        let branch: String = self.archive(in_url)?.reference;
+        let range: String = format!("{}...{}", in_commit, branch);
+        match self {
+            Backend::GitLab => {
+                let mut out_url: Url = self.api_url(in_url, &["repository", "compare"])?;
+                out_url.query_pairs_mut().append_pair("from", in_commit).append_pair("to", &branch);
+                Ok(out_url)
+            }
+            _ => {
+                let mut path: Vec<&str> = vec!["compare"];
+                path.extend(range.split('/'));
+                self.api_url(in_url, &path)
+            }
+        }
+    }
+
+    // The url of the API of the repository of the lake with the given path after it,
+    // e.g. https://api.github.com/repos/OWNER/REPOSITORY/commits/v1.0.
+    // A plain zip file has no repository, so it has no API.
+    fn api_url(&self, in_url: &Url, in_path: &[&str]) -> Result<Url> {
+        let archive: Archive = self.archive(in_url)?;
+
+        let mut out_url: Url = match self {
+            Backend::GitHub => Url::parse("https://api.github.com").unwrap(),
+            _ => {
+                let mut origin: Url = in_url.clone();
+                origin.set_path("");
+                origin.set_query(None);
+                origin.set_fragment(None);
+                origin
+            }
+        };
+        {
+            let mut segments = out_url
+                .path_segments_mut()
+                .map_err(|_| WamiError::Config(format!("Invalid url of the lake: {}", in_url)))?;
+            segments.clear();
+            match self {
+                Backend::GitHub => {
+                    segments.extend(["repos", &archive.project[0], &archive.project[1]]);
+                }
+                // The path of the project is a single segment, the slashes are encoded.
+                Backend::GitLab => {
+                    segments.extend(["api", "v4", "projects", &archive.project.join("/")]);
+                }
+                Backend::Gitea => {
+                    segments.extend(["api", "v1", "repos", &archive.project[0], &archive.project[1]]);
+                }
+                Backend::Https => unreachable!(),
+            }
+            segments.extend(in_path);
+        }
+        Ok(out_url)
+    }
+
+    // The url of the archive of a commit or a tag, a pinned lake is downloaded from it instead of its branch.
+    pub fn pinned_url(&self, in_url: &Url, in_reference: &str) -> Result<Url> {
+        let archive: Archive = self.archive(in_url)?;
+        let mut out_url: Url = in_url.clone();
+        out_url.set_query(None);
+        out_url.set_fragment(None);
+        {
+            let mut segments = out_url
+                .path_segments_mut()
+                .map_err(|_| WamiError::Config(format!("Invalid url of the lake: {}", in_url)))?;
+            segments.clear();
+            segments.extend(&archive.project);
+            match self {
+                // The archive of GitLab is named after the project and the commit.
+                Backend::GitLab => {
+                    let project_name: &str = archive.project.last().map(String::as_str).unwrap_or_default();
+                    segments.extend(["-", "archive", in_reference, &format!("{}-{}.zip", project_name, in_reference)]);
+                }
+                _ => {
+                    segments.extend(["archive", &format!("{}.zip", in_reference)]);
+                }
+            }
+        }
+        Ok(out_url)
+    }
+
+    // The header with the token of a private lake, if the token is set.
+    // It is sent with the download of the archive and the request of the version.
+    pub fn auth_header(&self) -> Option<(&'static str, String)> {
+        let token = |in_var: &str| env::var(in_var).ok().filter(|token| !token.is_empty());
+        match self {
+            Backend::GitLab => token(GITLAB_TOKEN_VAR).map(|token| ("PRIVATE-TOKEN", token)),
+            Backend::Gitea => token(GITEA_TOKEN_VAR).map(|token| ("Authorization", format!("token {}", token))),
+            Backend::GitHub | Backend::Https => None,
+        }
+    }
+
+    // Fetch the version of the lake, it is saved as the hash of the lake.
+    // The git backends answer with the commit of the branch, a plain zip file with its ETag
+    // or its Last-Modified header.
+    pub fn get_version(&self, in_url: &str) -> Result<String> {
+        let url: Url = Backend::parse_url(in_url)?;
+        let version_url: Url = self.version_url(&url)?;
+
+        match self {
+            Backend::GitHub => {
+                // The commit of a tag is answered by the commits API, the commit of a branch by the refs API.
+                let pointer: &str = if self.archive(&url)?.is_tag { "/sha" } else { "/object/sha" };
+                Backend::version_from_json(&self.get_json(&version_url)?, pointer, version_url.as_str())
+            }
+            Backend::GitLab | Backend::Gitea => {
+                Backend::version_from_json(&self.get_json(&version_url)?, "/commit/id", version_url.as_str())
+            }
+            Backend::Https => self.get_head_version(version_url.as_str()),
+        }
+    }
+
+    // Fetch the commit of a commit or a tag, a full commit hash is taken as it is.
+    // A pinned lake is downloaded with the commit, so a tag which is moved later does not change the lake.
+    pub fn get_commit(&self, in_url: &str, in_reference: &str) -> Result<String> {
+        let url: Url = Backend::parse_url(in_url)?;
+        if Backend::is_commit(in_reference) {
+            return Ok(in_reference.to_lowercase());
+        }
+
+        let commit_url: Url = self.commit_url(&url, in_reference)?;
+        let pointer: &str = if *self == Backend::GitLab { "/id" } else { "/sha" };
+        Backend::version_from_json(&self.get_json(&commit_url)?, pointer, commit_url.as_str())
+            .map_err(|_| WamiError::InvalidInput(format!("There is no commit or tag {} in the repository of {}.", in_reference, in_url)))
+    }
+
+    // Fetch the number of commits the branch of the lake is ahead of a commit, e.g. of the pin of the lake.
+    pub fn get_distance(&self, in_url: &str, in_commit: &str) -> Result<u64> {
+        let compare_url: Url = self.compare_url(&Backend::parse_url(in_url)?, in_commit)?;
+        let body: String = self.get_json(&compare_url)?;
+        Backend::distance_from_json(*self, &body, compare_url.as_str())
+    }
+
+    // A full SHA-1 commit hash, it needs no request to find the commit.
+    fn is_commit(in_reference: &str) -> bool {
+        in_reference.len() == 40 && in_reference.chars().all(|character| character.is_ascii_hexdigit())
+    }
+
+    fn parse_url(in_url: &str) -> Result<Url> {
+        Url::parse(in_url).map_err(|_| WamiError::Config(format!("Invalid url of the lake: {}", in_url)))
+    }
+
+    // Send a request to the API of the backend and return the body of the response.
+    // GitHub has its own rate limit, so its requests go through the GitHub API of the search.
+    fn get_json(&self, in_url: &Url) -> Result<String> {
+        match self {
+            Backend::GitHub => get_github_api(in_url.as_str()),
+            _ => self.get_api(in_url.as_str()),
+        }
+    }
+
+    // Send a request to the API of a GitLab or Gitea host and return the body of the response.
+    fn get_api(&self, in_url: &str) -> Result<String> {
+        let mut request = Request::get(in_url);
+        if let Some((name, value)) = self.auth_header() {
+            request = request.header(name, value);
+        }
+        let mut response = request
+            .body(())
+            .map_err(|err| WamiError::Network(err.to_string()))?
+            .send()?;
+        let status: u16 = response.status().as_u16();
+        let body: String = response.text()?;
+        if !(200..300).contains(&status) {
+            return Err(WamiError::Network(format!("The API {} answered with the status {}", in_url, status)));
+        }
+        Ok(body)
+    }
+
+    // The version of a plain zip file is its ETag, or its Last-Modified header if the server sends no ETag.
+    fn get_head_version(&self, in_url: &str) -> Result<String> {
+        let response = Request::head(in_url)
+            .body(())
+            .map_err(|err| WamiError::Network(err.to_string()))?
+            .send()?;
+        if !response.status().is_success() {
+            return Err(WamiError::Network(format!(
+                "Failed to fetch the version of {}, status {}",
+                in_url,
+                response.status()
+            )));
+        }
+        let header = |in_name: &str| -> Option<String> {
+            response
+                .headers()
+                .get(in_name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.trim().to_owned())
+                .filter(|value| !value.is_empty())
+        };
+        header("etag").or_else(|| header("last-modified")).ok_or_else(|| {
+            WamiError::Network(format!("The server of {} sends no ETag and no Last-Modified header.", in_url))
+        })
+    }
+
+    // Take the commit out of the json answer of an API.
+    fn version_from_json(in_body: &str, in_pointer: &str, in_url: &str) -> Result<String> {
+        let parsed_body: Value = serde_json::from_str(in_body)
+            .map_err(|err| WamiError::Network(format!("Invalid answer of {}: {}", in_url, err)))?;
+        parsed_body
+            .pointer(in_pointer)
+            .and_then(Value::as_str)
+            .map(str::to_owned)
+            .ok_or_else(|| WamiError::Network(format!("There is no commit in the answer of {}.", in_url)))
+    }
+
+    // Take the number of commits out of the json answer of a compare API.
+    // GitLab lists the commits, GitHub and Gitea count them.
+    fn distance_from_json(in_backend: Backend, in_body: &str, in_url: &str) -> Result<u64> {
+        let parsed_body: Value = serde_json::from_str(in_body)
+            .map_err(|err| WamiError::Network(format!("Invalid answer of {}: {}", in_url, err)))?;
+        let distance: Option<u64> = match in_backend {
+            Backend::GitHub => parsed_body.pointer("/ahead_by").and_then(Value::as_u64),
+            Backend::GitLab => parsed_body.pointer("/commits").and_then(Value::as_array).map(|commits| commits.len() as u64),
+            _ => parsed_body.pointer("/total_commits").and_then(Value::as_u64),
+        };
+        distance.ok_or_else(|| WamiError::Network(format!("There is no number of commits in the answer of {}.", in_url)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(in_url: &str) -> Url {
+        Url::parse(in_url).unwrap()
+    }
+
+    #[test]
+    fn test_backend_is_detected_from_the_url() {
+        let cases = [
+            ("https://github.com/evait-security/wami-templates/archive/refs/heads/main.zip", Backend::GitHub),
+            ("https://github.com/evait-security/wami-templates/archive/main.zip", Backend::GitHub),
+            ("https://gitlab.acme.local/red/tools/wami-lake/-/archive/main/wami-lake-main.zip", Backend::GitLab),
+            ("https://codeberg.org/acme/wami-lake/archive/main.zip", Backend::Gitea),
+            ("https://files.acme.local/wami/lake.zip", Backend::Https),
+            ("https://files.acme.local/download?file=lake", Backend::Https),
+        ];
+        for (in_url, backend) in cases {
+            assert_eq!(Backend::detect(&url(in_url)), backend, "{}", in_url);
+        }
+    }
+
+    #[test]
+    fn test_backend_archive() {
+        let archive = Backend::GitLab
+            .archive(&url("https://gitlab.acme.local/red/tools/wami-lake/-/archive/release/2024/wami-lake-release-2024.zip"))
+            .unwrap();
+        assert_eq!(archive.project, vec!["red", "tools", "wami-lake"]);
+        assert_eq!(archive.reference, "release/2024");
+
+        let archive = Backend::Gitea.archive(&url("https://codeberg.org/acme/wami-lake/archive/dev.zip")).unwrap();
+        assert_eq!(archive.project, vec!["acme", "wami-lake"]);
+        assert_eq!(archive.reference, "dev");
+        assert!(!archive.is_tag);
+
+        assert!(Backend::Https.archive(&url("https://files.acme.local/lake.zip")).is_err());
+        assert!(Backend::GitLab.archive(&url("https://gitlab.acme.local/lake.zip")).is_err());
+    }
+
+    #[test]
+    fn test_backend_archive_of_github() {
+        let archive = |in_url: &str| Backend::GitHub.archive(&url(in_url)).unwrap();
+
+        // The whole branch after refs/heads, not only its last part.
+        let branch = archive("https://github.com/acme/wami-lake/archive/refs/heads/feature/x.zip");
+        assert_eq!(branch.project, vec!["acme", "wami-lake"]);
+        assert_eq!(branch.reference, "feature/x");
+        assert!(!branch.is_tag);
+
+        // A tag is no branch.
+        let tag = archive("https://github.com/acme/wami-lake/archive/refs/tags/v1.zip");
+        assert_eq!(tag.reference, "v1");
+        assert!(tag.is_tag);
+
+        let short = archive("https://github.com/acme/wami-lake/archive/main.zip");
+        assert_eq!(short.reference, "main");
+        assert!(!short.is_tag);
+
+        assert!(Backend::GitHub.archive(&url("https://github.com/acme/wami-lake/archive/refs/heads/.zip")).is_err());
+    }
+
+    #[test]
+    fn test_backend_version_url_of_github_refs() {
+        let github = |in_url: &str| url(&format!("https://github.com/acme/wami-lake/archive/{}", in_url));
+
+        assert_eq!(
+            Backend::GitHub.version_url(&github("refs/heads/feature/x.zip")).unwrap().as_str(),
+            "https://api.github.com/repos/acme/wami-lake/git/refs/heads/feature/x"
+        );
+        assert_eq!(
+            Backend::GitHub.version_url(&github("refs/tags/v1.zip")).unwrap().as_str(),
+            "https://api.github.com/repos/acme/wami-lake/commits/v1"
+        );
+        assert_eq!(
+            Backend::GitHub.compare_url(&github("refs/heads/feature/x.zip"), "0a1b2c").unwrap().as_str(),
+            "https://api.github.com/repos/acme/wami-lake/compare/0a1b2c...feature/x"
+        );
+    }
+
+    #[test]
+    fn test_backend_version_url() {
+        let version_url = |in_backend: Backend, in_url: &str| in_backend.version_url(&url(in_url)).unwrap().to_string();
+
+        assert_eq!(
+            version_url(Backend::GitHub, "https://github.com/evait-security/wami-templates/archive/refs/heads/main.zip"),
+            "https://api.github.com/repos/evait-security/wami-templates/git/refs/heads/main"
+        );
+        assert_eq!(
+            version_url(Backend::GitLab, "https://gitlab.acme.local:8443/red/wami-lake/-/archive/main/wami-lake-main.zip"),
+            "https://gitlab.acme.local:8443/api/v4/projects/red%2Fwami-lake/repository/branches/main"
+        );
+        assert_eq!(
+            version_url(Backend::Gitea, "https://codeberg.org/acme/wami-lake/archive/main.zip"),
+            "https://codeberg.org/api/v1/repos/acme/wami-lake/branches/main"
+        );
+        assert_eq!(
+            version_url(Backend::Https, "https://files.acme.local/lake.zip"),
+            "https://files.acme.local/lake.zip"
+        );
+    }
+
+    #[test]
+    fn test_backend_pinned_urls() {
+        let github = url("https://github.com/evait-security/wami-templates/archive/refs/heads/main.zip");
+        let gitlab = url("https://gitlab.acme.local/red/wami-lake/-/archive/main/wami-lake-main.zip");
+        let gitea = url("https://codeberg.org/acme/wami-lake/archive/main.zip");
+
+        assert_eq!(
+            Backend::GitHub.pinned_url(&github, "v1.2").unwrap().as_str(),
+            "https://github.com/evait-security/wami-templates/archive/v1.2.zip"
+        );
+        assert_eq!(
+            Backend::GitLab.pinned_url(&gitlab, "0a1b2c").unwrap().as_str(),
+            "https://gitlab.acme.local/red/wami-lake/-/archive/0a1b2c/wami-lake-0a1b2c.zip"
+        );
+        assert_eq!(Backend::Gitea.pinned_url(&gitea, "0a1b2c").unwrap().as_str(), "https://codeberg.org/acme/wami-lake/archive/0a1b2c.zip");
+        assert!(Backend::Https.pinned_url(&url("https://files.acme.local/lake.zip"), "0a1b2c").is_err());
+
+        assert_eq!(
+            Backend::GitHub.commit_url(&github, "v1.2").unwrap().as_str(),
+            "https://api.github.com/repos/evait-security/wami-templates/commits/v1.2"
+        );
+        assert_eq!(
+            Backend::GitHub.compare_url(&github, "0a1b2c").unwrap().as_str(),
+            "https://api.github.com/repos/evait-security/wami-templates/compare/0a1b2c...main"
+        );
+        assert_eq!(
+            Backend::GitLab.compare_url(&gitlab, "0a1b2c").unwrap().as_str(),
+            "https://gitlab.acme.local/api/v4/projects/red%2Fwami-lake/repository/compare?from=0a1b2c&to=main"
+        );
+        assert_eq!(
+            Backend::Gitea.commit_url(&gitea, "v1.2").unwrap().as_str(),
+            "https://codeberg.org/api/v1/repos/acme/wami-lake/git/commits/v1.2"
+        );
+    }
+
+    #[test]
+    fn test_backend_distance_from_json() {
+        assert_eq!(Backend::distance_from_json(Backend::GitHub, r#"{"ahead_by": 3, "behind_by": 0}"#, "api").unwrap(), 3);
+        assert_eq!(Backend::distance_from_json(Backend::GitLab, r#"{"commits": [{"id": "a"}, {"id": "b"}]}"#, "api").unwrap(), 2);
+        assert_eq!(Backend::distance_from_json(Backend::Gitea, r#"{"total_commits": 0}"#, "api").unwrap(), 0);
+        assert!(Backend::distance_from_json(Backend::GitHub, "{}", "api").is_err());
+
+        // A full commit needs no request.
+        let commit = "3f8b349e0c4d7a2f5b6e1c9d8a7b6c5d4e3f2a1B";
+        assert_eq!(Backend::GitHub.get_commit("https://github.com/a/b/archive/main.zip", commit).unwrap(), commit.to_lowercase());
+    }
+
+    #[test]
+    fn test_backend_version_from_json() {
+        let body = r#"{"name": "main", "commit": {"id": "0a1b2c"}}"#;
+        assert_eq!(Backend::version_from_json(body, "/commit/id", "api").unwrap(), "0a1b2c");
+        assert!(Backend::version_from_json("{}", "/commit/id", "api").is_err());
+
+        assert_eq!(Backend::from_name("Forgejo"), Some(Backend::Gitea));
+        assert_eq!(Backend::from_name("svn"), None);
+    }
+}
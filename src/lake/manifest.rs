@@ -0,0 +1,278 @@
+use crate::error::{Result, WamiError};
+use minisign_verify::{PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+use zip::ZipArchive;
+
+// The manifest of a downloaded lake.
+// It records the SHA-256 hash of the archive and of every yaml file of the lake dir, so a template
+// which was changed after the download is found. The archive is kept next to the manifest,
+// so the templates can be checked against it and the signature of the archive can be checked again.
+// The files start with an underscore, so they are never read as templates.
+
+pub const MANIFEST_FILE_NAME: &str = "_manifest.yaml";
+pub const ARCHIVE_FILE_NAME: &str = "_archive.zip";
+pub const SIGNATURE_FILE_NAME: &str = "_archive.zip.minisig";
+
+// The signature of an archive is downloaded from the url of the archive with this extension, like minisign saves it.
+pub const SIGNATURE_EXTENSION: &str = ".minisig";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub archive: String, // The hash of the downloaded archive.
+    #[serde(default)]
+    pub url: String, // The url the lake was downloaded from.
+    #[serde(default)]
+    pub version: String, // The hash of the lake in the config.yaml, it is restored by `wami lake rollback`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pin: Option<String>, // The commit the lake was pinned to, it is restored by `wami lake rollback` as well.
+    #[serde(default)]
+    pub signed: bool, // True if the signature of the archive was checked with the public key of the lake.
+    #[serde(default)]
+    pub templates: BTreeMap<String, String>, // The hash of every yaml file of the lake dir by its name.
+}
+
+// The lower case hex string of the SHA-256 hash.
+pub fn sha256_hex(in_bytes: &[u8]) -> String {
+    Sha256::digest(in_bytes).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Check the minisign signature of an archive with the public key, which is pinned in the config.yaml.
+// The public key is the base64 line of a minisign public key, e.g. RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3.
+pub fn verify_signature(in_bytes: &[u8], in_signature: &str, in_public_key: &str) -> Result<()> {
+    let public_key: PublicKey = PublicKey::from_base64(in_public_key.trim())
+        .map_err(|err| WamiError::Config(format!("Invalid public key {}: {}", in_public_key, err)))?;
+    let signature: Signature = Signature::decode(in_signature)
+        .map_err(|err| WamiError::LakeCorrupt(format!("Invalid signature of the archive: {}", err)))?;
+    // Old versions of minisign sign the archive itself instead of its hash, both are ed25519 signatures.
+    public_key
+        .verify(in_bytes, &signature, true)
+        .map_err(|err| WamiError::LakeCorrupt(format!("The signature of the archive does not match the public key: {}", err)))
+}
+
+impl Manifest {
+    // Create the manifest of a lake, which was just extracted from the archive.
+    pub fn new(in_archive: &[u8], in_lake_dir: &Path, in_signed: bool) -> Result<Manifest> {
+        Ok(Manifest {
+            archive: sha256_hex(in_archive),
+            url: String::new(),
+            version: String::new(),
+            pin: None,
+            signed: in_signed,
+            templates: Manifest::hash_templates(in_lake_dir)?,
+        })
+    }
+
+    // The hash of every yaml file of a lake dir by its name, the templates and e.g. the _synonyms.yaml.
+    pub fn hash_templates(in_lake_dir: &Path) -> Result<BTreeMap<String, String>> {
+        let mut out_hashes: BTreeMap<String, String> = BTreeMap::new();
+        let entries = fs::read_dir(in_lake_dir)
+            .map_err(|err| WamiError::LakeCorrupt(format!("Failed to read {}: {}", in_lake_dir.display(), err)))?;
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if Manifest::is_lake_file(&path) {
+                let name: String = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                out_hashes.insert(name, sha256_hex(&fs::read(&path)?));
+            }
+        }
+        Ok(out_hashes)
+    }
+
+    // The hash of every yaml file of the lake dir in the archive.
+    // The lake dir is given relative to the dir the archive was extracted to, e.g. wami-templates-main/lake.
+    pub fn hash_archive_templates(in_archive: &[u8], in_lake_dir: &Path) -> Result<BTreeMap<String, String>> {
+        let mut archive = ZipArchive::new(std::io::Cursor::new(in_archive))?;
+        let mut out_hashes: BTreeMap<String, String> = BTreeMap::new();
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index)?;
+            let path: PathBuf = file.mangled_name();
+            if path.parent() != Some(in_lake_dir) || !Manifest::is_lake_file(&path) || file.is_dir() {
+                continue;
+            }
+            let mut bytes: Vec<u8> = Vec::new();
+            file.read_to_end(&mut bytes)?;
+            let name: String = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            out_hashes.insert(name, sha256_hex(&bytes));
+        }
+        Ok(out_hashes)
+    }
+
+    // The files of the lake, the manifest itself is no part of it.
+    fn is_lake_file(in_path: &Path) -> bool {
+        in_path.extension().map(|extension| extension == "yaml").unwrap_or(false)
+            && in_path.file_name().map(|name| name != MANIFEST_FILE_NAME).unwrap_or(false)
+    }
+
+    // Load the manifest of the download dir of a lake, a lake of an old version of wami has none.
+    pub fn load(in_download_dir: &Path) -> Result<Option<Manifest>> {
+        let path: PathBuf = in_download_dir.join(MANIFEST_FILE_NAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let file = fs::File::open(&path)?;
+        serde_yaml::from_reader(file)
+            .map(Some)
+            .map_err(|err| WamiError::LakeCorrupt(format!("Failed to read {}: {}", path.display(), err)))
+    }
+
+    pub fn save(&self, in_download_dir: &Path) -> Result<()> {
+        let yaml: String = serde_yaml::to_string(self).map_err(|err| WamiError::LakeCorrupt(err.to_string()))?;
+        fs::write(in_download_dir.join(MANIFEST_FILE_NAME), yaml)?;
+        Ok(())
+    }
+
+    // Check a downloaded lake against the manifest and return every problem.
+    // The templates of the lake dir and of the kept archive have to match the hashes of the manifest,
+    // the archive has to match its hash and, with a public key, its signature.
+    pub fn verify(&self, in_download_dir: &Path, in_lake_dir: &Path, in_public_key: Option<&str>) -> Result<Vec<String>> {
+        let mut out_problems: Vec<String> = self.compare_templates(&Manifest::hash_templates(in_lake_dir)?, "the lake dir");
+
+        let archive: Vec<u8> = match fs::read(in_download_dir.join(ARCHIVE_FILE_NAME)) {
+            Ok(archive) => archive,
+            Err(err) => {
+                out_problems.push(format!("The archive can not be read: {}", err));
+                return Ok(out_problems);
+            }
+        };
+        if sha256_hex(&archive) != self.archive {
+            out_problems.push("The archive was changed after the download.".to_string());
+        }
+        let archive_lake_dir: &Path = in_lake_dir.strip_prefix(in_download_dir).unwrap_or(Path::new(""));
+        match Manifest::hash_archive_templates(&archive, archive_lake_dir) {
+            Ok(hashes) => out_problems.extend(self.compare_templates(&hashes, "the archive")),
+            Err(err) => out_problems.push(err.to_string()),
+        }
+
+        if let Some(public_key) = in_public_key {
+            match fs::read_to_string(in_download_dir.join(SIGNATURE_FILE_NAME)) {
+                Ok(signature) => {
+                    if let Err(err) = verify_signature(&archive, &signature, public_key) {
+                        out_problems.push(err.to_string());
+                    }
+                }
+                Err(_) => out_problems.push("The lake was downloaded without its signature, update the lake.".to_string()),
+            }
+        }
+        Ok(out_problems)
+    }
+
+    // Compare the hashes of the manifest with other hashes, e.g. of the lake dir.
+    // Every changed, missing and unknown file is a problem.
+    pub fn compare_templates(&self, in_hashes: &BTreeMap<String, String>, in_where: &str) -> Vec<String> {
+        let mut out_problems: Vec<String> = Vec::new();
+        for (name, hash) in &self.templates {
+            match in_hashes.get(name) {
+                Some(other_hash) if other_hash == hash => {}
+                Some(_) => out_problems.push(format!("{} was changed in {}", name, in_where)),
+                None => out_problems.push(format!("{} is missing in {}", name, in_where)),
+            }
+        }
+        for name in in_hashes.keys().filter(|name| !self.templates.contains_key(*name)) {
+            out_problems.push(format!("{} is not part of the manifest, but of {}", name, in_where));
+        }
+        out_problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    // The key and the signature of the word test from the tests of minisign-verify.
+    const PUBLIC_KEY: &str = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+    const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+trusted comment: timestamp:1556193335\tfile:test
+y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==";
+
+    fn test_dir(in_name: &str) -> PathBuf {
+        let mut out_dir = std::env::temp_dir();
+        out_dir.push(format!("wami-manifest-test-{}-{}", in_name, std::process::id()));
+        let _ = fs::remove_dir_all(&out_dir);
+        fs::create_dir_all(&out_dir).unwrap();
+        out_dir
+    }
+
+    #[test]
+    fn test_signature_of_the_archive() {
+        assert!(verify_signature(b"test", SIGNATURE, PUBLIC_KEY).is_ok());
+        assert!(matches!(
+            verify_signature(b"Test", SIGNATURE, PUBLIC_KEY),
+            Err(WamiError::LakeCorrupt(_))
+        ));
+        assert!(matches!(verify_signature(b"test", SIGNATURE, "RWQ"), Err(WamiError::Config(_))));
+        assert!(verify_signature(b"test", "no signature", PUBLIC_KEY).is_err());
+    }
+
+    #[test]
+    fn test_manifest_finds_changed_templates() {
+        let dir = test_dir("changed");
+        fs::write(dir.join("nmap.yaml"), "name: nmap").unwrap();
+        fs::write(dir.join("_synonyms.yaml"), "dns: [domain]").unwrap();
+        fs::write(dir.join("README.md"), "no template").unwrap();
+
+        let manifest = Manifest::new(b"archive", &dir, false).unwrap();
+        manifest.save(&dir).unwrap();
+        assert_eq!(Manifest::load(&dir).unwrap(), Some(manifest.clone()));
+        assert_eq!(manifest.templates.keys().collect::<Vec<_>>(), vec!["_synonyms.yaml", "nmap.yaml"]);
+        assert!(manifest.compare_templates(&Manifest::hash_templates(&dir).unwrap(), "the lake").is_empty());
+
+        fs::write(dir.join("nmap.yaml"), "name: evil").unwrap();
+        fs::remove_file(dir.join("_synonyms.yaml")).unwrap();
+        fs::write(dir.join("nikto.yaml"), "name: nikto").unwrap();
+        let problems = manifest.compare_templates(&Manifest::hash_templates(&dir).unwrap(), "the lake");
+        assert_eq!(
+            problems,
+            vec![
+                "_synonyms.yaml is missing in the lake",
+                "nmap.yaml was changed in the lake",
+                "nikto.yaml is not part of the manifest, but of the lake",
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_manifest_of_the_archive() {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default();
+        for (name, content) in [("lake-main/lake/nmap.yaml", "name: nmap"), ("lake-main/README.yaml", "outside")] {
+            writer.start_file(name, options).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        let bytes = writer.finish().unwrap().into_inner();
+
+        let hashes = Manifest::hash_archive_templates(&bytes, Path::new("lake-main/lake")).unwrap();
+        assert_eq!(hashes.len(), 1);
+        assert_eq!(hashes["nmap.yaml"], sha256_hex(b"name: nmap"));
+        assert!(Manifest::load(&test_dir("missing")).unwrap().is_none());
+
+        // The download dir as it is left by a download of the lake.
+        let dir = test_dir("verify");
+        let lake_dir = dir.join("lake-main").join("lake");
+        fs::create_dir_all(&lake_dir).unwrap();
+        fs::write(lake_dir.join("nmap.yaml"), "name: nmap").unwrap();
+        fs::write(dir.join(ARCHIVE_FILE_NAME), &bytes).unwrap();
+        let manifest = Manifest::new(&bytes, &lake_dir, false).unwrap();
+        assert!(manifest.verify(&dir, &lake_dir, None).unwrap().is_empty());
+
+        // A pinned key needs the signature of the archive.
+        assert_eq!(manifest.verify(&dir, &lake_dir, Some(PUBLIC_KEY)).unwrap().len(), 1);
+        fs::write(dir.join(SIGNATURE_FILE_NAME), SIGNATURE).unwrap();
+        assert_eq!(manifest.verify(&dir, &lake_dir, Some(PUBLIC_KEY)).unwrap().len(), 1);
+
+        // A changed template and a changed archive are both found.
+        fs::write(lake_dir.join("nmap.yaml"), "name: evil").unwrap();
+        fs::write(dir.join(ARCHIVE_FILE_NAME), b"no zip").unwrap();
+        assert_eq!(manifest.verify(&dir, &lake_dir, None).unwrap().len(), 3);
+        fs::write(dir.join(ARCHIVE_FILE_NAME), &bytes).unwrap();
+        assert_eq!(manifest.verify(&dir, &lake_dir, None).unwrap(), vec!["nmap.yaml was changed in the lake dir"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{output::Hit, search::NameMatch, template::Template};
+use colored::Colorize;
+
+// The result of a search in the lake.
+// Only the templates which match the search are kept, the best hit first.
+// The results can be iterated like a list of templates, the text for the console is only built on request.
+
+#[derive(Debug, Clone)]
+pub struct SearchResults {
+    templates: Vec<Template>,       // The hits with their score, the best hit first.
+    name_match: Option<NameMatch>,  // The template the user named, it is always the first hit.
+    show_lake: bool,                // The text shows the lake of every hit, if there are several lakes.
+}
+
+impl SearchResults {
+    pub fn new(in_templates: Vec<Template>, in_name_match: Option<NameMatch>, in_show_lake: bool) -> SearchResults {
+        SearchResults {
+            templates: in_templates,
+            name_match: in_name_match,
+            show_lake: in_show_lake,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.templates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.templates.is_empty()
+    }
+
+    // The hits from the best to the worst.
+    pub fn iter(&self) -> std::slice::Iter<'_, Template> {
+        self.templates.iter()
+    }
+
+    pub fn get_name_match(&self) -> Option<&NameMatch> {
+        self.name_match.as_ref()
+    }
+
+    // The id of the named tool, if the user named it with a typo.
+    pub fn did_you_mean(&self) -> Option<&str> {
+        match &self.name_match {
+            Some(name_match) if !name_match.exact => Some(&name_match.id),
+            _ => None,
+        }
+    }
+
+    // The best hits as a list for the machine readable output, the best hit has rank 1.
+    pub fn hits(&self, how_many_max: usize) -> Vec<Hit> {
+        self.iter()
+            .take(how_many_max)
+            .enumerate()
+            .map(|(index, template)| Hit::from_template(index + 1, template))
+            .collect()
+    }
+
+    // The best hits as text for the console.
+    // With the sort value desc the best hit is printed last, so it is next to the prompt.
+    // The did you mean line is printed next to the best hit.
+    pub fn to_string(&self, how_many_max: usize, in_sort_value: &str, show_all: bool, why_not: bool, explain: bool) -> String {
+        let max_hits_templates: &[Template] = &self.templates[..how_many_max.min(self.templates.len())];
+        let did_you_mean: Option<String> = self
+            .did_you_mean()
+            .map(|id| format!("{} {}?", "Did you mean".yellow(), id.bold().yellow()));
+
+        let mut lines: Vec<String> = Vec::new();
+        for (index, template) in max_hits_templates.iter().enumerate() {
+            let template_string = if show_all {
+                template.to_string(why_not)
+            } else {
+                template.to_short_string(why_not)
+            };
+            let mut line = if self.show_lake {
+                format!(
+                    "{} {} {}",
+                    (index + 1).to_string().magenta(),
+                    format!("[{}]", template.lake()).truecolor(150, 150, 150),
+                    template_string
+                )
+            } else {
+                format!("{} {}", (index + 1).to_string().magenta(), template_string)
+            };
+            if explain {
+                line.push('\n');
+                line.push_str(&self.explain_to_string(template));
+            }
+            lines.push(line);
+        }
+
+        if in_sort_value == "desc" {
+            lines.reverse();
+            lines.extend(did_you_mean);
+        } else if let Some(did_you_mean) = did_you_mean {
+            lines.insert(0, did_you_mean);
+        }
+        lines.join("\n")
+    }
+
+    // The explain line of a template, with a note if it is first because the user named it.
+    fn explain_to_string(&self, in_template: &Template) -> String {
+        let mut out_string = in_template.explain_to_string();
+        if let Some(name_match) = &self.name_match {
+            if name_match.id == in_template.id() {
+                let name_line = if name_match.exact {
+                    "\n      first hit, the name was found".to_string()
+                } else {
+                    "\n      first hit, the name was found with a typo".to_string()
+                };
+                out_string.push_str(&name_line.truecolor(150, 150, 150).to_string());
+            }
+        }
+        out_string
+    }
+}
+
+impl IntoIterator for SearchResults {
+    type Item = Template;
+    type IntoIter = std::vec::IntoIter<Template>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.templates.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a SearchResults {
+    type Item = &'a Template;
+    type IntoIter = std::slice::Iter<'a, Template>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.templates.iter()
+    }
+}
@@ -0,0 +1,36 @@
+//! wami finds the tools you forgot the name of.
+//!
+//! The lake is a collection of yaml templates, one for every tool. It is searched with a ranked
+//! index, the command line tool `wami` is one front end of this library.
+//!
+//! ```no_run
+//! use wami::{Lake, Search};
+//!
+//! let lake = Lake::open()?;
+//! let search = Search::builder().words(["dns", "lookup"]).query("-tag:windows").build()?;
+//! for template in &lake.search(search) {
+//!     println!("{} {:?}", template.id(), template.references());
+//! }
+//! # Ok::<(), wami::WamiError>(())
+//! ```
+
+mod cache;
+pub mod config;
+pub mod error;
+pub mod github_search;
+pub mod github_topic;
+pub mod graph;
+pub mod index;
+pub mod lake;
+pub mod output;
+pub mod search;
+pub mod template;
+mod yaml_template;
+
+pub use config::{Config, LakeSource};
+pub use error::WamiError;
+pub use github_search::GithubSearch;
+pub use lake::{results::SearchResults, Lake};
+pub use output::{Format, Hit};
+pub use search::{builder::SearchBuilder, Search};
+pub use template::Template;
@@ -1,561 +1,510 @@
-mod config;
-mod lake;
-mod search;
-mod template;
-mod yaml_template;
-mod github_topic;
-mod github_search;
-
-use clap::{App, Arg};
-use colored::Colorize;
-use std::io::{self};
+mod browse;
+mod cli;
 
+use clap::{CommandFactory, Parser};
+use cli::{Cli, Command, ConfigCommand, GithubCommand, GithubSearchArgs, LakeCommand, OutputArgs, SearchArgs};
+use colored::Colorize;
+use std::io;
+use wami::{config, github_search, github_topic, graph, lake, output, search, WamiError};
 
 fn main() {
-    // Define the command-line arguments
-    let help_flag_github= "--github".green();
-    let help_flag_list_topics = "--list-topics".green();
-    let help_flag_show_all = "-a".green();
-    let help_flag_search_all = "-s".green();
-    let help_flag_search_order = "-S".green();
-    let help_value_search_order_desc = "desc".truecolor(200,200,200);
-    let example_text = format!("Example:\n  {} {} {} {} {} {}\n    {}\n\n  {} {} {} {} {} {}\n    {}\n\n  {} {} {}\n    {}",
-                                    "wami".magenta(),
-                                    help_flag_show_all,
-                                    help_flag_search_order,
-                                    help_value_search_order_desc,
-                                    help_flag_search_all,
-                                    "pentest".truecolor(90,90,255),
-                                    "This example will search in the lake with extended output in descending order in all categories for the word pentest.",
-                                    "wami".magenta(),
-                                    help_flag_show_all,
-                                    help_flag_search_order,
-                                    help_value_search_order_desc,
-                                    help_flag_github,
-                                    "pentest".truecolor(90,90,255),
-                                    "This example will search in GitHub with extended output in descending order for the word pentest.",
-                                    "wami".magenta(),
-                                    help_flag_list_topics,
-                                    "pentest".truecolor(90,90,255),
-                                    "This example will search in GitHub for topics with the word pentest."
-                                );
-    let about_text = format!("{} is a user-friendly tool designed in Rust, powered by Cargo, to assist individuals who struggle with remembering the names of the various programs they utilize. This open-source program aims to simplify the process of finding the most suitable programs for specific tasks.\n\nCreated at 10.07.2023", "WAMI".bold().green());
-    let app = App::new(format!("{} - What am I", "WAMI".bold().green()))
-        .version("\tVersion: 0.1.0\n")
-        .author("evait security GmbH\nNxtTAB <wami@evait.de>\n\n")
-        .about(&*about_text)
-        .arg(
-            Arg::with_name("strings")
-                .value_name("STRING")
-                .required(false)
-                .multiple(true),
-        )
-        .arg(
-            Arg::with_name("search-all")
-                .short("s")
-                .long("search-all")
-                .value_name("SEARCH_ALL")
-                .help("The search all functionality will search throw all fields with the same search criteria.")
-                .required(false)
-                .multiple(true)
-        )
-        .arg(
-            Arg::with_name("search-unique-name")
-                .short("n")
-                .long("search-unique-name")
-                .value_name("SEARCH_UNIQUE_NAME")
-                .help("This will search throw all the unique names with the values of SEARCH_UNIQUE_NAME.")
-                .required(false)
-                .multiple(true)
-        )
-        .arg(
-            Arg::with_name("search-title")
-                .short("t")
-                .long("search-title")
-                .value_name("SEARCH_TITLE")
-                .help("This will search throw all the title with the values of SEARCH_TITLE.")
-                .required(false) .multiple(true) )
-        .arg(
-            Arg::with_name("search-tags")
-                .short("T")
-                .long("search-tags")
-                .value_name("SEARCH_TAGS")
-                .help("This will search throw all the tags with the values.")
-                .required(false)
-                .multiple(true)
-        )
-        .arg(
-            Arg::with_name("search-description")
-                .short("d")
-                .long("search-description")
-                .value_name("SEARCH_DESCRIPTION")
-                .help("This will search throw all the description with the values of SEARCH_DESCRIPTION.")
-                .required(false)
-                .multiple(true)
-        )
-        .arg(
-            Arg::with_name("search-references")
-                .short("r")
-                .long("search-references")
-                .value_name("SEARCH_REFERENCES")
-                .help("This will search throw all the references with the values of SEARCH_REFERENCES.")
-                .required(false)
-                .multiple(true)
-        )
-        .arg(
-            Arg::with_name("list-topics")
-                .long("list-topics")
-                .value_name("LIST-TOPICS")
-                .help("This will use the GitHub API to list topics. This will exclude searching in the lake.")
-                .required(false)
-                .multiple(true)
-        )
-        .arg(
-            Arg::with_name("sort")
-                .short("S")
-                .long("sort")
-                .value_name("SORT")
-                .help("This will determine the sorting direction asc or desc, desc is the default direction.")
-                .required(false)
-                .multiple(false)
-        )
-        .arg(
-            Arg::with_name("url")
-                .short("U")
-                .long("url")
-                .value_name("URL")
-                .help("Set an alternative url for the lake download.")
-                .required(false)
-                .multiple(false)
-        )
-        .arg(
-            Arg::with_name("update")
-                .short("u")
-                .long("update")
-                .value_name("UPDATE")
-                .help("Make an update of the lake.")
-                .required(false)
-                .multiple(false)
-                .takes_value(false)
-        )
-        .arg(
-            Arg::with_name("offline")
-                .short("o")
-                .long("offline")
-                .value_name("OFFLINE")
-                .help("Set this flag if you do not want the online check for the updated lake file.")
-                .required(false)
-                .multiple(false)
-                .takes_value(false)
-        )
-        .arg(
-            Arg::with_name("min-stars")
-                .long("min-stars")
-                .value_name("MIN-STARS")
-                .help("Set the minimum of stars that have to be present in the Github repos. The default value is 100")
-                .required(false)
-                .multiple(false)
-        )
-        .arg(
-            Arg::with_name("max-stars")
-                .long("max-stars")
-                .value_name("MAX-STARS")
-                .help("Set the maximum of stars that have to be present in the GitHub repos. The default value is set to 0 which means that there is no limit.")
-                .required(false)
-                .multiple(false)
-        )
-        .arg(
-            Arg::with_name("github-fork")
-                .long("github-fork")
-                .help("This will set the search option for GitHub to the forks of an project. The default value is set to false.")
-                .required(false)
-                .multiple(false)
-                .takes_value(false)             
-        )
-        .arg(
-            Arg::with_name("max")
-                .short("M")
-                .long("max")
-                .value_name("MAX")
-                .help("Set the maximum of listed programs default (MAX = 10).")
-                .required(false)
-                .multiple(false)
-        )
-        .arg(
-            Arg::with_name("show-all")
-                .short("a")
-                .long("show-all")
-                .takes_value(false)
-                .help("Display detailed list of all available information.")
-                .required(false)
-                .multiple(false)
-        )
-        .arg(
-            Arg::with_name("why_not")
-                .short("W")
-                .long("why_not")
-                .value_name("WHY_NOT")
-                .takes_value(false)
-                .help("Set the why not flag to see other programs, witch are better for this use case.")
-                .required(false)
-                .multiple(false)
-        )
-        .arg(
-            Arg::with_name("github")
-                .long("github")
-                .takes_value(false)
-                .help("This will set the search to the GitHub API.")
-                .required(false)
-                .multiple(false)
-        ).after_help(&*example_text);
-
-        let matches = app.clone().get_matches();
-       
-    // If there are no arguments show the help.
-    if std::env::args().len() <= 1 {
-        app.write_help(&mut io::stdout()).unwrap();
-        println!(); // New line at the end of the help output
+    let cli = Cli::parse();
+
+    match cli.command {
+        Some(Command::Search(search_args)) => search_lake(search_args),
+        Some(Command::Github { command: GithubCommand::Search(github_args) }) => search_github(github_args),
+        Some(Command::Github { command: GithubCommand::Topics { words } }) => {
+            match github_topic::get_github_topics(words) {
+                Ok(topics) if topics.is_empty() => exit_with(WamiError::NoResults),
+                Ok(topics) => println!("{}", github_topic::topics_to_string(&topics)),
+                Err(err) => exit_with(err),
+            }
+        }
+        Some(Command::Lake { command }) => match command {
+            LakeCommand::Update { name } => update_lake(name.as_deref()),
+            LakeCommand::Rollback { name } => rollback_lake(name.as_deref()),
+            LakeCommand::Pin { reference, lake } => pin_lake(&reference, lake.as_deref()),
+            LakeCommand::Unpin { name } => unpin_lake(name.as_deref()),
+            LakeCommand::Status { offline } => print_lake_status(offline),
+            LakeCommand::Use { url, lake } => use_lake(&url, lake.as_deref()),
+            LakeCommand::Add { location, name, priority, backend, public_key } => {
+                add_lake(&location, name.as_deref(), priority, backend.as_deref(), public_key)
+            }
+            LakeCommand::Verify { name } => verify_lakes(name.as_deref()),
+            LakeCommand::Remove { name } => remove_lake(&name),
+            LakeCommand::List => list_lake(),
+        },
+        Some(Command::Config { command }) => match command {
+            ConfigCommand::Get { key } => print_config_value(key.as_deref()),
+            ConfigCommand::Set { key, value } => set_config_value(&key, &value),
+        },
+        Some(Command::Show { id }) => show_template(&id),
+        Some(Command::Alternatives { id, depth, words }) => show_alternatives(&id, depth, &words.join(" ")),
+        Some(Command::Graph { format }) => export_graph(&format),
+        // If there are no arguments show the help.
+        None if cli.search.is_empty() => {
+            let _ = Cli::command().print_help();
+            println!(); // New line at the end of the help output
+        }
+        // Plain words are a search in the lake.
+        None => search_lake(cli.search),
+    }
+}
+
+// Print the error to stderr and leave wami with the exit code of the error.
+// The exit codes are documented in the README, so scripts can tell the errors apart.
+fn exit_with(in_error: WamiError) -> ! {
+    match &in_error {
+        // The similar ids are highlighted, so the user can pick one.
+        WamiError::UnknownId { id, suggestions } => {
+            eprintln!("There is no template with the id {} in the lake.", id.bold());
+            if !suggestions.is_empty() {
+                eprintln!("{} {}?", "Did you mean".yellow(), suggestions.join(", ").bold().yellow());
+            }
+        }
+        error => eprintln!("{}", error),
+    }
+    std::process::exit(in_error.exit_code());
+}
+
+// Use the search arguments to define the search parameters.
+// The weights of the config.yaml can be overridden on the command line.
+fn create_search(in_args: &SearchArgs) -> search::Search {
+    let mut builder = search::Search::builder()
+        .words(&in_args.words)
+        .all(&in_args.search_all)
+        .names(&in_args.search_unique_name)
+        .title(&in_args.search_title)
+        .tags(&in_args.search_tags)
+        .description(&in_args.search_description)
+        .references(&in_args.search_references)
+        .weights(config::Config::new().map(|config| config.weights).unwrap_or_default())
+        .synonyms(!in_args.no_synonyms);
+    for query in &in_args.query {
+        builder = builder.query(query);
+    }
+    for weight_override in &in_args.weight {
+        builder = builder.weight_overrides(weight_override);
+    }
+
+    builder.build().unwrap_or_else(|err| exit_with(err))
+}
+
+// Search the lake and print the hits.
+fn search_lake(in_args: SearchArgs) {
+    let search: search::Search = create_search(&in_args);
+
+    if in_args.interactive {
+        // The words of the command line are the first search of the browser.
+        browse_lake(search, &in_args.words.join(" "));
         return;
     }
-    
-    // using the search struct to define the search parameters.
-    let mut search: search::Search 
-        = search::Search::new_empty();
-
-    // Is default search set by entering just strings,
-    // then we will search for tags
-    if let Some(search_names) 
-        = matches.values_of("strings") 
-    {
-        search
-            .tags_set(
-                &search_names
-                    .map(|tag| 
-                        tag
-                            .to_string()
-                    )
-                    .collect()
+
+    let format: output::Format = output::Format::from_name(&in_args.output.format).unwrap_or(output::Format::Text);
+    let output_template: Option<output::OutputTemplate> = read_output_template(&in_args.output);
+    let max_list: usize = in_args.output.max;
+
+    let lake = open_lake();
+    let results = lake.search(search);
+
+    if let Some(output_template) = &output_template {
+        print_hits_with_template(&results.hits(max_list), output_template);
+    } else if format != output::Format::Text {
+        // An empty list is printed as well, so a script can always parse the output.
+        print_hits(&results.hits(max_list), format);
+    } else if !results.is_empty() {
+        println!(
+            "{}",
+            results.to_string(max_list, &in_args.output.sort, in_args.output.show_all, in_args.why_not, in_args.explain)
         );
     }
 
-    // Is search all set?
-    if let Some(search_names) 
-        = matches.values_of("search-all") 
-    {
-        let search_all_string: String = 
-            search_names
-                .clone()
-                .collect::<Vec<_>>()
-                .join(" ");
-        
-        let search_all_vec: Vec<String>=
-            search_names
-                .map(|name|
-                    name
-                        .to_string()
-                )
-                .collect();   
-
-        search
-            .id_set(
-                &search_all_string
-            );
-
-        search
-            .title_set(
-                &search_all_string
-            );
-        
-        // It is possible that the search.tags is not empty,
-        // because of default search is tags.
-        let mut search_tags_vec: Vec<String> =
-            search_all_vec
-                .clone();
-
-        search_tags_vec
-            .push(
-                search
-                    .tags_get()
-                        .iter()
-                        .map(|tag| tag
-                            .to_string() 
-                        )
-                        .collect()
-            );
-
-        search
-            .tags_set(
-                &search_tags_vec
-        );
-        
-        search
-            .description_set(
-                &search_all_string
-        );
-        
-        search.reference_set(
-            &search_all_vec
-        );
+    if !in_args.offline {
+        for lake_source in lake.get_config().enabled_lakes() {
+            match lake::Lake::is_outdated(lake_source) {
+                Ok(outdated) => {
+                    if outdated {
+                        let message = format!(
+                            "{}",
+                            format!("Please update the lake {} with wami lake update, it is outdated.", lake_source.name)
+                                .bold()
+                                .red()
+                        );
+                        print_notice(&message, format, output_template.is_some());
+                    }
+                },
+                Err(_err) => print_notice(
+                    &format!("Version of the lake {} can not be downloaded.", lake_source.name),
+                    format,
+                    output_template.is_some(),
+                ),
+            }
+        }
     }
 
-    // Is search unique names set?
-    if let Some(search_names) 
-        = matches.values_of("search-unique-name") 
-    {
-        search.id_set(
-            &(
-                search
-                    .id_get()
-                        .clone()
-                        .to_owned()
-                + " "
-                + &search_names
-                    .collect::<Vec<_>>()
-                    .join(" ")
-            )
-        );
+    if results.is_empty() {
+        exit_with(WamiError::NoResults);
+    }
+}
+
+// Search the repositories of GitHub and print the hits.
+fn search_github(in_args: GithubSearchArgs) {
+    // The words are the tags of the repositories.
+    let search = search::Search::builder()
+        .tags(&in_args.words)
+        .stars(in_args.min_stars, in_args.max_stars)
+        .github_fork(in_args.fork)
+        .build()
+        .unwrap_or_else(|err| exit_with(err));
+
+    let format: output::Format = output::Format::from_name(&in_args.output.format).unwrap_or(output::Format::Text);
+    let output_template: Option<output::OutputTemplate> = read_output_template(&in_args.output);
+    let max_list: usize = in_args.output.max;
+
+    let search_result = github_search::GithubSearch::new(search).unwrap_or_else(|err| exit_with(err));
+    let hits: Vec<output::Hit> = search_result.hits(max_list);
+
+    if let Some(output_template) = &output_template {
+        print_hits_with_template(&hits, output_template);
+    } else if format != output::Format::Text {
+        print_hits(&hits, format);
+    } else if !hits.is_empty() {
+        println!("{}", search_result.to_string(max_list, &in_args.output.sort, in_args.output.show_all));
     }
 
-    // Is search title set?
-    if let Some(search_names) = matches.values_of("search-title") {
-        search.title_set(
-            &(
-                search
-                    .title_get()
-                        .to_owned()
-                + " "
-                + &search_names
-                    .collect::<Vec<_>>()
-                    .join(" ")
-            )
-        );
+    if hits.is_empty() {
+        exit_with(WamiError::NoResults);
+    }
+}
+
+// Download the newest version of all enabled lakes or of a single lake.
+fn update_lake(in_name: Option<&str>) {
+    let config = lake::Lake::update(in_name).unwrap_or_else(|err| exit_with(err));
+    for lake_source in &config.lakes {
+        if in_name.map(|name| name == lake_source.name).unwrap_or(lake_source.enabled) {
+            println!("The lake {} is updated to the version {}.", lake_source.name.bold(), lake_source.hash.bold());
+        }
+    }
+}
+
+// Restore the previous version of a lake.
+fn rollback_lake(in_name: Option<&str>) {
+    let config = lake::Lake::rollback(in_name).unwrap_or_else(|err| exit_with(err));
+    // Without a name there is a single lake.
+    if let Some(lake_source) = config.lakes.iter().find(|lake_source| in_name.map(|name| name == lake_source.name).unwrap_or(true)) {
+        println!("The lake {} is rolled back to the version {}.", lake_source.name.bold(), lake_source.hash.bold());
     }
+}
+
+// Pin a lake to a commit or a tag.
+fn pin_lake(in_reference: &str, in_name: Option<&str>) {
+    let config = lake::Lake::pin(in_reference, in_name).unwrap_or_else(|err| exit_with(err));
+    // Without a name there is a single lake.
+    if let Some(lake_source) = config.lakes.iter().find(|lake_source| in_name.map(|name| name == lake_source.name).unwrap_or(true)) {
+        println!("The lake {} is pinned to the commit {}.", lake_source.name.bold(), lake_source.hash.bold());
+    }
+}
 
-    // Is search tags set?
-    if let Some(search_names) = matches.values_of("search-tags") {
-        let mut in_search_tags_vec: Vec<String> = 
-            search_names
-                .map(|search_name| search_name
-                    .to_string()
-                )
-                .collect();        
-        
-        in_search_tags_vec
-            .push(
-                search
-                    .tags_get()
-                    .iter()
-                    .map(|tag| tag
-                        .to_string())
-                    .collect());
-        search.tags_set(&in_search_tags_vec);
-    }
-
-    // Is search description set?
-    if let Some(search_names) = matches.values_of("search-description") {
-        search.description_set(
-            &(
-                search
-                    .description_get()
-                        .to_owned()
-                + " "
-                + &search_names
-                    .collect::<Vec<_>>()
-                    .join(" ")
-            )
-        );
+// Remove the pin of a lake, it follows its branch again.
+fn unpin_lake(in_name: Option<&str>) {
+    let config = lake::Lake::unpin(in_name).unwrap_or_else(|err| exit_with(err));
+    if let Some(lake_source) = config.lakes.iter().find(|lake_source| in_name.map(|name| name == lake_source.name).unwrap_or(true)) {
+        println!("The lake {} is unpinned and updated to the version {}.", lake_source.name.bold(), lake_source.hash.bold());
     }
+}
 
-    // Is search reference set?
-    if let Some(search_names) = matches.values_of("search-references") {
-        let mut in_search_references_vec: Vec<String> = 
-            search_names
-                .map(|search_name| search_name
-                    .to_string())
-                .collect();        
-        in_search_references_vec
-            .push(
-                search
-                    .reference_get()
-                    .iter()
-                    .map(|refe| refe
-                        .to_string())
-                    .collect());
-        search.reference_set(&in_search_references_vec);
-    }
-
-    // Is min-stars set for github search?
-    // Set it anyway because it will be ingnored, if the github search is not active.
-    if let Some(min_stars_str) = matches.value_of("min-stars") {
-        if let Ok(min_stars) = min_stars_str.parse::<isize>() {
-            if min_stars > 100 {
-                search.min_stars_set(min_stars);
-            } else {
-                search.min_stars_set(100);
-            }
+// Download a lake from a new url, the url is saved in the config.yaml.
+fn use_lake(in_url: &str, in_name: Option<&str>) {
+    match lake::Lake::use_url(in_url, in_name) {
+        Ok(_) => println!("The lake of {} is used now.", in_url.bold()),
+        Err(err) => exit_with(err),
+    }
+}
+
+// Add a new lake and download it, the lake can be an url or a local dir or zip file.
+fn add_lake(in_location: &str, in_name: Option<&str>, in_priority: i32, in_backend: Option<&str>, in_public_key: Option<String>) {
+    let backend: Option<lake::backend::Backend> = in_backend.and_then(lake::backend::Backend::from_name);
+    let mut lake_source = config::LakeSource::from_location(in_location, in_name, in_priority, backend)
+        .unwrap_or_else(|err| exit_with(err));
+    lake_source.public_key = in_public_key;
+    match lake::Lake::add(lake_source.clone()) {
+        Ok(_) => println!("The lake {} of {} is added.", lake_source.name.bold(), lake_source.url.bold()),
+        Err(err) => exit_with(err),
+    }
+}
+
+// Check the downloaded lakes against their manifests and print every problem.
+// The exit code is the one of a corrupt lake, if a single lake has a problem.
+fn verify_lakes(in_name: Option<&str>) {
+    let config = config::Config::new().unwrap_or_else(|err| exit_with(err));
+    let lake_sources: Vec<&config::LakeSource> = match in_name {
+        Some(name) => vec![config.find_lake(name).unwrap_or_else(|err| exit_with(err))],
+        None => config.enabled_lakes(),
+    };
+
+    let mut corrupt: bool = false;
+    for lake_source in lake_sources {
+        if let Ok(config::LakeKind::LocalDir(_)) = lake_source.kind() {
+            println!("{} {}", lake_source.name.bold(), "is a local dir, its files are read directly.".truecolor(150, 150, 150));
+            continue;
+        }
+        let problems: Vec<String> = lake::Lake::verify(lake_source).unwrap_or_else(|err| exit_with(err));
+        if problems.is_empty() {
+            let signed = if lake_source.public_key.is_some() { ", the signature is valid" } else { "" };
+            println!("{} {}", lake_source.name.bold(), format!("is unchanged{}.", signed).green());
         } else {
-            search.min_stars_set(100);
-            println!("Failed to pares the min stars value. Please enter a vialed unsigned positive number.");
-            println!("Using the default of main 100 stars to search on github.");
+            corrupt = true;
+            println!("{} {}", lake_source.name.bold(), "is corrupt:".red());
+            for problem in problems {
+                println!("  {}", problem);
+            }
         }
     }
+    if corrupt {
+        std::process::exit(WamiError::LakeCorrupt(String::new()).exit_code());
+    }
+}
 
-    // Is max-stars set for github search?
-    // If not, set it to zero or if it is zero,  
-    // set it to unlimited.
-    if let Some(max_stars_str) = matches.value_of("max-stars") {
-        if let Ok(max_stars) = max_stars_str.parse::<isize>() {
-            if  max_stars <= search.min_stars_get().to_owned() {
-                search.max_stars_set(0);
-            } else {
-                search.max_stars_set(max_stars);
-            }
+// Remove a lake and its templates.
+fn remove_lake(in_name: &str) {
+    match lake::Lake::remove(in_name) {
+        Ok(_) => println!("The lake {} is removed.", in_name.bold()),
+        Err(err) => exit_with(err),
+    }
+}
+
+// Print the url, the version and the number of templates of every lake.
+// The lakes are not downloaded, if they are missing.
+// Every template is parsed, so a broken template of a lake is found, even if the cache is fine.
+fn print_lake_status(in_offline: bool) {
+    let config = config::Config::new().unwrap_or_else(|err| exit_with(err));
+
+    let mut broken: Vec<WamiError> = Vec::new();
+    for (index, lake_source) in config.lakes.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        let lake_dir = lake_source.get_lake_dir().unwrap_or_else(|err| exit_with(err));
+        println!("{} {}", "Lake:".bold(), lake_source.name.bold().green());
+        println!("{} {}", "Url:".bold(), lake_source.url);
+        if let Ok(kind) = lake_source.kind() {
+            println!("{} {}", "Backend:".bold(), kind.name());
+        }
+        println!("{} {}", "Version:".bold(), lake_source.hash);
+        if let Some(pin) = &lake_source.pin {
+            println!("{} {}", "Pinned:".bold(), pin);
+        }
+        println!("{} {}", "Priority:".bold(), lake_source.priority);
+        println!("{} {}", "Enabled:".bold(), lake_source.enabled);
+        println!("{} {}", "Directory:".bold(), lake_dir.display());
+        println!("{} {}", "Old versions:".bold(), lake_source.get_versions().len());
+        if config::Config::is_dir_present(lake_dir.clone()) {
+            let (templates, broken_templates) =
+                lake::Lake::load_templates_from_dir(&lake_dir).unwrap_or_else(|err| exit_with(err));
+            println!("{} {}", "Templates:".bold(), templates.len());
+            broken.extend(broken_templates);
         } else {
-            // Prasing faild
-            search.max_stars_set(0);
-            println!("Failed to parse the max stars value please enter a valid number.");
-            println!("Using the default value 0, there will be no max value");
+            let message = format!("not downloaded, run wami lake update {}", lake_source.name);
+            println!("{} {}", "Templates:".bold(), message.yellow());
+        }
 
+        if !in_offline && lake_source.pin.is_some() {
+            // A pinned lake is never outdated, the status tells how far its branch moved on.
+            let status = match lake::Lake::get_upstream(lake_source) {
+                Ok((head, distance)) => {
+                    println!("{} {}", "Upstream:".bold(), head);
+                    match distance {
+                        0 => "pinned, the branch has no newer commits".green(),
+                        1 => "pinned, the branch is 1 commit ahead".yellow(),
+                        _ => format!("pinned, the branch is {} commits ahead", distance).yellow(),
+                    }
+                }
+                Err(_) => "pinned, the branch of the lake can not be compared".yellow(),
+            };
+            println!("{} {}", "Status:".bold(), status);
+        } else if !in_offline {
+            let status = match lake::Lake::is_outdated(lake_source) {
+                Ok(false) => "up to date".green(),
+                Ok(true) => format!("outdated, run wami lake update {}", lake_source.name).red(),
+                Err(_) => "the version of the lake can not be downloaded".yellow(),
+            };
+            println!("{} {}", "Status:".bold(), status);
         }
     }
 
-    // Is the github fork search option set to true?
-    // If it is not set, the standard initialization of the search struct is github-fork = false.
-    // This will search the forks of an project on github.
-    if matches.is_present("github-fork") {
-        search.github_fork_set(true);
+    // The first broken template is the exit code, all of them are listed.
+    print_warnings(&broken);
+    if let Some(error) = broken.into_iter().next() {
+        std::process::exit(error.exit_code());
+    }
+}
+
+// List the id and the title of every template of the lakes.
+// With several lakes the lake of every template is listed as well.
+fn list_lake() {
+    let lake = open_lake();
+    let show_lake: bool = lake.get_config().enabled_lakes().len() > 1;
+    let mut templates: Vec<&wami::Template> = lake.get_templates().iter().collect();
+    templates.sort_by(|a, b| a.id().cmp(b.id()));
+    for template in templates {
+        if show_lake {
+            println!("{} - {} {}", template.id().bold(), template.title(), format!("[{}]", template.lake()).truecolor(150, 150, 150));
+        } else {
+            println!("{} - {}", template.id().bold(), template.title());
+        }
     }
-    
-    if let Some(search_topics) = matches.values_of("list-topics") {
-        let out_search_topics_vec: Vec<String> =
-            search_topics
-                .map(|search_topic| search_topic
-                    .to_string())
-                .collect();
-        let _ = github_topic::get_github_topics(out_search_topics_vec);
-        std::process::exit(0); 
+}
+
+// Print the whole config.yaml or the value of a single key.
+fn print_config_value(in_key: Option<&str>) {
+    let config = config::Config::new().unwrap_or_else(|err| exit_with(err));
+
+    match in_key {
+        None => match serde_yaml::to_string(&config) {
+            Ok(yaml) => println!("{}", yaml.trim_start_matches("---\n").trim_end()),
+            Err(err) => exit_with(WamiError::Config(format!("Failed to print the config.yaml: {}", err))),
+        },
+        Some(key) => match config.get_value(key) {
+            Ok(value) => println!("{}", value),
+            Err(err) => exit_with(err),
+        },
     }
+}
 
-    // let mut update flag
-    let mut update = false;
-
-    // If the update flag is set, set update.
-    if let Some(_search_name) = matches.values_of("update") {
-        update = true;
+// Set the value of a key in the config.yaml.
+fn set_config_value(in_key: &str, in_value: &str) {
+    let mut config = config::Config::new().unwrap_or_else(|err| exit_with(err));
+    if let Err(err) = config.set_value(in_key, in_value).and_then(|_| config.save()) {
+        exit_with(err);
     }
-
-    // offline flag is set default to false so there will be an check if the lake is up to date
-    let mut offline: bool = false;
-
-    // if the offline flag is set, set the flag to true
-    if let Some(_search_name) = matches.values_of("offline") {
-        offline = true;
+}
+
+// A template for every hit replaces the output format.
+fn read_output_template(in_args: &OutputArgs) -> Option<output::OutputTemplate> {
+    if let Some(template) = &in_args.template {
+        Some(output::OutputTemplate::parse_argument(template).unwrap_or_else(|err| exit_with(err)))
+    } else if let Some(template_file) = &in_args.template_file {
+        match std::fs::read_to_string(template_file) {
+            // The line break at the end of the file is added for every hit anyway.
+            Ok(template) => Some(
+                output::OutputTemplate::parse(template.strip_suffix('\n').unwrap_or(&template))
+                    .unwrap_or_else(|err| exit_with(err)),
+            ),
+            Err(err) => exit_with(WamiError::InvalidInput(format!("Failed to read the template file: {}", err))),
+        }
+    } else {
+        None
     }
+}
+
+// Print the hits in a machine readable format.
+// The hits are always listed from the best to the worst, the rank is part of every hit.
+fn print_hits(in_hits: &[output::Hit], in_format: output::Format) {
+    match output::render(in_hits, in_format) {
+        Ok(out_string) => println!("{}", out_string.trim_end()),
+        Err(err) => exit_with(err),
+    }
+}
+
+// Open the lake for the subcommands, the lake is downloaded if it is missing.
+// The broken files of the lake are skipped, the user is warned about them.
+fn open_lake() -> lake::Lake {
+    let lake = lake::Lake::open().unwrap_or_else(|err| exit_with(err));
+    print_warnings(lake.get_warnings());
+    lake
+}
+
+// Print the errors, which did not stop wami, to stderr.
+fn print_warnings(in_warnings: &[WamiError]) {
+    for warning in in_warnings {
+        eprintln!("{} {}", "Warning:".yellow(), warning);
+    }
+}
+
+// Print every field of a single template of the lake.
+// If there is no template with the id, similar ids are suggested and wami exits with an error.
+fn show_template(in_id: &str) {
+    let lake = open_lake();
+    match lake.find_template(in_id) {
+        Ok(template) => println!("{}", template.to_detail_string(lake.get_templates())),
+        Err(err) => exit_with(err),
+    }
+}
+
+// Print the alternatives of a template, ranked by the search words.
+fn show_alternatives(in_id: &str, in_depth: usize, in_words: &str) {
+    let lake = open_lake();
+    let mut builder = search::Search::builder().weights(lake.get_config().weights.clone());
+    // Without words the alternatives are listed by their depth.
+    if !in_words.trim().is_empty() {
+        builder = builder.query(in_words);
+    }
+    let search = builder.build().unwrap_or_else(|err| exit_with(err));
 
-    let mut url: String = "".to_string();
-
-    // Is the url set?
-    if let Some(in_url) = matches
-        .values_of("url")
-        .and_then(|mut values| values.next())
-    {
-        url = in_url.to_string();
+    report_dangling(&lake.graph());
+    match lake.alternatives(in_id, in_depth, search) {
+        Ok(alternatives) if alternatives.is_empty() => println!("{} has no alternatives in the lake.", in_id.bold()),
+        Ok(alternatives) => println!("{}", lake::Lake::alternatives_to_string(&alternatives)),
+        Err(err) => exit_with(err),
     }
+}
 
-    // In sort the default value for the sort value is asc
-    let mut sort_value = "desc".to_string();
+// Export the alternatives graph of the whole lake as dot or mermaid.
+fn export_graph(in_format: &str) {
+    let lake = open_lake();
+    let graph = lake.graph();
+    report_dangling(&graph);
 
-    if let Some(sort) = matches
-        .value_of("sort")
-    {
-        sort_value = sort.to_string();
+    match in_format {
+        "mermaid" => println!("{}", graph.to_mermaid(lake.get_templates())),
+        _ => println!("{}", graph.to_dot(lake.get_templates())),
     }
-    
-    // The default value of max item to list is 10.
-    let mut max_list: usize = 10;
+}
+
+// Warn about why_not entries, which are no ids of the lake.
+// The warnings are printed to stderr, so an exported graph can be piped into a file.
+fn report_dangling(in_graph: &graph::Graph) {
+    for dangling in in_graph.dangling() {
+        eprintln!(
+            "{} {} names the unknown alternative {}",
+            "Warning:".yellow(),
+            dangling.id,
+            dangling.why_not
+        );
+    }
+}
 
-    // Is the max value set?
-    if let Some(search_names) = matches.value_of("max") {
-        if let Ok(max) = search_names.parse::<usize>() {
-            max_list = max;
-        } else {
-            // Praseing failed
-            println!("Failed to parse the max value please enter a vailid not negative number.");
-            println!("Using the default of 10 Items to list.");
-        }
+// Browse the lake interactively and print the selection of the user.
+fn browse_lake(in_search: search::Search, in_input: &str) {
+    let (templates, index, lake_synonyms) = open_lake().into_parts();
+    let synonyms = if *in_search.use_synonyms_get() {
+        lake_synonyms
+    } else {
+        search::synonyms::Synonyms::default()
+    };
+
+    let browser = browse::Browser::new(templates, index, in_search.weights_get().clone(), synonyms, in_input);
+    match browser.run() {
+        Ok(Some(browse::Selection::Reference(reference))) => println!("{}", reference),
+        Ok(Some(browse::Selection::Id(id))) => println!("{}", id),
+        Ok(None) => {}
+        Err(err) => exit_with(WamiError::Io(io::Error::new(err.kind(), format!("Failed to run the browser: {}", err)))),
     }
+}
 
-    // Is the why_not value set?
-    let mut why_not_option: bool = false;
-    if let Some(_search_name) = matches.values_of("why_not") {
-        why_not_option = true;
-    }
-
-    // Set the default value for the GitHub search.
-    let mut github: bool = false;
-
-    // Check if the GitHub search value is set to true.
-    if let Some(_search_name) = matches.values_of("github") {
-        github = true;
-    }   
-
-    // If github is not set
-    // Create the lake an instance of the lake
-    // We have the url if it has changed
-    // We have the update boolean
-    // And we have all the search parameters
-    if !github {
-        let lake_result = 
-            lake::Lake::new(
-                url, 
-                update, 
-                search
-            );
-        match lake_result {
-            Ok(mut lake) => {
-                // Now you have a valid Lake instance in the lake variable.
-                if matches.is_present("show-all") {
-                    lake.print_top_hits(max_list, sort_value, why_not_option);
-                } else {
-                    lake.print_top_short_list(max_list, sort_value, why_not_option);
-                }
+// Render every hit with the output template of the user, one hit per line.
+fn print_hits_with_template(in_hits: &[output::Hit], in_template: &output::OutputTemplate) {
+    for hit in in_hits {
+        println!("{}", in_template.render(hit));
+    }
+}
 
-                if !update && !offline {
-                    match config::Config::get_git_hash(&lake.get_config_url()) {
-                        Ok(hash) => {
-                            if hash != lake.get_config_hash() {
-                                let message = format!("{}", "Please update the lake, it is outdated.".bold().red());
-                                println!("{}", message);
-                            }
-                        },
-                        Err(_err) => println!("Version of lake can not be downloaded.")
-                    }
-                }
-            }
-            Err(e) => {
-                println!("Failed to create the Lake: {}", e);
-                std::process::exit(1);
-            }
-        }
+// Notices for the user are printed to stderr with a machine readable format or an output template,
+// so they do not break the output for scripts.
+fn print_notice(in_message: &str, in_format: output::Format, in_template: bool) {
+    if in_format == output::Format::Text && !in_template {
+        println!("{}", in_message);
     } else {
-        let github_result = 
-            github_search::GithubSearch::new(
-                search
-            );
-        match github_result {
-            Ok(search_result) => {
-                if matches.is_present("show-all"){
-                    let result_string = search_result.to_string(max_list, &sort_value, true);
-                    println!("{}", result_string);
-                } else {
-                    let result_string = search_result.to_string(max_list, &sort_value, false);
-                    println!("{}", result_string);
-                }
-            }
-            Err(err) => {
-                println!("The response form GitHub is empty");
-                println!("Error: {:?}", err);
-            }
-        }
+        eprintln!("{}", in_message);
     }
-}
\ No newline at end of file
+}
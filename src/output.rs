@@ -0,0 +1,375 @@
+use crate::{error::{Result, WamiError}, template::Template};
+use serde::Serialize;
+use std::io;
+
+// This is the machine readable output of the search results.
+// The text output is colored for the console, the other formats are meant for scripts.
+// Every format is built from the same list of hits, no matter if they come from the lake or GitHub.
+
+// The output formats of the --format option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+    Yaml,
+    Csv,
+    Ndjson,
+}
+
+impl Format {
+    pub const NAMES: [&'static str; 5] = ["text", "json", "yaml", "csv", "ndjson"];
+
+    // Find the format by its name.
+    pub fn from_name(in_name: &str) -> Option<Format> {
+        match in_name.trim().to_lowercase().as_str() {
+            "text" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            "yaml" => Some(Format::Yaml),
+            "csv" => Some(Format::Csv),
+            "ndjson" => Some(Format::Ndjson),
+            _ => None,
+        }
+    }
+}
+
+// A single search result.
+// The GitHub fields are only set for hits of the GitHub search.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Hit {
+    pub rank: usize,
+    pub score: f32,
+    pub id: String,
+    pub title: String,
+    pub tags: Vec<String>,
+    pub description: String,
+    pub references: Vec<String>,
+    pub why_not: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lake: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stars: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topics: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+}
+
+impl Hit {
+    // Create a hit from a template of the lake.
+    pub fn from_template(in_rank: usize, in_template: &Template) -> Hit {
+        Hit {
+            rank: in_rank,
+            score: in_template.distance(),
+            id: in_template.id().to_owned(),
+            title: in_template.title().to_owned(),
+            tags: in_template.tags().to_vec(),
+            description: in_template.description().to_owned(),
+            references: in_template.references().to_vec(),
+            why_not: in_template.why_not().to_vec(),
+            lake: Some(in_template.lake().to_owned()).filter(|lake| !lake.is_empty()),
+            stars: None,
+            topics: None,
+            updated_at: None,
+        }
+    }
+}
+
+// The columns of the csv output, lists are joined with a semicolon.
+const CSV_HEADER: &str = "rank,score,id,title,tags,description,references,why_not,lake,stars,topics,updated_at";
+
+// Render the hits in a machine readable format.
+// The text format is printed by the lake and the GitHub search themselves, so it is not handled here.
+pub fn render(in_hits: &[Hit], in_format: Format) -> Result<String> {
+    let render_error = |in_err: &dyn std::fmt::Display| WamiError::Io(io::Error::other(in_err.to_string()));
+
+    match in_format {
+        Format::Json => serde_json::to_string_pretty(in_hits).map_err(|err| render_error(&err)),
+        Format::Yaml => serde_yaml::to_string(in_hits).map_err(|err| render_error(&err)),
+        Format::Ndjson => {
+            let mut out_string = String::new();
+            for hit in in_hits {
+                out_string.push_str(&serde_json::to_string(hit).map_err(|err| render_error(&err))?);
+                out_string.push('\n');
+            }
+            Ok(out_string)
+        }
+        Format::Csv => {
+            let mut out_string = String::from(CSV_HEADER);
+            out_string.push('\n');
+            for hit in in_hits {
+                let row: Vec<String> = vec![
+                    hit.rank.to_string(),
+                    hit.score.to_string(),
+                    csv_field(&hit.id),
+                    csv_field(&hit.title),
+                    csv_field(&hit.tags.join(";")),
+                    csv_field(&hit.description),
+                    csv_field(&hit.references.join(";")),
+                    csv_field(&hit.why_not.join(";")),
+                    csv_field(hit.lake.as_deref().unwrap_or("")),
+                    hit.stars.map(|stars| stars.to_string()).unwrap_or_default(),
+                    csv_field(&hit.topics.as_ref().map(|topics| topics.join(";")).unwrap_or_default()),
+                    csv_field(hit.updated_at.as_deref().unwrap_or("")),
+                ];
+                out_string.push_str(&row.join(","));
+                out_string.push('\n');
+            }
+            Ok(out_string)
+        }
+        Format::Text => Err(WamiError::InvalidInput(
+            "The text format is printed by the lake and the GitHub search.".to_string(),
+        )),
+    }
+}
+
+// Quote a csv field, if it contains a separator, a quote or a line break.
+fn csv_field(in_value: &str) -> String {
+    if in_value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", in_value.replace('"', "\"\""))
+    } else {
+        in_value.to_owned()
+    }
+}
+
+// The fields of a hit, which can be used in an output template.
+const TEMPLATE_FIELDS: [&str; 12] = [
+    "rank", "score", "id", "title", "tags", "description", "references", "why_not", "lake", "stars", "topics",
+    "updated_at",
+];
+
+// A part of an output template, either plain text or a field of the hit.
+#[derive(Debug, Clone, PartialEq)]
+enum TemplatePart {
+    Text(String),
+    Field { name: String, index: Option<usize> },
+}
+
+// A user defined output template for a single hit, e.g. '{{rank}} {{id}} {{references[0]}}'.
+// A field in double braces is replaced by the value of the hit.
+// A list field like tags is joined with ", ", with an index only this entry is used.
+// An entry which does not exist, like a missing reference or the stars of a lake hit, is empty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputTemplate {
+    parts: Vec<TemplatePart>,
+}
+
+impl OutputTemplate {
+    // Parse an output template, unknown fields and open braces are an error.
+    pub fn parse(in_template: &str) -> Result<OutputTemplate> {
+        OutputTemplate::parse_parts(in_template)
+            .map(|parts| OutputTemplate { parts })
+            .map_err(|err| WamiError::InvalidInput(format!("Failed to parse the output template: {}", err)))
+    }
+
+    fn parse_parts(in_template: &str) -> std::result::Result<Vec<TemplatePart>, String> {
+        let mut parts: Vec<TemplatePart> = Vec::new();
+        let mut rest: &str = in_template;
+
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                parts.push(TemplatePart::Text(rest[..start].to_owned()));
+            }
+            let end = rest[start..]
+                .find("}}")
+                .ok_or(format!("Missing }}}} after '{}'.", &rest[start..]))?
+                + start;
+            parts.push(OutputTemplate::parse_field(&rest[start + 2..end])?);
+            rest = &rest[end + 2..];
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Text(rest.to_owned()));
+        }
+
+        Ok(parts)
+    }
+
+    // Parse a field like "id" or "references[0]".
+    fn parse_field(in_field: &str) -> std::result::Result<TemplatePart, String> {
+        let field = in_field.trim();
+        let (name, index) = match field.strip_suffix(']').and_then(|field| field.split_once('[')) {
+            Some((name, index)) => {
+                let index = index
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid index in '{{{{{}}}}}'.", field))?;
+                (name.trim(), Some(index))
+            }
+            None => (field, None),
+        };
+
+        if !TEMPLATE_FIELDS.contains(&name) {
+            return Err(format!(
+                "Unknown field '{}', expected one of {}.",
+                name,
+                TEMPLATE_FIELDS.join(", ")
+            ));
+        }
+        Ok(TemplatePart::Field { name: name.to_owned(), index })
+    }
+
+    // Parse a template from the command line.
+    // A shell makes it hard to type a line break, so \n and \t are replaced as well.
+    pub fn parse_argument(in_template: &str) -> Result<OutputTemplate> {
+        OutputTemplate::parse(
+            &in_template
+                .replace("\\n", "\n")
+                .replace("\\t", "\t"),
+        )
+    }
+
+    // Render a hit with the template.
+    pub fn render(&self, in_hit: &Hit) -> String {
+        let mut out_string = String::new();
+        for part in &self.parts {
+            match part {
+                TemplatePart::Text(text) => out_string.push_str(text),
+                TemplatePart::Field { name, index } => {
+                    let values = OutputTemplate::field_values(in_hit, name);
+                    match index {
+                        Some(index) => out_string.push_str(values.get(*index).map(|value| value.as_str()).unwrap_or("")),
+                        None => out_string.push_str(&values.join(", ")),
+                    }
+                }
+            }
+        }
+        out_string
+    }
+
+    // The values of a field, a single value is a list with one entry.
+    fn field_values(in_hit: &Hit, in_name: &str) -> Vec<String> {
+        match in_name {
+            "rank" => vec![in_hit.rank.to_string()],
+            "score" => vec![in_hit.score.to_string()],
+            "id" => vec![in_hit.id.to_owned()],
+            "title" => vec![in_hit.title.to_owned()],
+            "tags" => in_hit.tags.to_owned(),
+            "description" => vec![in_hit.description.to_owned()],
+            "references" => in_hit.references.to_owned(),
+            "why_not" => in_hit.why_not.to_owned(),
+            "lake" => in_hit.lake.iter().cloned().collect(),
+            "stars" => in_hit.stars.iter().map(|stars| stars.to_string()).collect(),
+            "topics" => in_hit.topics.to_owned().unwrap_or_default(),
+            "updated_at" => in_hit.updated_at.iter().cloned().collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit() -> Hit {
+        Hit::from_template(
+            1,
+            &Template::new(
+                "dig".to_string(),
+                "dig - DNS lookup utility".to_string(),
+                vec!["dns".to_string(), "lookup".to_string()],
+                "Query DNS servers, e.g. \"dig axfr\".".to_string(),
+                vec!["https://manned.org/dig".to_string()],
+                vec!["host".to_string()],
+            ),
+        )
+    }
+
+    #[test]
+    fn test_format_from_name() {
+        assert_eq!(Format::from_name("JSON"), Some(Format::Json));
+        assert_eq!(Format::from_name("ndjson"), Some(Format::Ndjson));
+        assert_eq!(Format::from_name("xml"), None);
+        assert!(Format::NAMES.iter().all(|name| Format::from_name(name).is_some()));
+    }
+
+    #[test]
+    fn test_render_json() {
+        let json = render(&[hit()], Format::Json).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value[0]["rank"], 1);
+        assert_eq!(value[0]["id"], "dig");
+        assert_eq!(value[0]["tags"][1], "lookup");
+        assert_eq!(value[0]["why_not"][0], "host");
+        // The GitHub fields are not part of a lake hit.
+        assert!(value[0].get("stars").is_none());
+    }
+
+    #[test]
+    fn test_render_ndjson_one_hit_per_line() {
+        let mut second = hit();
+        second.rank = 2;
+        let ndjson = render(&[hit(), second], Format::Ndjson).unwrap();
+
+        let lines: Vec<&str> = ndjson.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let value: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(value["rank"], 2);
+    }
+
+    #[test]
+    fn test_render_yaml() {
+        let yaml = render(&[hit()], Format::Yaml).unwrap();
+        let value: serde_yaml::Value = serde_yaml::from_str(&yaml).unwrap();
+
+        assert_eq!(value[0]["id"].as_str(), Some("dig"));
+        assert_eq!(value[0]["references"][0].as_str(), Some("https://manned.org/dig"));
+    }
+
+    #[test]
+    fn test_render_csv_quotes_fields() {
+        let csv = render(&[hit()], Format::Csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(
+            lines[1],
+            "1,0,dig,dig - DNS lookup utility,dns;lookup,\"Query DNS servers, e.g. \"\"dig axfr\"\".\",https://manned.org/dig,host,,,,"
+        );
+    }
+
+    #[test]
+    fn test_render_lake_of_the_hit() {
+        let mut hit = hit();
+        hit.lake = Some("internal".to_string());
+        let value: serde_json::Value = serde_json::from_str(&render(&[hit.clone()], Format::Json).unwrap()).unwrap();
+
+        assert_eq!(value[0]["lake"], "internal");
+        assert_eq!(OutputTemplate::parse("{{id}} {{lake}}").unwrap().render(&hit), "dig internal");
+    }
+
+    #[test]
+    fn test_render_empty() {
+        assert_eq!(render(&[], Format::Json).unwrap(), "[]");
+        assert_eq!(render(&[], Format::Ndjson).unwrap(), "");
+        assert_eq!(render(&[], Format::Csv).unwrap(), format!("{}\n", CSV_HEADER));
+    }
+
+    #[test]
+    fn test_output_template_render() {
+        let template = OutputTemplate::parse("{{rank}} {{id}} {{references[0]}}").unwrap();
+
+        assert_eq!(template.render(&hit()), "1 dig https://manned.org/dig");
+    }
+
+    #[test]
+    fn test_output_template_lists_and_missing_values() {
+        let template = OutputTemplate::parse("| {{ title }} | {{tags}} | {{references[3]}} | {{stars}} |").unwrap();
+
+        assert_eq!(template.render(&hit()), "| dig - DNS lookup utility | dns, lookup |  |  |");
+    }
+
+    #[test]
+    fn test_output_template_argument_escapes() {
+        let template = OutputTemplate::parse_argument("{{id}}\\n\\t{{tags[1]}}").unwrap();
+
+        assert_eq!(template.render(&hit()), "dig\n\tlookup");
+    }
+
+    #[test]
+    fn test_output_template_errors() {
+        assert!(OutputTemplate::parse("{{name}}").unwrap_err().to_string().contains("Unknown field 'name'"));
+        assert!(OutputTemplate::parse("{{id").is_err());
+        assert!(OutputTemplate::parse("{{tags[first]}}").is_err());
+        assert_eq!(OutputTemplate::parse("no fields").unwrap().render(&hit()), "no fields");
+    }
+}
@@ -1,6 +1,16 @@
+pub mod builder;
+pub mod query;
+pub mod synonyms;
+
+use crate::index::Field;
 use crate::template::Template;
+use query::Query;
+use synonyms::Synonyms;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-// use levenshtein::levenshtein;
+use lazy_static::lazy_static;
+use levenshtein::levenshtein;
+use rust_stemmers::{Algorithm, Stemmer};
 
 // This will be the framework for the search algorithmic.
 
@@ -10,7 +20,105 @@ use std::collections::HashMap;
 // An value close to 0.0 will be no match.
 // This is almost imposable, because some letters will match at some point.
 
+// Common English words, which are dropped from the search and the index.
+// The words of the query language like AND, OR and NOT are parsed before, so they are not affected.
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "has", "in", "is", "it", "its",
+    "of", "on", "or", "that", "the", "this", "to", "was", "were", "which", "will", "with",
+];
+
+lazy_static! {
+    // The stemmer is created once and shared by all threads.
+    static ref STEMMER: Stemmer = Stemmer::create(Algorithm::English);
+}
+
+// The weight of every field for the ranking of the lake.
+// A higher weight lets a match in this field count more,
+// a weight of 0.0 will ignore the field completely.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Weights {
+    pub id: f32,
+    pub title: f32,
+    pub tags: f32,
+    pub description: f32,
+    pub references: f32,
+}
+
+impl Default for Weights {
+    // Every field is equally important by default.
+    fn default() -> Weights {
+        Weights {
+            id: 1.0,
+            title: 1.0,
+            tags: 1.0,
+            description: 1.0,
+            references: 1.0,
+        }
+    }
+}
+
+impl Weights {
+    pub fn get(&self, in_field: Field) -> f32 {
+        match in_field {
+            Field::Id => self.id,
+            Field::Title => self.title,
+            Field::Tags => self.tags,
+            Field::Description => self.description,
+            Field::References => self.references,
+        }
+    }
+
+    pub fn set(&mut self, in_field: Field, in_weight: f32) {
+        match in_field {
+            Field::Id => self.id = in_weight,
+            Field::Title => self.title = in_weight,
+            Field::Tags => self.tags = in_weight,
+            Field::Description => self.description = in_weight,
+            Field::References => self.references = in_weight,
+        }
+    }
+
+    // Override the weights with a list like "title=3,tags=2".
+    // Fields which are not in the list keep their weight.
+    pub fn apply_overrides(&mut self, in_overrides: &str) -> Result<(), String> {
+        for pair in in_overrides.split(',').filter(|pair| !pair.trim().is_empty()) {
+            let (name, value) = pair
+                .split_once('=')
+                .ok_or(format!("Invalid weight '{}', expected FIELD=WEIGHT.", pair))?;
+
+            let field = Field::from_name(name).ok_or(format!(
+                "Unknown field '{}', expected one of id, title, tags, description, references.",
+                name.trim()
+            ))?;
+
+            let weight = value
+                .trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|weight| weight.is_finite() && *weight >= 0.0)
+                .ok_or(format!(
+                    "Invalid weight '{}' for {}, expected a positive number.",
+                    value.trim(),
+                    field.name()
+                ))?;
+
+            self.set(field, weight);
+        }
+        Ok(())
+    }
+}
+
+// A template which was found by its name, even if the name has a typo.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NameMatch {
+    pub id: String,
+    pub exact: bool, // False if the name was only similar, then a "did you mean" is shown.
+}
+
+#[derive(Debug, Clone)]
 pub struct Search {
+    name: String, // The name of a tool, which the user half remembers.
     id: String,
     title: String,
     tags: Vec<String>,
@@ -19,11 +127,17 @@ pub struct Search {
     min_stars: isize,
     max_stars: isize,
     github_fork: bool,
+    weights: Weights,
+    must: Vec<Query>,     // Every template has to match these queries.
+    must_not: Vec<Query>, // No template may match these queries.
+    synonyms: Synonyms,   // The synonyms of the search terms are searched as well.
+    use_synonyms: bool,   // False if the user turned the synonyms off.
 }
 
 impl Search {
     pub fn new_empty() -> Search {
         Search {
+            name: "".to_owned(),
             id: "".to_owned(),
             title: "".to_owned(),
             tags: Vec::new(),
@@ -32,15 +146,24 @@ impl Search {
             min_stars: 100,
             max_stars: -1, // The -1 will set the max value to an open end.
             github_fork: false,
+            weights: Weights::default(),
+            must: Vec::new(),
+            must_not: Vec::new(),
+            synonyms: Synonyms::default(),
+            use_synonyms: true,
         }
     }
     
+    pub fn name_set(&mut self, in_name: &str) {
+        self.name = in_name.trim().to_lowercase();
+    }
+
     pub fn id_get(&self) -> &String {
         &self.id
     }
 
-    pub fn id_set(&mut self, in_id: &String) {
-        self.id = Template::convert_to_lowercase_alphanumeric_with_hyphens(&in_id);
+    pub fn id_set(&mut self, in_id: &str) {
+        self.id = Template::convert_to_lowercase_alphanumeric_with_hyphens(in_id);
     }
 
     pub fn title_get(&self) -> &str {
@@ -99,51 +222,215 @@ impl Search {
         self.github_fork = in_github_fork.to_owned();
     }
 
-    // Function to calculate the average cosine similarity between pairs of strings from two vectors.
-    // This function iterates over every combination of strings between 'words' and 'queries',
-    // computes the cosine similarity for each pair, and then returns the average of these similarities.
-    pub fn cosine_similarities(words: &Vec<String>, queries: &Vec<String>) -> f32 {
-        // Check if either of the vectors is empty and return 1.0 if both are empty.
-        if words.is_empty() && queries.is_empty() {
-            return 1.0;
+    pub fn weights_get(&self) -> &Weights {
+        &self.weights
+    }
+
+    pub fn weights_set(&mut self, in_weights: Weights) {
+        self.weights = in_weights;
+    }
+
+    pub fn synonyms_set(&mut self, in_synonyms: Synonyms) {
+        self.synonyms = in_synonyms;
+    }
+
+    pub fn use_synonyms_get(&self) -> &bool {
+        &self.use_synonyms
+    }
+
+    pub fn use_synonyms_set(&mut self, in_use_synonyms: bool) {
+        self.use_synonyms = in_use_synonyms;
+    }
+
+    // Add a query in the query language to the search.
+    // The required and negated parts become filters,
+    // all other words are added to the fields to rank the templates.
+    // A part with a nested required or negated part, e.g. "dns OR smb -windows", is a filter as a whole.
+    pub fn query_set(&mut self, in_query: &str) -> Result<(), String> {
+        let query: Query = Query::parse(in_query)?;
+
+        let mut ranking_words: Vec<(Option<Field>, String)> = Vec::new();
+        for clause in query.clauses() {
+            match clause {
+                Query::Not(negated) => self.must_not.push(*negated.clone()),
+                Query::Must(required) => {
+                    self.must.push(*required.clone());
+                    required.ranking_words(&mut ranking_words);
+                }
+                _ if clause.has_filter() => {
+                    self.must.push(clause.clone());
+                    clause.ranking_words(&mut ranking_words);
+                }
+                _ => clause.ranking_words(&mut ranking_words),
+            }
         }
 
-        // Check if either of the vectors is empty and return 0.0 in that case.
-        if words.is_empty() || queries.is_empty() {
-            return 0.0;
+        for (field, words) in ranking_words {
+            match field {
+                Some(field) => self.field_add(field, &words),
+                // Words without a field are searched in all fields.
+                None => {
+                    for field in Field::ALL {
+                        self.field_add(field, &words);
+                    }
+                }
+            }
         }
-        
-        // Initialize total similarity and count.
-        let mut dist: f32 = 0.0;
-        let mut count: i32 = 0;
-    
-        // Iterate over each word in the words vector.
-        for v in words {
-            // For each word, iterate over each query in the queries vector.
-            for q in queries {
-                // Add the cosine similarity between the current word and query to the total.
-                dist += Search::cosine_similarity(&v.to_string(), &q.to_string());
-                
-                // Increment the count for each word-query pair.
-                count += 1;
+        Ok(())
+    }
+
+    // Add search words to a field.
+    fn field_add(&mut self, in_field: Field, in_words: &str) {
+        match in_field {
+            Field::Id => {
+                let id = format!("{} {}", self.id, in_words);
+                self.id_set(id.trim());
+            }
+            Field::Title => self.title = format!("{} {}", self.title, in_words).trim().to_string(),
+            Field::Tags => self.tags.push(in_words.to_string()),
+            Field::Description => {
+                self.description = format!("{} {}", self.description, in_words).trim().to_string()
             }
+            Field::References => self.references.push(in_words.to_string()),
         }
-    
-        // If there were comparisons made (count > 0), return the average similarity.
-        // Otherwise, return 0.0 to avoid division by zero.
-        if count > 0 {
-            dist / count as f32
+    }
+
+    // Check if the template passes the required and negated parts of the query.
+    pub fn matches(&self, in_template: &Template) -> bool {
+        self.must.iter().all(|query| query.matches(in_template))
+            && !self.must_not.iter().any(|query| query.matches(in_template))
+    }
+
+    // True if the search only filters the templates, e.g. -q "-tag:windows".
+    // There is nothing to rank, so every template which passes the filters is a hit.
+    pub fn is_filter_only(&self) -> bool {
+        (!self.must.is_empty() || !self.must_not.is_empty())
+            && self.name.is_empty()
+            && Field::ALL.iter().all(|field| self.field_terms(*field).is_empty())
+    }
+
+    // The number of typos which are allowed in a name.
+    // Short names need to be almost exact, otherwise every short id would be similar.
+    pub fn name_threshold(in_name: &str) -> usize {
+        match in_name.chars().count() {
+            0..=2 => 0,
+            3 => 1,
+            4..=7 => 2,
+            _ => 3,
+        }
+    }
+
+    // Find the template whose id or title is the name of the search.
+    // An exact match wins, otherwise the template with the smallest edit distance
+    // within the threshold is used. On a tie the id wins over the title.
+    pub fn find_name(&self, in_templates: &[Template]) -> Option<NameMatch> {
+        if self.name.is_empty() {
+            return None;
+        }
+
+        let threshold = Search::name_threshold(&self.name);
+        let mut best: Option<(usize, usize, &Template)> = None; // (distance, 0 for id or 1 for title, template)
+
+        for template in in_templates {
+            let candidates = [
+                (levenshtein(&self.name, template.id()), 0),
+                (levenshtein(&self.name, &template.title().to_lowercase()), 1),
+            ];
+            for (distance, kind) in candidates {
+                if distance > threshold {
+                    continue;
+                }
+                let better = match best {
+                    Some((best_distance, best_kind, best_template)) => {
+                        (distance, kind, template.id()) < (best_distance, best_kind, best_template.id())
+                    }
+                    None => true,
+                };
+                if better {
+                    best = Some((distance, kind, template));
+                }
+            }
+        }
+
+        best.map(|(distance, _, template)| NameMatch {
+            id: template.id().to_string(),
+            exact: distance == 0,
+        })
+    }
+
+    // Find the ids which are similar to a name, for suggestions if a name does not exist.
+    // An id within the typo threshold or an id which contains the name is similar.
+    // The closest ids come first.
+    pub fn similar_ids(in_name: &str, in_templates: &[Template], in_max: usize) -> Vec<String> {
+        let name: String = in_name.trim().to_lowercase();
+        let threshold = Search::name_threshold(&name).max(1);
+
+        let mut similar: Vec<(usize, &str)> = in_templates
+            .iter()
+            .map(|template| (levenshtein(&name, template.id()), template.id()))
+            .filter(|(distance, id)| *distance <= threshold || (!name.is_empty() && id.contains(name.as_str())))
+            .collect();
+        similar.sort();
+
+        similar
+            .into_iter()
+            .take(in_max)
+            .map(|(_, id)| id.to_string())
+            .collect()
+    }
+
+    // Get the search terms of a field.
+    // The terms are created the same way as the terms of the lake index.
+    pub fn field_terms(&self, in_field: Field) -> Vec<String> {
+        let terms: Vec<String> = match in_field {
+            Field::Id => Search::tokenize(&self.id),
+            Field::Title => Search::tokenize(&self.title),
+            Field::Tags => self.tags.iter().flat_map(|tag| Search::tokenize(tag)).collect(),
+            Field::Description => Search::tokenize(&self.description),
+            Field::References => self
+                .references
+                .iter()
+                .flat_map(|reference| Search::tokenize(reference))
+                .collect(),
+        };
+
+        // The synonyms are added before the scoring, so they are ranked like the words of the user.
+        if self.use_synonyms {
+            self.synonyms.expand(&terms)
         } else {
-            0.0 as f32
+            terms
         }
     }
 
+    // Split a text into the terms of the search and the index.
+    // Everything that is not alphanumeric separates two words, the words are lowercase.
+    // Stopwords like "the" or "and" are dropped, because they describe no tool.
+    // The remaining words are reduced to their stem with the English stemmer,
+    // so "scanning", "scans" and "scan" are the same term.
+    pub fn tokenize(in_text: &str) -> Vec<String> {
+        in_text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(|word| word.to_lowercase())
+            .filter(|word| !STOPWORDS.contains(&word.as_str()))
+            .map(|word| STEMMER.stem(&word).into_owned())
+            .collect()
+    }
+
     // Function to create bigrams from a given string.
     // A bigram is a pair of consecutive characters.
     // This function returns a HashMap where each key is a bigram and its value is its relative frequency in the string.
-    fn create_bigrams(word: &str) -> HashMap<String, f64> {
+    // The index of the lake keeps the bigrams of its words, so they are only created once per word.
+    // A word with a single character has no bigram, so the character itself is used.
+    pub fn create_bigrams(word: &str) -> HashMap<String, f64> {
         let mut ngrams = HashMap::new();
         let chars: Vec<char> = word.chars().collect();
+        if chars.len() < 2 {
+            if let Some(c) = chars.first() {
+                ngrams.insert(c.to_string(), 1.0);
+            }
+            return ngrams;
+        }
         let total = (chars.len() - 1) as f64;
 
         // Iterate through the string to create bigrams.
@@ -156,23 +443,8 @@ impl Search {
         ngrams
     }
     
-    // Function to calculate the cosine similarity between two strings using bigrams.
-    // This version uses the frequencies of bigrams to capture more contextual information compared to individual characters.
-    pub fn cosine_similarity(word: &str, query: &str) -> f32 {
-        // Check if either of the strings is empty and return 1.0 if both are empty.
-        if word.is_empty() && query.is_empty() {
-            return 1.0;
-        }
-
-        // Check if either of the strings is empty and return 0.0 in that case.
-        if word.is_empty() || query.is_empty() {
-            return 0.0;
-        }
-        
-        // Create bigrams for each string.
-        let freq1 = Search::create_bigrams(word);
-        let freq2 = Search::create_bigrams(query);
-    
+    // Calculate the cosine similarity of two bigram frequency vectors created by create_bigrams.
+    pub fn bigram_similarity(freq1: &HashMap<String, f64>, freq2: &HashMap<String, f64>) -> f32 {
         // Calculate the dot product of the two bigram frequency vectors.
         let dot_product: f64 = freq1.iter()
             .filter_map(|(k, v)| freq2.get(k).map(|v2| *v * *v2))
@@ -185,7 +457,7 @@ impl Search {
         // Calculate and return the cosine similarity.
         // If either vector is zero, return 0.0 to avoid division by zero.
         if magnitude1 * magnitude2 == 0.0 {
-            0.0 as f32
+            0.0
         } else {
             (dot_product / (magnitude1 * magnitude2)) as f32
         }
@@ -195,7 +467,6 @@ impl Search {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use lazy_static::lazy_static;
     use regex::Regex;
 
     lazy_static! {
@@ -213,7 +484,8 @@ mod tests {
         assert_eq!(search.references, Vec::<String>::new());
         assert_eq!(search.min_stars, 100);
         assert_eq!(search.max_stars, -1);
-        assert_eq!(search.github_fork, false);
+        assert!(!search.github_fork);
+        assert_eq!(search.weights, Weights::default());
     }
 
     // This test function is designed to verify both setting and getting functionalities of a Search object.
@@ -222,6 +494,7 @@ mod tests {
         // Initialize a new Search object with specific values.
         // Fields `id`, `title`, `tags`, `description`, `references`, `min_stars`, `max_stars`, and `github_fork` are set.
         let mut search = Search{
+            name: "".to_owned(),
             id: "id1".to_owned(),
             title: "title1".to_owned(),
             tags:  vec![String::from("tags1")] ,
@@ -230,6 +503,11 @@ mod tests {
             min_stars: 100,
             max_stars: 1000,
             github_fork: true,
+            weights: Weights::default(),
+            must: Vec::new(),
+            must_not: Vec::new(),
+            synonyms: Synonyms::default(),
+            use_synonyms: true,
         };
 
         // Assert that the initial values of each field are set as expected.
@@ -240,10 +518,10 @@ mod tests {
         assert_eq!(search.references, vec![String::from("references1")]);
         assert_eq!(search.min_stars, 100);
         assert_eq!(search.max_stars, 1000);
-        assert_eq!(search.github_fork, true);
+        assert!(search.github_fork);
         
         // Set new values to each field of the Search object using respective setter methods.
-        search.id_set(&"id2".to_string());
+        search.id_set("id2");
         search.title_set(&"title2".to_string());
         search.tags_set(&vec![String::from("tags2")]);
         search.description_set(&"description2".to_string());
@@ -262,403 +540,310 @@ mod tests {
         // since these methods return references.
         assert_eq!(*search.min_stars_get(), 200 );
         assert_eq!(*search.max_stars_get(), 2000);
-        assert_eq!(*search.github_fork_get(), false);
+        assert!(!*search.github_fork_get());
     }
 
-    #[test]
-    fn test_search_cosine_similarities_full_empty_vectors() {
-        // Define two empty vectors 'word' and 'query' for testing.
-        let word: Vec<String> = Vec::new();
-        let query: Vec<String> = Vec::new();
+    // The similarity of two words, the way the index compares a search term with the terms of the lake.
+    fn bigram_similarity_of(in_word: &str, in_query: &str) -> f32 {
+        Search::bigram_similarity(&Search::create_bigrams(in_word), &Search::create_bigrams(in_query))
+    }
 
-        // Calculate the average cosine similarity between all pairs of strings from the two vectors.
-        let similarity = Search::cosine_similarities(&word, &query);
+    #[test]
+    fn test_search_bigram_similarity_empty_words() {
+        // An empty word has no bigrams, so it is not similar to any word, not even to another empty word.
+        // Otherwise every empty field would count as a perfect hit.
+        assert_eq!(bigram_similarity_of("", ""), 0.0);
+        assert_eq!(bigram_similarity_of("", "test"), 0.0);
+        assert_eq!(bigram_similarity_of("test", ""), 0.0);
+    }
 
-        // This test checks the behavior of the cosine_similarities function when both input vectors are empty.
-        // The expected behavior in this case is that the similarity should be 1.0.
-        // This is because, conceptually, two empty vectors are identical in that they both lack any elements.
-        // Since cosine similarity measures the degree of overlap in features (here represented by strings) between two vectors,
-        // two empty vectors have complete overlap (i.e., neither vector has any elements that the other lacks),
-        // which leads to a similarity score of 1.0, indicating perfect similarity.
+    #[test]
+    fn test_search_bigram_similarity_equal_words() {
+        // The same bigrams with the same frequencies are a perfect match.
+        assert_eq!(bigram_similarity_of("aaa", "aaa"), 1.0);
+        // 'aa' is the only bigram of both words, so the length does not matter.
+        assert_eq!(bigram_similarity_of("aaaa", "aa"), 1.0);
+    }
 
-        // The assertion checks if the calculated average similarity is indeed 1.0 as expected.
-        assert_eq!(similarity, 1.0);
+    #[test]
+    fn test_search_bigram_similarity_different_words() {
+        // No common bigram means no similarity.
+        assert_eq!(bigram_similarity_of("aaa", "bbb"), 0.0);
+        assert_eq!(bigram_similarity_of("aaaa", "bb"), 0.0);
     }
 
     #[test]
-    fn test_search_cosine_similarities_word_empty_vectors() {
-        // Define an empty vector 'word' and a vector 'query' with a single string for testing.
-        let word: Vec<String> = Vec::new();
-        let query: Vec<String> = vec!["This is a sample text.".to_owned()];
-
-        // Calculate the average cosine similarity between all pairs of strings from the two vectors.
-        let similarity = Search::cosine_similarities(&word, &query);
-
-        // This test checks the behavior of the cosine_similarities function when one of the input vectors is empty.
-        // In this scenario, 'word' is an empty vector, while 'query' contains a single string.
-        // The expected behavior in this case is that the similarity should be 0.0.
-        // This is because with an empty 'word' vector, there are no strings to compare against the strings in 'query'.
-        // Consequently, there are no pairs of strings to evaluate for similarity, and the function should correctly return 0.0.
-        // This indicates that there is no similarity when one of the vectors is empty, as there are no data points for comparison.
-
-        // The assertion checks if the calculated average similarity is indeed 0.0 as expected.
-        assert_eq!(similarity, 0.0);
-}
+    fn test_search_bigram_similarity_of_a_typo() {
+        // 'nmap' and 'nmapp' share 3 of the 4 bigrams of 'nmapp', which is above the fuzzy threshold of the index.
+        let similarity = bigram_similarity_of("nmap", "nmapp");
+        assert!((similarity - 3.0 / 12.0_f32.sqrt()).abs() < 1e-6, "{}", similarity);
+        assert!(bigram_similarity_of("nmap", "nmapp") > bigram_similarity_of("nmap", "nmxpp"));
+        // The similarity does not depend on the order of the words.
+        assert_eq!(bigram_similarity_of("nmap", "nmapp"), bigram_similarity_of("nmapp", "nmap"));
+    }
 
     #[test]
-    fn test_search_cosine_similarities_query_empty_vectors() {
-        // Define a vector 'word' with a single string and an empty vector 'query' for testing.
-        let word: Vec<String> = vec!["This is a sample text.".to_owned()];
-        let query: Vec<String> = Vec::new();
+    fn test_search_create_bigrams_frequencies() {
+        // The frequencies are relative to the number of bigrams of the word.
+        let bigrams = Search::create_bigrams("dnsdns");
+        assert_eq!(bigrams.len(), 3);
+        assert_eq!(bigrams.get("dn"), Some(&0.4));
+        assert_eq!(bigrams.get("ns"), Some(&0.4));
+        assert_eq!(bigrams.get("sd"), Some(&0.2));
+    }
 
-        // Calculate the average cosine similarity between all pairs of strings from the two vectors.
-        let similarity = Search::cosine_similarities(&word, &query);
+    #[test]
+    fn test_search_tokenize() {
+        // Everything that is not alphanumeric separates the words, and the words are lowercase.
+        let tokens = Search::tokenize("DNS-Lookup, zone transfer: https://example.com/dig");
 
-        // This test checks the behavior of the cosine_similarities function when one of the input vectors is empty.
-        // In this scenario, 'word' is a vector with a single string, while 'query' is an empty vector.
-        // The expected behavior in this case is that the similarity should be 0.0.
-        // This is because with an empty 'query' vector, there are no strings to compare against the strings in 'value'.
-        // As a result, there are no pairs of strings to evaluate for similarity, and the function should correctly return 0.0.
-        // This indicates that there is no similarity when one of the vectors is empty, as there are no data points for comparison.
+        assert_eq!(
+            tokens,
+            vec!["dns", "lookup", "zone", "transfer", "https", "exampl", "com", "dig"]
+        );
+    }
 
-        // The assertion checks if the calculated average similarity is indeed 0.0 as expected.
-        assert_eq!(similarity, 0.0);
+    #[test]
+    fn test_search_tokenize_stopwords_and_stems() {
+        // The stopwords are dropped and every word is reduced to its stem.
+        assert_eq!(
+            Search::tokenize("Scanning the networks and scans for a scanner"),
+            vec!["scan", "network", "scan", "scanner"]
+        );
+        assert_eq!(Search::tokenize("the and of"), Vec::<String>::new());
     }
 
     #[test]
-    fn test_search_cosine_similarity_full_empty_strings() {
-        // Define two empty strings 'word' and 'query' for testing.
-        let word = "";
-        let query = "";
-
-        // Calculate the cosine similarity between 'word' and 'query'.
-        let similarity = Search::cosine_similarity(word, query);
-
-        // This test checks the behavior of the cosine_similarity function when both input strings are empty.
-        // The expected behavior in this scenario is that the similarity should be 1.0.
-        // This is because, conceptually, two empty strings are identical in that they both lack any characters or features.
-        // Since cosine similarity measures the overlap in features (bigrams) between two strings,
-        // two empty strings have complete overlap (i.e., neither has any features that the other lacks),
-        // which leads to a similarity score of 1.0, indicating perfect similarity.
-
-        // The assertion checks if the calculated similarity is indeed 1.0 as expected.
-        assert_eq!(similarity, 1.0);
-}
+    fn test_search_tokenize_single_character_and_non_ascii() {
+        assert_eq!(Search::tokenize("x 9"), vec!["x", "9"]);
+        assert_eq!(Search::tokenize("Übersicht straße 日本"), vec!["übersicht", "straße", "日本"]);
+    }
 
     #[test]
-    fn test_search_cosine_similarity_empty_word_with_query_strings() {
-        // Define an empty string 'word' and a non-empty string 'query' for testing.
-        let word = "";
-        let query = "test";
+    fn test_search_create_bigrams_short_words() {
+        assert!(Search::create_bigrams("").is_empty());
+        assert_eq!(Search::create_bigrams("x").get("x"), Some(&1.0));
+        assert_eq!(Search::create_bigrams("ü").len(), 1);
+        assert_eq!(bigram_similarity_of("x", "x"), 1.0);
+        assert_eq!(bigram_similarity_of("x", "xy"), 0.0);
+        assert_eq!(Search::create_bigrams("日本語").len(), 2);
+    }
 
-        // Calculate the cosine similarity between 'word' and 'query'.
-        let similarity = Search::cosine_similarity(word, query);
+    #[test]
+    fn test_search_tokenize_empty() {
+        assert!(Search::tokenize("").is_empty());
+        assert!(Search::tokenize(" -- ").is_empty());
+    }
 
-        // This test checks the behavior of the cosine_similarity function when one of the input strings is empty.
-        // In this scenario, the 'word' string is empty, while 'query' is a regular non-empty string.
-        // The expected behavior in this case is that the similarity should be 0.0.
-        // This is because an empty string, having no characters, also has no bigrams, 
-        // and therefore shares no common features with any non-empty string.
-        // Since cosine similarity calculates the degree of overlap in features (bigrams) between two strings,
-        // the lack of any common features means the similarity score should be 0.0, indicating no similarity.
+    #[test]
+    fn test_search_field_terms() {
+        let mut search = Search::new_empty();
+        search.title_set(&"Zone Transfer".to_string());
+        search.tags_set(&vec!["dns".to_string(), "port-scan".to_string()]);
 
-        // The assertion checks if the calculated similarity is indeed 0.0 as expected.
-        assert_eq!(similarity, 0.0);
+        assert_eq!(search.field_terms(Field::Title), vec!["zone", "transfer"]);
+        assert_eq!(search.field_terms(Field::Tags), vec!["dns", "port", "scan"]);
+        assert!(search.field_terms(Field::Description).is_empty());
     }
 
+    #[test]
+    fn test_weights_apply_overrides() {
+        let mut weights = Weights::default();
+        weights.apply_overrides("title=3, Tags=2.5").unwrap();
+
+        assert_eq!(weights.get(Field::Title), 3.0);
+        assert_eq!(weights.get(Field::Tags), 2.5);
+        // The other fields keep their weight.
+        assert_eq!(weights.get(Field::Id), 1.0);
+        assert_eq!(weights.get(Field::Description), 1.0);
+        assert_eq!(weights.get(Field::References), 1.0);
+    }
 
     #[test]
-    fn test_search_cosine_similarity_word_with_empty_query() {
-        // Define a non-empty string 'word' and an empty string 'query' for testing.
-        let word = "test";
-        let query = "";
-    
-        // Calculate the cosine similarity between 'word' and 'query'.
-        let similarity = Search::cosine_similarity(word, query);
-    
-        // This test checks the behavior of the cosine_similarity function when one of the input strings is empty.
-        // The expected behavior in this case is that the similarity should be 0.0.
-        // This is because an empty string has no characters (and hence no bigrams),
-        // so it shares no common features with any other string, including a non-empty string.
-        // As a result, the cosine similarity, which measures the degree of overlap in features (bigrams) between two strings,
-        // should be 0.0, indicating no similarity.
-    
-        // The assertion checks if the calculated similarity is indeed 0.0 as expected.
-        assert_eq!(similarity, 0.0);
+    fn test_weights_apply_overrides_invalid() {
+        let mut weights = Weights::default();
+
+        assert!(weights.apply_overrides("title").is_err());
+        assert!(weights.apply_overrides("name=2").is_err());
+        assert!(weights.apply_overrides("title=high").is_err());
+        assert!(weights.apply_overrides("title=-1").is_err());
+
+        // Nothing was changed by the invalid overrides.
+        assert_eq!(weights, Weights::default());
     }
-    
 
     #[test]
-    fn test_search_cosine_similarities_full_long_vectors_equal() {
-        // Define two vectors of strings for testing.
-        let value = vec![
-            "aaaa".to_owned(),
-            "aaab".to_owned(),
-            "aabc".to_owned(),
-            "abcd".to_owned(),
-        ];
-        let query = vec![
-            "aaaa".to_owned(),
-            "aaab".to_owned(),
-            "aabc".to_owned(),
-            "abcd".to_owned(),
-        ];
-    
-        // Calculate the average cosine similarity between all pairs of strings from the two vectors.
-        let similarity = Search::cosine_similarities(&value, &query);
-    
-        // Define the expected similarity value based on manual calculation or prior knowledge.
-        let expected_similarity = 0.646405;
-        // Define a tolerance range to account for potential minor deviations in floating-point calculations.
-        let tolerance = 0.001;
-    
-        // The assertion checks whether the calculated similarity is within the expected range.
-        // This approach is used because floating-point calculations can have small rounding errors,
-        // so exact equality might not be a reliable condition.
-        // The tolerance range allows for slight numerical variations while still ensuring the accuracy of the calculation.
-        assert!(
-            (similarity - expected_similarity).abs() < tolerance,
-            "The similarity value is not within the expected range"
-        );
+    fn test_weights_deserialize_partial() {
+        // Fields missing in the config.yaml keep the default weight.
+        let weights: Weights = serde_yaml::from_str("title: 3.0\nreferences: 0.5\n").unwrap();
+
+        assert_eq!(weights.title, 3.0);
+        assert_eq!(weights.references, 0.5);
+        assert_eq!(weights.tags, 1.0);
     }
 
     #[test]
-    fn test_search_cosine_similarities_full_long_reversed_vectors() {
-        // Define two vectors of strings for testing, with the second vector being a reverse of the first.
-        let value = vec![
-            "aaaa".to_owned(),
-            "aaab".to_owned(),
-            "aabc".to_owned(),
-            "abcd".to_owned(),
-        ];
-        let query = vec![
-            "abcd".to_owned(), // Reversed order compared to 'value'
-            "aabc".to_owned(),
-            "aaab".to_owned(),
-            "aaaa".to_owned(),
-        ];
-    
-        // Calculate the average cosine similarity between all pairs of strings from the two vectors.
-        let similarity = Search::cosine_similarities(&value, &query);
-    
-        // Define the expected similarity value and a tolerance range for the test.
-        let expected_similarity = 0.646405;
-        let tolerance = 0.001;
-    
-        // The assertion checks whether the calculated similarity is within the tolerance range of the expected value.
-        // This approach is necessary due to potential minor variations in floating-point calculations.
-        assert!(
-            (similarity - expected_similarity).abs() < tolerance,
-            "The similarity value is not within the expected range"
-        );
+    fn test_search_query_set() {
+        let mut search = Search::new_empty();
+        search
+            .query_set(r#"tag:dns title:"zone transfer" -tag:windows +description:lookup scanner"#)
+            .unwrap();
+
+        // The words without a field are searched in every field.
+        assert_eq!(search.tags_get(), &vec!["dns".to_string(), "scanner".to_string()]);
+        assert_eq!(search.title_get(), "zone transfer scanner");
+        assert_eq!(search.description_get(), "lookup scanner");
+        assert_eq!(search.id_get(), "scanner");
+        assert_eq!(search.reference_get(), &vec!["scanner".to_string()]);
+
+        // The negated and required parts are filters.
+        assert_eq!(search.must.len(), 1);
+        assert_eq!(search.must_not.len(), 1);
     }
 
     #[test]
-    fn test_search_cosine_similarities_1_long_vectors_equal() {
-        // Define two vectors 'word' and 'query', each containing a single identical string "aa".
-        let word = vec![
-            "aa".to_owned(),
-        ];
-        let query = vec![
-            "aa".to_owned(),
-        ];
-    
-        // Calculate the average cosine similarity between all pairs of strings from the two vectors.
-        // In this case, since each vector has only one string and both strings are identical,
-        // the cosine similarity is calculated for this single pair of identical strings.
-        let similarity = Search::cosine_similarities(&word, &query);
-    
-        // This test checks the behavior of the cosine_similarities function when the input vectors contain identical strings.
-        // Since the only pair of strings to compare is identical ("aa" from both vectors),
-        // the expected result is a cosine similarity of 1.0, indicating perfect similarity.
-    
-        // The assertion verifies if the calculated average similarity for this pair is indeed 1.0 as expected,
-        // confirming that the function correctly calculates the similarity for identical strings,
-        // recognizing them as perfectly similar.
-        assert_eq!(similarity, 1.0);
+    fn test_search_query_set_invalid() {
+        let mut search = Search::new_empty();
+
+        assert!(search.query_set("title:\"zone").is_err());
     }
-    
-    
+
     #[test]
-    fn test_search_cosine_similarities_1_long_vectors_not_equal() {
-        // Define two vectors 'word' and 'query', each containing a single string.
-        // 'word' contains the string "aa", and 'query' contains the string "cb".
-        let word = vec![
-            "aa".to_owned(),
-        ];
-        let query = vec![
-            "cb".to_owned(),
-        ];
-    
-        // Calculate the average cosine similarity between all pairs of strings from the two vectors.
-        // In this case, there is only one pair of strings to compare, and these strings are "aa" and "cb".
-        let similarity = Search::cosine_similarities(&word, &query);
-    
-        // This test checks the behavior of the cosine_similarities function with two completely different strings.
-        // Since "aa" and "cb" do not share any common characters, they also do not have any common bigrams.
-        // Therefore, the expected result is a cosine similarity of 0.0, indicating no similarity.
-    
-        // The assertion verifies if the calculated average similarity for this pair is indeed 0.0 as expected,
-        // confirming that the function correctly identifies the lack of similarity between completely different strings.
-        assert_eq!(similarity, 0.0);
-    }    
+    fn test_search_matches() {
+        let windows = Template::new(
+            "responder".to_string(),
+            "Responder".to_string(),
+            vec!["smb".to_string(), "windows".to_string()],
+            "".to_string(),
+            vec![],
+            vec![],
+        );
+        let linux = Template::new(
+            "smbclient".to_string(),
+            "smbclient".to_string(),
+            vec!["smb".to_string()],
+            "".to_string(),
+            vec![],
+            vec![],
+        );
+
+        let mut search = Search::new_empty();
+        search.query_set("+tag:smb -tag:windows").unwrap();
+
+        assert!(!search.matches(&windows));
+        assert!(search.matches(&linux));
+
+        // A search without a query lets every template pass.
+        assert!(Search::new_empty().matches(&windows));
+    }
 
     #[test]
-    fn test_search_cosine_similarities_2_char_strings_vectors_have_not_equal_values() {
-        let value = vec![
-            "aa".to_owned(),
-        ];
-        let query = vec![
-            "ab".to_owned(),
-        ];
-
-        let similarity = Search::cosine_similarities(&value, &query);
-
-        // In this test case, we calculate the cosine similarity between the strings "aa" and "ab".
-        // Since the cosine similarity algorithm uses bigrams, we consider the bigrams in each string.
-        // "aa" contains the bigram "aa".
-        // "ab" contains the bigram "ab".
-        // As there are no common bigrams between the two strings, the similarity is 0.0.
-        // This result is confirmed by the formula for calculating cosine similarity,
-        // which is based on the number of shared features (here bigrams) between the two strings.
-    
-        // The test checks if the calculated similarity is 0.0.
-        assert_eq!(similarity, 0.0);
-        // assert!(expected_similarity_range.contains(&similarity));
+    fn test_search_matches_nested_filters() {
+        let template = |in_id: &str, in_tags: &[&str]| {
+            Template::new(
+                in_id.to_string(),
+                in_id.to_string(),
+                in_tags.iter().map(|tag| tag.to_string()).collect(),
+                "".to_string(),
+                vec![],
+                vec![],
+            )
+        };
+        let responder = template("responder", &["smb", "windows"]);
+        let smbclient = template("smbclient", &["smb"]);
+        let dig = template("dig", &["dns", "lookup"]);
+
+        // A negated word in a group is a filter like a negated word at the top level.
+        let mut search = Search::new_empty();
+        search.query_set("tag:smb (client -tag:windows)").unwrap();
+        assert_eq!(search.must_not.len(), 1);
+        assert!(!search.matches(&responder));
+        assert!(search.matches(&smbclient));
+
+        // The OR with a negated word is required as a whole.
+        let mut search = Search::new_empty();
+        search.query_set("dns OR smb -tag:windows").unwrap();
+        assert_eq!(search.must.len(), 1);
+        assert!(!search.matches(&responder));
+        assert!(search.matches(&smbclient));
+        assert!(search.matches(&dig));
+        assert_eq!(search.tags_get(), &vec!["dns".to_string(), "smb".to_string()]);
+    }
+
+    fn name_templates() -> Vec<Template> {
+        ["nmap", "masscan", "dig", "host"]
+            .iter()
+            .map(|id| {
+                Template::new(
+                    id.to_string(),
+                    format!("{} tool", id),
+                    vec![],
+                    "".to_string(),
+                    vec![],
+                    vec![],
+                )
+            })
+            .collect()
     }
 
     #[test]
-    fn test_search_cosine_similarities_2_char_strings_vectors_have_not_equal_value_revers() {
-        let value = vec![
-            "aa".to_owned(),
-        ];
-        let query = vec![
-            "ba".to_owned(),
-        ];
-
-        let similarity = Search::cosine_similarities(&value, &query);
-
-        // In this test case, we calculate the cosine similarity between the strings "aa" and "ab".
-        // Since the cosine similarity algorithm uses bigrams, we consider the bigrams in each string.
-        // "aa" contains the bigram "aa".
-        // "ab" contains the bigram "ab".
-        // As there are no common bigrams between the two strings, the similarity is 0.0.
-        // This result is confirmed by the formula for calculating cosine similarity,
-        // which is based on the number of shared features (here bigrams) between the two strings.
-    
-        // The test checks if the calculated similarity is 0.0.
-        assert_eq!(similarity, 0.0);
-        // assert!(expected_similarity_range.contains(&similarity));
+    fn test_search_name_threshold() {
+        assert_eq!(Search::name_threshold("nc"), 0);
+        assert_eq!(Search::name_threshold("dig"), 1);
+        assert_eq!(Search::name_threshold("nmap"), 2);
+        assert_eq!(Search::name_threshold("metasploit"), 3);
     }
 
     #[test]
-    fn test_search_cosine_similarities_1_long_vectors_2_by_3_1_equal() {
-        // Define two vectors 'word' and 'query', each containing a single identical string "baa".
-        let word = vec![
-            "baa".to_owned(),
-        ];
-        let query = vec![
-            "baa".to_owned(),
-        ];
-    
-        // Calculate the average cosine similarity between all pairs of strings from the two vectors.
-        // In this case, since each vector has only one string and both strings are identical,
-        // the cosine similarity is calculated for this single pair of identical strings.
-        let similarity = Search::cosine_similarities(&word, &query);
-    
-        // This test checks the behavior of the cosine_similarities function when the input vectors contain identical strings.
-        // Since the only pair of strings to compare is identical ("baa" from both vectors),
-        // the expected result is a cosine similarity of 1.0, indicating perfect similarity.
-    
-        // The assertion verifies if the calculated average similarity for this pair is indeed 1.0 as expected.
-        // This confirms that the function correctly calculates the similarity for identical strings,
-        // recognizing them as perfectly similar.
-        assert_eq!(similarity, 1.0);
-    }    
+    fn test_search_similar_ids() {
+        // A typo and a part of the id are similar, the closest id first.
+        assert_eq!(Search::similar_ids("nmpa", &name_templates(), 5), vec!["nmap"]);
+        assert_eq!(Search::similar_ids("scan", &name_templates(), 5), vec!["masscan"]);
+        assert_eq!(Search::similar_ids("hos", &name_templates(), 5), vec!["host"]);
+        assert!(Search::similar_ids("metasploit", &name_templates(), 5).is_empty());
+        assert_eq!(Search::similar_ids("dig", &name_templates(), 1), vec!["dig"]);
+    }
 
     #[test]
-    fn test_search_cosine_similarity_string_1_by_1_equal() {
-        // Define two strings 'word' and 'query' for testing, where both strings are identical.
-        // Both 'word' and 'query' consist of the character 'a' repeated three times.
-        let word: &str = "aaa";
-        let query: &str = "aaa";
-    
-        // Calculate the cosine similarity between 'word' and 'query'.
-        let similarity = Search::cosine_similarity(word, query);
-    
-        // This test checks the behavior of the cosine_similarity function when the two input strings are identical.
-        // In this case, since 'word' and 'query' are exactly the same, they share all their characters and, consequently, all their bigrams.
-        // Therefore, the expected outcome is that the cosine similarity should be 1.0,
-        // indicating perfect similarity as per the cosine similarity metric.
-    
-        // The assertion verifies if the calculated similarity is indeed 1.0 as expected,
-        // confirming that the function accurately identifies the complete similarity between two identical strings.
-        assert_eq!(similarity, 1.0);
+    fn test_search_find_name_exact() {
+        let mut search = Search::new_empty();
+        search.name_set("NMAP");
+
+        assert_eq!(
+            search.find_name(&name_templates()),
+            Some(NameMatch { id: "nmap".to_string(), exact: true })
+        );
     }
-    
+
     #[test]
-    fn test_search_cosine_similarity_string_1_by_1_not_equal() {
-        // Define two strings 'word' and 'query' for testing.
-        // 'word' consists of the character 'a' repeated three times.
-        // 'query' consists of the character 'b' repeated three times.
-        let word: &str = "aaa";
-        let query: &str = "bbb";
-    
-        // Calculate the cosine similarity between 'word' and 'query'.
-        let similarity = Search::cosine_similarity(word, query);
-    
-        // This test checks the behavior of the cosine_similarity function with two strings that are different.
-        // In this case, 'word' and 'query' do not share any common characters, which means they have no common bigrams.
-        // The expected outcome is that the cosine similarity between these two strings should be 0.0,
-        // indicating that they are completely dissimilar according to the cosine similarity metric.
-    
-        // The assertion verifies if the calculated similarity is indeed 0.0 as expected, 
-        // confirming that the two strings are recognized as completely dissimilar.
-        assert_eq!(similarity, 0.0);
+    fn test_search_find_name_typo() {
+        let mut search = Search::new_empty();
+        search.name_set("nmpa");
+
+        assert_eq!(
+            search.find_name(&name_templates()),
+            Some(NameMatch { id: "nmap".to_string(), exact: false })
+        );
     }
-    
+
     #[test]
-    fn test_search_cosine_similarity_string_1_4_by_1_2_not_equal() {
-        // Define two strings 'word' and 'query' for testing.
-        // 'word' consists of the character 'a' repeated four times.
-        // 'query' consists of the character 'b' repeated two times.
-        let word: &str = "aaaa";
-        let query: &str = "bb";
-    
-        // Calculate the cosine similarity between 'word' and 'query'.
-        let similarity = Search::cosine_similarity(word, query);
-    
-        // This test checks the behavior of the cosine_similarity function with two entirely different strings.
-        // In this case, 'word' and 'query' do not share any common characters. Therefore, they do not have any common bigrams.
-        // The cosine similarity should reflect this lack of commonality by returning a similarity score of 0.0.
-        // This would mean that the two strings are completely dissimilar as per the cosine similarity metric.
-    
-        // The assertion checks if the calculated similarity is indeed 0.0 as expected.
-        assert_eq!(similarity, 0.0);
+    fn test_search_find_name_title() {
+        let mut search = Search::new_empty();
+        search.name_set("masscan tol");
+
+        assert_eq!(
+            search.find_name(&name_templates()),
+            Some(NameMatch { id: "masscan".to_string(), exact: false })
+        );
     }
-    
 
     #[test]
-    fn test_search_cosine_similarity_string_1_4_by_1_2_05_equal() {
-        // Define two strings 'word' and 'query' for testing.
-        // 'word' is a string with repeated character 'a' four times.
-        // 'query' is a string with repeated character 'a' two times.
-        let word: &str = "aaaa";
-        let query: &str = "aa";
-    
-        // Calculate the cosine similarity between 'word' and 'query'.
-        let similarity = Search::cosine_similarity(word, query);
-    
-        // This test checks the behavior of the cosine_similarity function with two strings of different lengths,
-        // but with repetitive characters. The test case is designed to see how the function handles strings where
-        // one is a subset of the other.
-        // The expected behavior in this case is that the similarity should be 1.0 because the shorter string 'aa'
-        // is entirely contained within the longer string 'aaaa'. In terms of bigrams, both strings will have
-        // the bigram 'aa' repeated, leading to a perfect similarity score.
-    
-        // The assertion checks if the calculated similarity is indeed 1.0 as expected.
-        assert_eq!(similarity, 1.0);
+    fn test_search_find_name_too_different() {
+        let mut search = Search::new_empty();
+        search.name_set("dns");
+
+        // dns is two typos away from dig, too many for a short name.
+        assert_eq!(search.find_name(&name_templates()), None);
+        assert_eq!(Search::new_empty().find_name(&name_templates()), None);
     }
-    
-    
 }
@@ -0,0 +1,243 @@
+use crate::{error::{Result, WamiError}, search::{Search, Weights}};
+
+// A builder for the search, so the lake and GitHub can be searched without the command line.
+// The words of every field are collected first, build puts them together like the flags of wami.
+// The errors of the queries and the weights are returned by build, so the calls can be chained.
+
+#[derive(Debug, Clone)]
+pub struct SearchBuilder {
+    words: Vec<String>,        // The names and the tags, like wami <words>.
+    all: Vec<String>,          // The same words for every field.
+    names: Vec<String>,
+    title: Vec<String>,
+    tags: Vec<String>,
+    description: Vec<String>,
+    references: Vec<String>,
+    queries: Vec<String>,      // Queries in the query language.
+    weights: Weights,
+    weight_overrides: Vec<String>,
+    use_synonyms: bool,
+    min_stars: isize,
+    max_stars: isize,
+    github_fork: bool,
+}
+
+impl Default for SearchBuilder {
+    fn default() -> SearchBuilder {
+        SearchBuilder {
+            words: Vec::new(),
+            all: Vec::new(),
+            names: Vec::new(),
+            title: Vec::new(),
+            tags: Vec::new(),
+            description: Vec::new(),
+            references: Vec::new(),
+            queries: Vec::new(),
+            weights: Weights::default(),
+            weight_overrides: Vec::new(),
+            use_synonyms: true,
+            min_stars: 100,
+            max_stars: 0,
+            github_fork: false,
+        }
+    }
+}
+
+// Collect the words of an iterator as strings.
+fn strings<I, S>(in_words: I) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    in_words.into_iter().map(Into::into).collect()
+}
+
+impl Search {
+    pub fn builder() -> SearchBuilder {
+        SearchBuilder::default()
+    }
+}
+
+impl SearchBuilder {
+    // Search the names and the tags of the lake.
+    pub fn words<I: IntoIterator<Item = S>, S: Into<String>>(mut self, in_words: I) -> SearchBuilder {
+        self.words.extend(strings(in_words));
+        self
+    }
+
+    // Search all fields with the same words.
+    pub fn all<I: IntoIterator<Item = S>, S: Into<String>>(mut self, in_words: I) -> SearchBuilder {
+        self.all.extend(strings(in_words));
+        self
+    }
+
+    // Search the unique names, they can have typos as well.
+    pub fn names<I: IntoIterator<Item = S>, S: Into<String>>(mut self, in_words: I) -> SearchBuilder {
+        self.names.extend(strings(in_words));
+        self
+    }
+
+    pub fn title<I: IntoIterator<Item = S>, S: Into<String>>(mut self, in_words: I) -> SearchBuilder {
+        self.title.extend(strings(in_words));
+        self
+    }
+
+    pub fn tags<I: IntoIterator<Item = S>, S: Into<String>>(mut self, in_words: I) -> SearchBuilder {
+        self.tags.extend(strings(in_words));
+        self
+    }
+
+    pub fn description<I: IntoIterator<Item = S>, S: Into<String>>(mut self, in_words: I) -> SearchBuilder {
+        self.description.extend(strings(in_words));
+        self
+    }
+
+    pub fn references<I: IntoIterator<Item = S>, S: Into<String>>(mut self, in_words: I) -> SearchBuilder {
+        self.references.extend(strings(in_words));
+        self
+    }
+
+    // Add a query in the query language, e.g. 'tag:dns -tag:windows'.
+    pub fn query(mut self, in_query: &str) -> SearchBuilder {
+        self.queries.push(in_query.to_owned());
+        self
+    }
+
+    // The weights of the fields, e.g. the weights of the config.yaml.
+    pub fn weights(mut self, in_weights: Weights) -> SearchBuilder {
+        self.weights = in_weights;
+        self
+    }
+
+    // Override some weights with a list like "title=3,tags=2".
+    pub fn weight_overrides(mut self, in_overrides: &str) -> SearchBuilder {
+        self.weight_overrides.push(in_overrides.to_owned());
+        self
+    }
+
+    pub fn synonyms(mut self, in_use_synonyms: bool) -> SearchBuilder {
+        self.use_synonyms = in_use_synonyms;
+        self
+    }
+
+    // The stars of the GitHub repositories, a max of 0 means there is no limit.
+    pub fn stars(mut self, in_min_stars: isize, in_max_stars: isize) -> SearchBuilder {
+        self.min_stars = in_min_stars;
+        self.max_stars = in_max_stars;
+        self
+    }
+
+    // Search the forks of the GitHub repositories as well.
+    pub fn github_fork(mut self, in_github_fork: bool) -> SearchBuilder {
+        self.github_fork = in_github_fork;
+        self
+    }
+
+    // Put the search together.
+    // The plain words are the name and the tags, the fields add their words to them.
+    pub fn build(self) -> Result<Search> {
+        let mut out_search: Search = Search::new_empty();
+
+        if !self.words.is_empty() {
+            out_search.name_set(&self.words.join(" "));
+            out_search.tags_set(&self.words);
+        }
+
+        if !self.all.is_empty() {
+            let all_string: String = self.all.join(" ");
+            out_search.id_set(&all_string);
+            out_search.title_set(&all_string);
+            out_search.tags_set(&[self.all.clone(), out_search.tags_get().clone()].concat());
+            out_search.description_set(&all_string);
+            out_search.reference_set(&self.all);
+        }
+
+        if !self.names.is_empty() {
+            let names_string: String = self.names.join(" ");
+            out_search.name_set(&names_string);
+            out_search.id_set(&(out_search.id_get().to_owned() + " " + &names_string));
+        }
+
+        if !self.title.is_empty() {
+            out_search.title_set(&(out_search.title_get().to_owned() + " " + &self.title.join(" ")));
+        }
+
+        if !self.tags.is_empty() {
+            out_search.tags_set(&[self.tags, out_search.tags_get().clone()].concat());
+        }
+
+        if !self.description.is_empty() {
+            out_search.description_set(&(out_search.description_get().to_owned() + " " + &self.description.join(" ")));
+        }
+
+        if !self.references.is_empty() {
+            out_search.reference_set(&[self.references, out_search.reference_get().clone()].concat());
+        }
+
+        for query in &self.queries {
+            out_search
+                .query_set(query)
+                .map_err(|err| WamiError::InvalidInput(format!("Failed to parse the query: {}", err)))?;
+        }
+
+        let mut weights: Weights = self.weights;
+        for weight_overrides in &self.weight_overrides {
+            weights
+                .apply_overrides(weight_overrides)
+                .map_err(|err| WamiError::InvalidInput(format!("Failed to parse the weight: {}", err)))?;
+        }
+        out_search.weights_set(weights);
+        out_search.use_synonyms_set(self.use_synonyms);
+
+        // GitHub needs at least 100 stars, a max below the min means there is no limit.
+        out_search.min_stars_set(self.min_stars.max(100));
+        if self.max_stars <= *out_search.min_stars_get() {
+            out_search.max_stars_set(0);
+        } else {
+            out_search.max_stars_set(self.max_stars);
+        }
+        out_search.github_fork_set(self.github_fork);
+
+        Ok(out_search)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_words_are_name_and_tags() {
+        let search = Search::builder().words(["dns", "lookup"]).tags(["zone"]).build().unwrap();
+
+        assert_eq!(search.tags_get(), &vec!["zone", "dns", "lookup"]);
+        assert_eq!(search.id_get(), "");
+    }
+
+    #[test]
+    fn test_builder_all_fields() {
+        let search = Search::builder().all(["port", "scanner"]).title(["fast"]).build().unwrap();
+
+        assert_eq!(search.id_get(), "port scanner");
+        assert_eq!(search.title_get(), "port scanner fast");
+        assert_eq!(search.description_get(), "port scanner");
+        assert_eq!(search.reference_get(), &vec!["port", "scanner"]);
+    }
+
+    #[test]
+    fn test_builder_errors() {
+        assert!(Search::builder().query("title:(dns").build().is_err());
+        assert!(Search::builder().weight_overrides("stars=3").build().is_err());
+
+        let search = Search::builder().weight_overrides("title=3").build().unwrap();
+        assert_eq!(search.weights_get().title, 3.0);
+    }
+
+    #[test]
+    fn test_builder_stars() {
+        let search = Search::builder().stars(10, 50).build().unwrap();
+
+        assert_eq!(*search.min_stars_get(), 100);
+        assert_eq!(*search.max_stars_get(), 0);
+    }
+}
@@ -0,0 +1,556 @@
+use crate::index::Field;
+use crate::search::Search;
+use crate::template::Template;
+
+// This is the query language of the lake search.
+//
+//   dns scanner                  Words rank the templates, like the -s flag.
+//   tag:dns                      A field prefix searches only in this field.
+//   title:"zone transfer"        Quoted words have to be found one after another.
+//   description:(spoof OR poison) The prefix is used for the whole group.
+//   -tag:windows  NOT windows    The template must not contain the words.
+//   +tag:dns  dns AND scanner    The template must contain the words.
+//
+// Words next to each other only rank the templates, they are not required.
+// A template which does not fulfill the + and AND parts or contains a negated part
+// is removed from the results before the ranking.
+
+// The query is a tree of words and boolean operators.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    // Words which have to be found one after another, a single word or a quoted phrase.
+    // Without a field, the words can be found in any field.
+    Words {
+        field: Option<Field>,
+        words: Vec<String>,
+    },
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+    Must(Box<Query>),
+}
+
+impl Query {
+    // Parse the query string.
+    pub fn parse(in_query: &str) -> Result<Query, String> {
+        let tokens: Vec<Token> = tokenize(in_query)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+        };
+
+        let out_query = parser.parse_or(None)?;
+        match parser.peek() {
+            None => out_query.ok_or_else(|| format!("Nothing to search for in '{}'.", in_query.trim())),
+            Some(Token::RightParen) => Err("Unexpected ')' in the query.".to_string()),
+            Some(token) => Err(format!("Unexpected {} in the query.", token.describe())),
+        }
+    }
+
+    // Split the query into the parts which are combined by the top level.
+    // A group of words is split as well, so "tag:dns (lookup -windows)" has the parts tag:dns, lookup and -windows.
+    // A single part is returned as it is.
+    pub fn clauses(&self) -> Vec<&Query> {
+        match self {
+            Query::And(clauses) => clauses.iter().flat_map(Query::clauses).collect(),
+            query => vec![query],
+        }
+    }
+
+    // Check if a part of the query is negated or required, e.g. the OR of "dns OR smb -windows".
+    // Such a part is a filter, even if it is nested in an OR.
+    pub fn has_filter(&self) -> bool {
+        match self {
+            Query::Words { .. } => false,
+            Query::And(queries) | Query::Or(queries) => queries.iter().any(Query::has_filter),
+            Query::Not(_) | Query::Must(_) => true,
+        }
+    }
+
+    // Check if the template fulfills the query.
+    pub fn matches(&self, in_template: &Template) -> bool {
+        match self {
+            Query::Words { field, words } => match field {
+                Some(field) => Query::field_contains(*field, in_template, words),
+                None => Field::ALL
+                    .iter()
+                    .any(|field| Query::field_contains(*field, in_template, words)),
+            },
+            Query::And(queries) => queries.iter().all(|query| query.matches(in_template)),
+            Query::Or(queries) => queries.iter().any(|query| query.matches(in_template)),
+            Query::Not(query) => !query.matches(in_template),
+            Query::Must(query) => query.matches(in_template),
+        }
+    }
+
+    // Collect the words which are used to rank the templates.
+    // Negated words are not collected, they will never be found in a hit.
+    pub fn ranking_words(&self, out_words: &mut Vec<(Option<Field>, String)>) {
+        match self {
+            Query::Words { field, words } => {
+                out_words.push((*field, words.join(" ")));
+            }
+            Query::And(queries) | Query::Or(queries) => {
+                for query in queries {
+                    query.ranking_words(out_words);
+                }
+            }
+            Query::Not(_) => {}
+            Query::Must(query) => query.ranking_words(out_words),
+        }
+    }
+
+    // Check if the words are found one after another in one of the values of the field.
+    fn field_contains(in_field: Field, in_template: &Template, in_words: &[String]) -> bool {
+        in_field.values(in_template).iter().any(|value| {
+            let value_words = Search::tokenize(value);
+            value_words
+                .windows(in_words.len())
+                .any(|window| window == in_words)
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Phrase(String),
+    Field(Field),
+    LeftParen,
+    RightParen,
+    Minus,
+    Plus,
+    And,
+    Or,
+    Not,
+}
+
+impl Token {
+    // Describe the token for an error message.
+    fn describe(&self) -> String {
+        match self {
+            Token::Word(word) => format!("word '{}'", word),
+            Token::Phrase(phrase) => format!("phrase \"{}\"", phrase),
+            Token::Field(field) => format!("field '{}:'", field.name()),
+            Token::LeftParen => "'('".to_string(),
+            Token::RightParen => "')'".to_string(),
+            Token::Minus => "'-'".to_string(),
+            Token::Plus => "'+'".to_string(),
+            Token::And => "'AND'".to_string(),
+            Token::Or => "'OR'".to_string(),
+            Token::Not => "'NOT'".to_string(),
+        }
+    }
+}
+
+// Find the field for a prefix of the query.
+// Besides the names of the fields, the singular forms and some short forms are allowed.
+fn field_from_prefix(in_prefix: &str) -> Option<Field> {
+    match in_prefix.to_lowercase().as_str() {
+        "id" | "name" => Some(Field::Id),
+        "title" => Some(Field::Title),
+        "tag" | "tags" => Some(Field::Tags),
+        "desc" | "description" => Some(Field::Description),
+        "ref" | "reference" | "references" => Some(Field::References),
+        _ => None,
+    }
+}
+
+// Split the query string into tokens.
+fn tokenize(in_query: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = in_query.chars().collect();
+    let mut out_tokens: Vec<Token> = Vec::new();
+    let mut position: usize = 0;
+
+    while position < chars.len() {
+        let c = chars[position];
+
+        if c.is_whitespace() {
+            position += 1;
+        } else if c == '(' {
+            out_tokens.push(Token::LeftParen);
+            position += 1;
+        } else if c == ')' {
+            out_tokens.push(Token::RightParen);
+            position += 1;
+        } else if c == '"' {
+            // Read until the closing quote.
+            let end = chars[position + 1..]
+                .iter()
+                .position(|c| *c == '"')
+                .map(|offset| position + 1 + offset)
+                .ok_or("Missing closing '\"' in the query.".to_string())?;
+            out_tokens.push(Token::Phrase(chars[position + 1..end].iter().collect()));
+            position = end + 1;
+        } else if (c == '-' || c == '+')
+            && chars
+                .get(position + 1)
+                .map(|next| !next.is_whitespace() && *next != ')')
+                .unwrap_or(false)
+        {
+            // A leading - or + belongs to the following part of the query.
+            out_tokens.push(if c == '-' { Token::Minus } else { Token::Plus });
+            position += 1;
+        } else {
+            // Read the word until the next separator.
+            let start = position;
+            while position < chars.len()
+                && !chars[position].is_whitespace()
+                && !matches!(chars[position], '(' | ')' | '"')
+            {
+                position += 1;
+            }
+            let word: String = chars[start..position].iter().collect();
+
+            // A known field followed by a colon is a field prefix, like tag:dns.
+            // Other words with a colon, like urls, are normal words.
+            if let Some((prefix, rest)) = word.split_once(':') {
+                if let Some(field) = field_from_prefix(prefix) {
+                    out_tokens.push(Token::Field(field));
+                    position = start + prefix.chars().count() + 1;
+                    if rest.is_empty() && chars.get(position).map(|c| c.is_whitespace()).unwrap_or(true) {
+                        return Err(format!("Missing search value after '{}:'.", prefix));
+                    }
+                    continue;
+                }
+            }
+
+            out_tokens.push(match word.as_str() {
+                "AND" => Token::And,
+                "OR" => Token::Or,
+                "NOT" => Token::Not,
+                _ => Token::Word(word),
+            });
+        }
+    }
+
+    Ok(out_tokens)
+}
+
+// A recursive descent parser for the tokens.
+// OR has the lowest precedence, then AND and words next to each other,
+// then the - + NOT prefixes.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    // The parse functions return None for a part which has nothing to search for, e.g. the stopword "for".
+    // Such a part is skipped like a soft word, only a query without any word to search for is an error.
+    fn parse_or(&mut self, in_field: Option<Field>) -> Result<Option<Query>, String> {
+        let mut queries: Vec<Query> = self.parse_and(in_field)?.into_iter().collect();
+        let mut skipped: bool = queries.is_empty();
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            match self.parse_and(in_field)? {
+                Some(query) => queries.push(query),
+                None => skipped = true,
+            }
+        }
+
+        Ok(match queries.len() {
+            0 if skipped => None,
+            1 => Some(queries.remove(0)),
+            _ => Some(Query::Or(queries)),
+        })
+    }
+
+    fn parse_and(&mut self, in_field: Option<Field>) -> Result<Option<Query>, String> {
+        let mut queries: Vec<Query> = Vec::new();
+        let mut skipped: bool = false;
+        // True if the last part was skipped, it is the left side of a following AND.
+        let mut last_skipped: bool = false;
+        loop {
+            match self.peek() {
+                None | Some(Token::RightParen) | Some(Token::Or) => break,
+                Some(Token::And) => {
+                    self.next();
+                    // Both sides of an AND are required.
+                    if !last_skipped {
+                        let left = queries
+                            .pop()
+                            .ok_or("Missing search value before 'AND'.".to_string())?;
+                        queries.push(Parser::require(left));
+                    }
+                    last_skipped = match self.parse_unary(in_field)? {
+                        Some(right) => {
+                            queries.push(Parser::require(right));
+                            false
+                        }
+                        None => true,
+                    };
+                }
+                Some(_) => {
+                    last_skipped = match self.parse_unary(in_field)? {
+                        Some(query) => {
+                            queries.push(query);
+                            false
+                        }
+                        None => true,
+                    };
+                }
+            }
+            skipped |= last_skipped;
+        }
+
+        match queries.len() {
+            0 if skipped => Ok(None),
+            0 => Err(match self.peek() {
+                Some(token) => format!("Missing search value before {}.", token.describe()),
+                None => "Missing search value at the end of the query.".to_string(),
+            }),
+            1 => Ok(Some(queries.remove(0))),
+            _ => Ok(Some(Query::And(queries))),
+        }
+    }
+
+    // Wrap a query as required, if it is not required or negated already.
+    fn require(in_query: Query) -> Query {
+        match in_query {
+            Query::Must(_) | Query::Not(_) => in_query,
+            query => Query::Must(Box::new(query)),
+        }
+    }
+
+    fn parse_unary(&mut self, in_field: Option<Field>) -> Result<Option<Query>, String> {
+        match self.peek() {
+            Some(Token::Minus) | Some(Token::Not) => {
+                self.next();
+                Ok(self.parse_unary(in_field)?.map(|query| Query::Not(Box::new(query))))
+            }
+            Some(Token::Plus) => {
+                self.next();
+                Ok(self.parse_unary(in_field)?.map(|query| Query::Must(Box::new(query))))
+            }
+            Some(Token::Field(field)) => {
+                let field = *field;
+                self.next();
+                self.parse_primary(Some(field))
+            }
+            _ => self.parse_primary(in_field),
+        }
+    }
+
+    fn parse_primary(&mut self, in_field: Option<Field>) -> Result<Option<Query>, String> {
+        match self.next() {
+            Some(Token::Word(text)) | Some(Token::Phrase(text)) => {
+                // A word of stopwords only is skipped, e.g. "for" of "scan for ports".
+                let words = Search::tokenize(&text);
+                if words.is_empty() {
+                    return Ok(None);
+                }
+                Ok(Some(Query::Words {
+                    field: in_field,
+                    words,
+                }))
+            }
+            Some(Token::LeftParen) => {
+                let query = self.parse_or(in_field)?;
+                match self.next() {
+                    Some(Token::RightParen) => Ok(query),
+                    _ => Err("Missing closing ')' in the query.".to_string()),
+                }
+            }
+            Some(token) => Err(format!("Unexpected {} in the query.", token.describe())),
+            None => Err("Missing search value at the end of the query.".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(in_field: Option<Field>, in_words: &[&str]) -> Query {
+        Query::Words {
+            field: in_field,
+            words: in_words.iter().map(|word| word.to_string()).collect(),
+        }
+    }
+
+    fn template(in_id: &str, in_title: &str, in_tags: &[&str], in_description: &str) -> Template {
+        Template::new(
+            in_id.to_string(),
+            in_title.to_string(),
+            in_tags.iter().map(|tag| tag.to_string()).collect(),
+            in_description.to_string(),
+            vec![format!("https://example.com/{}", in_id)],
+            vec![],
+        )
+    }
+
+    #[test]
+    fn test_query_parse_words() {
+        assert_eq!(
+            Query::parse("dns Scanner").unwrap(),
+            Query::And(vec![words(None, &["dns"]), words(None, &["scanner"])])
+        );
+    }
+
+    #[test]
+    fn test_query_parse_full_example() {
+        let query =
+            Query::parse(r#"tag:dns title:"zone transfer" -tag:windows description:(spoof OR poison)"#)
+                .unwrap();
+
+        assert_eq!(
+            query,
+            Query::And(vec![
+                words(Some(Field::Tags), &["dns"]),
+                words(Some(Field::Title), &["zone", "transfer"]),
+                // The words are stemmed like the words of the index.
+                Query::Not(Box::new(words(Some(Field::Tags), &["window"]))),
+                Query::Or(vec![
+                    words(Some(Field::Description), &["spoof"]),
+                    words(Some(Field::Description), &["poison"]),
+                ]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_query_parse_and_requires_both_sides() {
+        assert_eq!(
+            Query::parse("dns AND NOT windows").unwrap(),
+            Query::And(vec![
+                Query::Must(Box::new(words(None, &["dns"]))),
+                Query::Not(Box::new(words(None, &["window"]))),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_query_parse_or_precedence() {
+        assert_eq!(
+            Query::parse("dns zone OR smb").unwrap(),
+            Query::Or(vec![
+                Query::And(vec![words(None, &["dns"]), words(None, &["zone"])]),
+                words(None, &["smb"]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_query_parse_plus_and_hyphenated_words() {
+        assert_eq!(
+            Query::parse("+tag:port-scan web-server").unwrap(),
+            Query::And(vec![
+                Query::Must(Box::new(words(Some(Field::Tags), &["port", "scan"]))),
+                words(None, &["web", "server"]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_query_parse_url_is_a_word() {
+        assert_eq!(
+            Query::parse("ref:https://nmap.org").unwrap(),
+            words(Some(Field::References), &["https", "nmap", "org"])
+        );
+    }
+
+    #[test]
+    fn test_query_parse_errors() {
+        assert!(Query::parse("").is_err());
+        assert!(Query::parse("title:\"zone transfer").is_err());
+        assert!(Query::parse("(dns OR host").is_err());
+        assert!(Query::parse("dns)").is_err());
+        assert!(Query::parse("dns OR").is_err());
+        assert!(Query::parse("AND dns").is_err());
+        assert!(Query::parse("tag: dns").is_err());
+        assert!(Query::parse("()").is_err());
+    }
+
+    #[test]
+    fn test_query_parse_skips_stopwords() {
+        assert_eq!(
+            Query::parse("scan for ports").unwrap(),
+            Query::And(vec![words(None, &["scan"]), words(None, &["port"])])
+        );
+        // The lowercase and is a stopword, not the operator.
+        assert_eq!(
+            Query::parse("dns and smb").unwrap(),
+            Query::And(vec![words(None, &["dns"]), words(None, &["smb"])])
+        );
+        assert_eq!(Query::parse("tag:dns -the").unwrap(), words(Some(Field::Tags), &["dns"]));
+        assert_eq!(Query::parse("(for OR dns)").unwrap(), words(None, &["dns"]));
+        assert_eq!(
+            Query::parse("for AND dns").unwrap(),
+            Query::Must(Box::new(words(None, &["dns"])))
+        );
+        assert_eq!(
+            Query::parse(r#"title:"scan for ports""#).unwrap(),
+            words(Some(Field::Title), &["scan", "port"])
+        );
+
+        // A query of stopwords only has nothing to search for.
+        assert!(Query::parse("for").is_err());
+        assert!(Query::parse("the OR and").is_err());
+    }
+
+    #[test]
+    fn test_query_clauses_of_nested_groups() {
+        let query = Query::parse("tag:dns (lookup -windows)").unwrap();
+        let clauses = query.clauses();
+
+        assert_eq!(clauses.len(), 3);
+        assert!(matches!(clauses[2], Query::Not(_)));
+
+        // The OR is a single part, it is a filter because of its negated word.
+        let query = Query::parse("dns OR smb -windows").unwrap();
+        assert_eq!(query.clauses().len(), 1);
+        assert!(query.has_filter());
+        assert!(!Query::parse("dns OR smb").unwrap().has_filter());
+    }
+
+    #[test]
+    fn test_query_matches() {
+        let responder = template("responder", "Responder", &["smb", "windows"], "LLMNR poisoner");
+        let smbclient = template("smbclient", "smbclient", &["smb", "cifs"], "Access SMB resources");
+
+        let query = Query::parse("tag:smb -tag:windows").unwrap();
+        assert!(!query.matches(&responder));
+        assert!(query.matches(&smbclient));
+
+        let query = Query::parse("description:(poisoner OR spoofer)").unwrap();
+        assert!(query.matches(&responder));
+        assert!(!query.matches(&smbclient));
+    }
+
+    #[test]
+    fn test_query_matches_phrase_in_order() {
+        let dig = template("dig", "DNS zone transfer", &["dns"], "");
+
+        assert!(Query::parse(r#"title:"zone transfer""#).unwrap().matches(&dig));
+        assert!(!Query::parse(r#"title:"transfer zone""#).unwrap().matches(&dig));
+        // Without a field, the phrase can be found in any field.
+        assert!(Query::parse(r#""zone transfer""#).unwrap().matches(&dig));
+    }
+
+    #[test]
+    fn test_query_ranking_words_skip_negated_words() {
+        let query = Query::parse("tag:dns -tag:windows (spoof OR poison)").unwrap();
+        let mut ranking_words = Vec::new();
+        query.ranking_words(&mut ranking_words);
+
+        assert_eq!(
+            ranking_words,
+            vec![
+                (Some(Field::Tags), "dns".to_string()),
+                (None, "spoof".to_string()),
+                (None, "poison".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{error::{Result, WamiError}, search::Search};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+// The synonym table of the search.
+// Every group is a list of words or phrases with the same meaning, e.g.:
+//
+//   - [portscan, port scanner, port scan]
+//   - [smb, cifs, samba]
+//
+// If a search contains one entry of a group, the other entries are searched as well.
+// So a search for "portscan" and "port scanner" finds the same tools,
+// even if the author of a template picked a different word.
+// The table ships with the lake as _synonyms.yaml and can be extended in the config.yaml.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Synonyms {
+    groups: Vec<Vec<String>>,
+}
+
+impl Synonyms {
+    // Load the synonym table from a yaml file.
+    pub fn load(in_path: &Path) -> Result<Synonyms> {
+        let parse_error = |in_message: String| WamiError::TemplateParse {
+            path: in_path.to_path_buf(),
+            message: in_message,
+        };
+        let yaml_string = fs::read_to_string(in_path).map_err(|err| parse_error(err.to_string()))?;
+        serde_yaml::from_str(&yaml_string).map_err(|err| parse_error(err.to_string()))
+    }
+
+    // Add the groups of another table, e.g. the groups of the user's config.
+    pub fn extend(&mut self, in_synonyms: Synonyms) {
+        self.groups.extend(in_synonyms.groups);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    // Add the synonyms of the search terms to the terms.
+    // An entry of a group is found, if all its words follow each other in the terms.
+    // The words of the other entries are appended, if they are not already part of the terms,
+    // so equal searches end with the same terms.
+    pub fn expand(&self, in_terms: &[String]) -> Vec<String> {
+        let mut out_terms: Vec<String> = in_terms.to_vec();
+
+        for group in &self.groups {
+            let entries: Vec<Vec<String>> = group.iter().map(|entry| Search::tokenize(entry)).collect();
+
+            let found = entries.iter().any(|entry| {
+                !entry.is_empty() && in_terms.windows(entry.len()).any(|window| window == entry.as_slice())
+            });
+            if !found {
+                continue;
+            }
+
+            for term in entries.into_iter().flatten() {
+                if !out_terms.contains(&term) {
+                    out_terms.push(term);
+                }
+            }
+        }
+
+        out_terms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn synonyms() -> Synonyms {
+        Synonyms {
+            groups: vec![
+                vec!["portscan".to_string(), "port scanner".to_string()],
+                vec!["smb".to_string(), "cifs".to_string(), "samba".to_string()],
+            ],
+        }
+    }
+
+    fn terms(in_text: &str) -> Vec<String> {
+        Search::tokenize(in_text)
+    }
+
+    #[test]
+    fn test_synonyms_expand_word() {
+        assert_eq!(synonyms().expand(&terms("smb shares")), terms("smb shares cifs samba"));
+    }
+
+    #[test]
+    fn test_synonyms_expand_phrase() {
+        assert_eq!(synonyms().expand(&terms("portscan")), terms("portscan port scanner"));
+        assert_eq!(synonyms().expand(&terms("fast port scanner")), terms("fast port scanner portscan"));
+    }
+
+    #[test]
+    fn test_synonyms_phrase_needs_all_words() {
+        // A port alone is not a port scanner.
+        assert_eq!(synonyms().expand(&terms("port knocking")), terms("port knocking"));
+        assert_eq!(synonyms().expand(&terms("scanner port")), terms("scanner port"));
+    }
+
+    #[test]
+    fn test_synonyms_empty_table() {
+        assert_eq!(Synonyms::default().expand(&terms("smb")), terms("smb"));
+    }
+
+    #[test]
+    fn test_synonyms_from_yaml() {
+        let synonyms: Synonyms = serde_yaml::from_str("- [smb, cifs, samba]\n- [portscan, port scanner]\n").unwrap();
+
+        assert_eq!(synonyms.expand(&terms("samba")), terms("samba smb cifs"));
+    }
+
+    #[test]
+    fn test_synonyms_extend() {
+        let mut synonyms = synonyms();
+        synonyms.extend(Synonyms {
+            groups: vec![vec!["dns".to_string(), "domain name".to_string()]],
+        });
+
+        assert_eq!(synonyms.expand(&terms("dns")), terms("dns domain name"));
+    }
+}
@@ -1,8 +1,8 @@
-use crate::search;
+use crate::index::Score;
 use colored::Colorize;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Template {
     id: String,              // A unique name in lower case letters.
     title: String,           // The real name of the tool / software.
@@ -10,66 +10,31 @@ pub struct Template {
     description: String,     // A longer text describing the why and what it does.
     references: Vec<String>, // Links to websites, repositories, or other resources.
     why_not: Vec<String>,    // Links to other programs or sites as an alternative.
-    distance: f32,           // This will set the average similarity to the search value.
+    lake: String,            // The name of the lake the template comes from.
+    #[serde(skip)]
+    score: Score,            // This will set the relevance score to the search value.
 }
 
 impl Template {
     // Create a new Template an return it.
     // I am using the new method,
     // because I will transform the id and tags fields to the right form.
-    // and I will calculate the distance to the search string.
+    // The distance to the search is calculated later by the lake index,
+    // because the ranking needs the statistics of the whole lake.
     pub fn new(
         in_id: String,
-        in_id_search: String,
         in_title: String,
-        in_title_search: String,
         in_tags: Vec<String>,
-        in_tags_search: Vec<String>,
         in_description: String,
-        in_description_search: String,
         in_references: Vec<String>,
-        in_references_search: Vec<String>,
         in_why_not: Vec<String>,
     ) -> Template {
         // Convert the incoming unique name to lowercase alphanumeric letters with hyphens.
         let out_id: String = Template::convert_to_lowercase_alphanumeric_with_hyphens(&in_id);
 
-        // Convert search unique name to lowercase alphanumeric letters with hyphens.
-        let out_id_search: String =
-            Template::convert_to_lowercase_alphanumeric_with_hyphens(&in_id_search);
-
-        // Calculate the similarity from the unique name to the unique search name.
-        let out_id_distance: f32 = search::Search::cosine_similarity(&out_id, &out_id_search);
-
-        // Calculate the similarity from the real title to the search title.
-        let out_title_distance: f32 = search::Search::cosine_similarity(&in_title, &in_title_search);
-
         // Convert the incoming tags to lowercase alphanumeric letters with hyphens.
         let out_tags: Vec<String> = Template::convert_tags_to_excepted_format(&in_tags);
 
-        // Convert the incoming search tags to lowercase alphanumeric letter with hyphens.
-        let out_tags_search: Vec<String> =
-            Template::convert_tags_to_excepted_format(&in_tags_search);
-
-        // Calculate the similarities from the tags to the search tags.
-        let out_tags_distance: f32 = search::Search::cosine_similarities(&out_tags, &out_tags_search);
-
-        // Calculate the similarity from the description to the search description.
-        let out_description_distance: f32 =
-            search::Search::cosine_similarity(&in_description, &in_description_search);
-
-        // Calculate the similarity from the references to the search references.
-        let out_references_distance: f32 =
-            search::Search::cosine_similarities(&in_references, &in_references_search);
-
-        // Calculate the over all similarity. This is done by adding all 5 distances together and multiplying them by 0.2.
-        let out_distance: f32 = (out_id_distance
-            + out_title_distance
-            + out_tags_distance
-            + out_description_distance
-            + out_references_distance)
-            * 0.2;
-
         // Creating and returning the new Template
         Template {
             id: out_id,                  // The unique name of the template
@@ -78,12 +43,64 @@ impl Template {
             description: in_description, // The description of the template
             references: in_references,   // The references of the template
             why_not: in_why_not,         // This will save the alternative programmes.
-            distance: out_distance,      // The average similarity of all similarities
+            lake: String::new(),         // Will be set by the lake, which loads the template.
+            score: Score::default(),     // Will be set by the lake index.
         }
     }
 
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn references(&self) -> &[String] {
+        &self.references
+    }
+
+    pub fn why_not(&self) -> &[String] {
+        &self.why_not
+    }
+
+    pub fn lake(&self) -> &str {
+        &self.lake
+    }
+
+    pub fn set_lake(&mut self, in_lake: &str) {
+        self.lake = in_lake.to_owned();
+    }
+
+    pub fn set_score(&mut self, in_score: Score) {
+        self.score = in_score;
+    }
+
     pub fn distance(&self) -> f32 {
-        self.distance
+        self.score.total
+    }
+
+    // This will return the score of every searched field and the total score, for the explain mode.
+    pub fn explain_to_string(&self) -> String {
+        let mut out_string = format!("    {} {:.4}", "score".magenta(), self.score.total);
+        for field_score in &self.score.fields {
+            let field_line = format!(
+                "\n      {:<12} {:>8.4}  weight {}",
+                field_score.field.name(),
+                field_score.score,
+                field_score.weight
+            );
+            out_string.push_str(&field_line.truecolor(150, 150, 150).to_string());
+        }
+        out_string
     }
 
     // This will return a string, for the console.
@@ -91,21 +108,60 @@ impl Template {
         let mut out_string = format!("{} {}\n    {}\n    {}\n{}",
                                         &self.id.truecolor(90,90, 255),
                                         &self.title.green(),
-                                        &Template::tags_to_string(&self).truecolor(200,200,150),
+                                        &Template::tags_to_string(self).truecolor(200,200,150),
                                         &self.description.truecolor(150,150,200),
-                                        &Template::references_to_string(&self).truecolor(200,200,200),
+                                        &Template::references_to_string(self).truecolor(200,200,200),
                                     );
-        if why_not && self.why_not.is_empty(){
-            let why_not_out = format!("/n    {}", &self.why_not_to_string().bold().green());
+        if why_not && !self.why_not.is_empty() {
+            let why_not_out = format!("\n    {}", &self.why_not_to_string().replace('\n', "\n    ").bold().green());
             out_string.push_str(&why_not_out);
         }
         out_string.to_owned()
     }
 
+    // This will return every field of the template, for the detail view of a single tool.
+    // The why not entries, which are templates of the lake, are shown with their title.
+    pub fn to_detail_string(&self, in_lake: &[Template]) -> String {
+        let mut out_string = format!("{} {}", &self.id.bold().truecolor(90, 90, 255), &self.title.green());
+        out_string.push_str(&Template::detail_line("tags", &[self.tags.join(", ")]));
+        out_string.push_str(&Template::detail_line("description", &[self.description.to_owned()]));
+        out_string.push_str(&Template::detail_line("references", &self.references));
+
+        let why_not: Vec<String> = self
+            .why_not
+            .iter()
+            .map(|why_not| {
+                // The entry is written by hand, e.g. "Nmap ", so it is converted like the ids of the templates.
+                let id: String = Template::convert_to_lowercase_alphanumeric_with_hyphens(why_not.trim());
+                match in_lake.iter().find(|template| template.id() == id) {
+                    Some(template) => format!("{} ({})", template.title(), template.id()),
+                    None => why_not.to_owned(),
+                }
+            })
+            .collect();
+        out_string.push_str(&Template::detail_line("why not", &why_not));
+        if !self.lake.is_empty() {
+            out_string.push_str(&Template::detail_line("lake", &[self.lake.to_owned()]));
+        }
+        out_string
+    }
+
+    // A field of the detail view, every value is on its own line below each other.
+    fn detail_line(in_label: &str, in_values: &[String]) -> String {
+        let mut out_string = format!("\n    {}", format!("{:<12}", in_label).magenta());
+        for (index, value) in in_values.iter().enumerate() {
+            if index > 0 {
+                out_string.push_str(&format!("\n    {:<12}", ""));
+            }
+            out_string.push_str(&format!(" {}", value));
+        }
+        out_string
+    }
+
     pub fn to_short_string(&self, why_not: bool) -> String {
         let mut out_string = format!("{}\n{}",&self.title.green(),&self.references_to_string().truecolor(200, 200, 200));
-        if why_not && self.why_not.is_empty() {
-            let why_not_out = format!("\n    {}", &self.why_not_to_string().bold().green());
+        if why_not && !self.why_not.is_empty() {
+            let why_not_out = format!("\n    {}", &self.why_not_to_string().replace('\n', "\n    ").bold().green());
             out_string.push_str(&why_not_out);
         }
         out_string.to_owned()
@@ -133,7 +189,7 @@ impl Template {
             out_string.push_str("    ");
             out_string.push_str(reference);
             if index < reference_len - 1 {
-                out_string.push_str("\n");
+                out_string.push('\n');
             }
         }
 
@@ -148,7 +204,7 @@ impl Template {
             out_string.push_str("Why Not ");
             out_string.push_str(why_not);
             if index < why_not_len - 1 {
-                out_string.push_str("\n");
+                out_string.push('\n');
             }
         }
         out_string
@@ -156,10 +212,10 @@ impl Template {
 
     // Make the tags vec robust
     // Will make all tags uniform, this simplifies the search algorithmic.
-    pub fn convert_tags_to_excepted_format(in_tags: &Vec<String>) -> Vec<String> {
+    pub fn convert_tags_to_excepted_format(in_tags: &[String]) -> Vec<String> {
         in_tags
             .iter()
-            .map(|tag| Template::convert_to_lowercase_alphanumeric_with_hyphens(&tag))
+            .map(|tag| Template::convert_to_lowercase_alphanumeric_with_hyphens(tag))
             .collect()
     }
 
@@ -167,7 +223,7 @@ impl Template {
     // If there is an error in the String, it will be converted or deleted.
     // See the requirements for the unique name and tags fields.
     // Only lowercase alphanumeric letters or hyphens are allowed.
-    pub fn convert_to_lowercase_alphanumeric_with_hyphens(in_str: &String) -> String {
+    pub fn convert_to_lowercase_alphanumeric_with_hyphens(in_str: &str) -> String {
         in_str
             .chars()
             .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || c.is_whitespace())
@@ -179,25 +235,20 @@ impl Template {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::index::{Field, FieldScore};
 
     // Tests the Template function new
     #[test]
     fn test_template_structure() {
         let tags_vec: Vec<String> = vec!["tag1".to_string(), "tag2".to_string()];
         let references_vec: Vec<String> = vec!["ref1".to_string(), "ref2".to_string()];
-        let search_vec: Vec<String> = vec!["search2".to_string(), "search3".to_string()];
         let why_not_vec: Vec<String> = vec!["why_not1".to_string(), "why_not2".to_string()];
         let template: Template = Template::new(
             "id".to_string(),
-            "id_search".to_string(),
             "title".to_string(),
-            "title_search".to_string(),
             tags_vec.clone(),
-            search_vec.clone(),
             "description".to_string(),
-            "description_search".to_string(),
             references_vec.clone(),
-            search_vec.clone(),
             why_not_vec.clone(),
         );
 
@@ -232,19 +283,13 @@ mod tests {
     fn test_new() {
         let tags = vec!["Tag1".to_string(), "Tag2".to_string()];
         let references = vec!["ref1".to_string(), "ref2".to_string()];
-        let search_vec: Vec<String> = vec!["search2".to_string(), "search3".to_string()];
         let why_not_vec: Vec<String> = vec!["why_not1".to_string(), "why_not2".to_string()];
         let template = Template::new(
             "Id".to_string(),
-            "id_search".to_string(),
             "Title".to_string(),
-            "title_search".to_string(),
             tags.clone(),
-            search_vec.clone(),
             "Description".to_string(),
-            "description_search".to_string(),
             references.clone(),
-            search_vec.clone(),
             why_not_vec.clone(),
         );
 
@@ -289,20 +334,15 @@ mod tests {
         let why_not: Vec<String> = vec!["why_not1".to_string(), "why_not2".to_string()];
         let template = Template::new(
             "id".to_string(),
-            "id_search".to_string(),
             "title".to_string(),
-            "title_search".to_string(),
-            tags.clone(),
             tags.clone(),
             "description".to_string(),
-            "description_search".to_string(),
-            references.clone(),
             references.clone(),
             why_not.clone(),
         );
 
         // Compare the template to string output with the expected output
-        let expected_output = "id title\n    tag1, tag2\n    description\n    ref1\n    ref2";
+        let expected_output = "id title\n    tag1, tag2\n    description\n    ref1\n    ref2\n    Why Not why_not1\n    Why Not why_not2";
         assert_eq!(template.to_string(in_why_not_bool), expected_output);
     }
 
@@ -315,14 +355,9 @@ mod tests {
         let why_not: Vec<String> = vec!["why_not1".to_string(), "why_not2".to_string()];
         let template = Template::new(
             "id".to_string(),
-            "id_search".to_string(),
             "title".to_string(),
-            "title_search".to_string(),
-            tags.clone(),
             tags.clone(),
             "description".to_string(),
-            "description_search".to_string(),
-            references.clone(),
             references.clone(),
             why_not.clone(),
         );
@@ -342,21 +377,16 @@ mod tests {
         let why_not: Vec<String> = vec!["why_not1".to_string(), "why_not2".to_string()];
         let template = Template::new(
             "id".to_string(),
-            "id_search".to_string(),
             "title".to_string(),
-            "title".to_string(),
-            tags.clone(),
             tags.clone(),
             "description".to_string(),
-            "description_search".to_string(),
-            references.clone(),
             references.clone(),
             why_not.clone(),
         );
 
         // Compare the template to string output with the expected output
-        let expected_output: &str = "id title\n    tag1, tag2\n    description\n    ref1\n    ref2";
-        assert_eq!(template.to_string(in_why_not_bool), expected_output);
+        let expected_output: &str = "title\n    ref1\n    ref2\n    Why Not why_not1\n    Why Not why_not2";
+        assert_eq!(template.to_short_string(in_why_not_bool), expected_output);
     }
 
     // Test for the to_short_string function why_not = false
@@ -368,14 +398,9 @@ mod tests {
         let why_not: Vec<String> = vec!["why_not1".to_string(), "why_not2".to_string()];
         let template = Template::new(
             "id".to_string(),
-            "id_search".to_string(),
-            "title".to_string(),
             "title".to_string(),
             tags.clone(),
-            tags.clone(),
             "description".to_string(),
-            "description_search".to_string(),
-            references.clone(),
             references.clone(),
             why_not.clone(),
         );
@@ -394,14 +419,9 @@ mod tests {
         let why_not: Vec<String> = vec!["why_not1".to_string(), "why_not2".to_string()];
         let template = Template::new(
             "id".to_string(),
-            "id_search".to_string(),
             "title".to_string(),
-            "title_search".to_string(),
-            tags.clone(),
             tags.clone(),
             "description".to_string(),
-            "description_search".to_string(),
-            references.clone(),
             references.clone(),
             why_not.clone(),
         );
@@ -421,22 +441,95 @@ mod tests {
         let why_not: Vec<String> = vec!["why_not1".to_string(), "why_not2".to_string()];
         let template = Template::new(
             "id".to_string(),
-            "id_search".to_string(),
             "title".to_string(),
-            "title_search".to_string(),
-            tags.clone(),
             tags.clone(),
             "description".to_string(),
-            "description_search".to_string(),
-            references.clone(),
             references.clone(),
             why_not.clone(),
         );
 
         // Compare the template to string output with the expected output
-        let expected_output: &str =
-            "id title\n    tag1, tag2\n    description\n    ref1\n    ref2";
-        assert_eq!(template.to_string(in_why_not_bool), expected_output);
+        let expected_output: &str = "title\n    ref1\n    ref2\n    Why Not why_not1\n    Why Not why_not2";
+        assert_eq!(template.to_short_string(in_why_not_bool), expected_output);
+    }
+
+    // Without alternatives there is no why not line, even if the flag is set.
+    #[test]
+    fn test_to_string_without_why_not() {
+        let template = Template::new(
+            "id".to_string(),
+            "title".to_string(),
+            vec!["tag1".to_string()],
+            "description".to_string(),
+            vec!["ref1".to_string()],
+            vec![],
+        );
+
+        assert_eq!(template.to_string(true), "id title\n    tag1\n    description\n    ref1");
+        assert_eq!(template.to_short_string(true), "title\n    ref1");
+    }
+
+    #[test]
+    fn test_to_detail_string() {
+        let template = Template::new(
+            "dig".to_string(),
+            "dig - DNS lookup utility".to_string(),
+            vec!["dns".to_string(), "lookup".to_string()],
+            "DNS lookups.".to_string(),
+            vec!["ref1".to_string(), "ref2".to_string()],
+            vec!["host".to_string(), "nslookup".to_string()],
+        );
+        let lake = vec![Template::new(
+            "host".to_string(),
+            "host - DNS lookup utility".to_string(),
+            vec![],
+            String::new(),
+            vec![],
+            vec![],
+        )];
+
+        // The alternative host is part of the lake, nslookup is not.
+        let expected_output = "dig dig - DNS lookup utility\n    tags         dns, lookup\n    description  DNS lookups.\n    references   ref1\n                 ref2\n    why not      host - DNS lookup utility (host)\n                 nslookup";
+        assert_eq!(template.to_detail_string(&lake), expected_output);
+    }
+
+    // The short string hides the why not entries without the flag, like the long one.
+    #[test]
+    fn test_to_short_string_hides_why_not() {
+        let template = Template::new(
+            "id".to_string(),
+            "title".to_string(),
+            vec!["tag1".to_string()],
+            "description".to_string(),
+            vec!["ref1".to_string()],
+            vec!["why_not1".to_string()],
+        );
+
+        assert_eq!(template.to_short_string(false), "title\n    ref1");
+        assert_eq!(template.to_short_string(true), "title\n    ref1\n    Why Not why_not1");
+    }
+
+    // A why not entry is found like an id, even with upper case letters and spaces.
+    #[test]
+    fn test_to_detail_string_normalizes_why_not() {
+        let template = Template::new(
+            "masscan".to_string(),
+            "masscan".to_string(),
+            vec![],
+            String::new(),
+            vec![],
+            vec!["Nmap ".to_string()],
+        );
+        let lake = vec![Template::new(
+            "nmap".to_string(),
+            "Nmap - the Network Mapper".to_string(),
+            vec![],
+            String::new(),
+            vec![],
+            vec![],
+        )];
+
+        assert!(template.to_detail_string(&lake).ends_with("why not      Nmap - the Network Mapper (nmap)"));
     }
 
     // Test the tags_to_string function against the expected values.
@@ -447,14 +540,9 @@ mod tests {
         let why_not: Vec<String> = vec!["why_not1".to_string(), "why_not2".to_string()];
         let template = Template::new(
             "id".to_string(),
-            "id_search".to_string(),
             "title".to_string(),
-            "title_search".to_string(),
-            tags.clone(),
             tags.clone(),
             "description".to_string(),
-            "description_search".to_string(),
-            references.clone(),
             references.clone(),
             why_not.clone(),
         );
@@ -472,14 +560,9 @@ mod tests {
         let why_not: Vec<String> = vec!["why_not1".to_string(), "why_not2".to_string()];
         let template = Template::new(
             "id".to_string(),
-            "id_search".to_string(),
             "title".to_string(),
-            "title_search".to_string(),
-            tags.clone(),
             tags.clone(),
             "description".to_string(),
-            "description_search".to_string(),
-            references.clone(),
             references.clone(),
             why_not.clone(),
         );
@@ -496,14 +579,9 @@ mod tests {
         let why_not: Vec<String> = vec!["why_not1".to_string(), "why_not2".to_string()];
         let template = Template::new(
             "id".to_string(),
-            "id_search".to_string(),
             "title".to_string(),
-            "title_search".to_string(),
-            tags.clone(),
             tags.clone(),
             "description".to_string(),
-            "description_search".to_string(),
-            references.clone(),
             references.clone(),
             why_not.clone(),
         );
@@ -536,4 +614,29 @@ mod tests {
             expected_output
         );
     }
+
+    // Test the explain_to_string function with the score of the searched fields.
+    #[test]
+    fn test_explain_to_string() {
+        let mut template = Template::new(
+            "id".to_string(),
+            "title".to_string(),
+            vec![],
+            "description".to_string(),
+            vec![],
+            vec![],
+        );
+        template.set_score(Score {
+            fields: vec![
+                FieldScore { field: Field::Title, score: 1.5, weight: 3.0 },
+                FieldScore { field: Field::Tags, score: 0.5, weight: 1.0 },
+            ],
+            total: 1.25,
+        });
+
+        let expected_output: &str =
+            "    score 1.2500\n      title          1.5000  weight 3\n      tags           0.5000  weight 1";
+        assert_eq!(template.explain_to_string(), expected_output);
+        assert_eq!(template.distance(), 1.25);
+    }
 }